@@ -0,0 +1,18 @@
+use chess_rs::search::{bench, DEFAULT_BENCH_DEPTH};
+
+// cargo run --release --example bench -- [depth]
+fn main() {
+    let depth = std::env::args()
+        .nth(1)
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(DEFAULT_BENCH_DEPTH);
+
+    let total = bench(depth, |fen, result| {
+        println!("{fen}: {} nodes, score {}", result.nodes, result.score);
+    });
+
+    println!("===========================");
+    println!("Total time (ms) : {}", total.elapsed.as_millis());
+    println!("Nodes searched  : {}", total.nodes);
+    println!("Nodes/second    : {}", total.nps());
+}
@@ -1,33 +1,28 @@
 use chess_rs::{
     chess_create, chess_get_latest_move, chess_move, chess_remove_figure_at, chess_run,
-    ChessWrapper, Move,
+    ChessStatus, ChessWrapper, FigureType, Move, MoveResult,
 };
 use std::ptr::null_mut;
 
 fn main() {
     let mut chess: ChessWrapper = ChessWrapper(null_mut());
 
-    chess_create(&mut chess.0);
+    assert_eq!(chess_create(&mut chess.0), ChessStatus::Ok);
 
     std::thread::spawn(move || {
         let chess = chess;
         std::thread::sleep(std::time::Duration::from_millis(2000));
 
-        chess_move(chess, 1, 1, 2, 1);
+        let mut result = MoveResult::Illegal;
+        let status = chess_move(chess, 6, 1, 5, 1, FigureType::Empty as u32, &mut result);
+        assert_eq!((status, result), (ChessStatus::Ok, MoveResult::Ok));
 
-        let mv = chess_get_latest_move(chess.0);
-        assert_eq!(
-            mv,
-            Move {
-                start_row: 1,
-                start_col: 1,
-                end_row: 2,
-                end_col: 1
-            }
-        );
+        let mut mv = Move::new((0, 0), (0, 0));
+        assert_eq!(chess_get_latest_move(chess.0, &mut mv), ChessStatus::Ok);
+        assert_eq!(mv, Move::new((6, 1), (5, 1)));
 
-        chess_remove_figure_at(chess.0, 7, 0);
+        assert_eq!(chess_remove_figure_at(chess.0, 7, 0), ChessStatus::Ok);
     });
 
-    chess_run(ChessWrapper(chess.0));
+    assert_eq!(chess_run(ChessWrapper(chess.0)), ChessStatus::Ok);
 }
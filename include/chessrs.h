@@ -21,22 +21,60 @@ namespace chessrs {
 
     struct Move {
         size_t start_y, start_x, dest_y, dest_x;
+        FigureType promotion; // Empty if no promotion
     };
 
 
 
-    void chess_create(void **);
-    void chess_run(void *);
-    void chess_move(void *, size_t start_y, size_t start_x, size_t dest_y, size_t dest_x);
-    void chess_free(void *);
-    Team chess_get_current_team(void*);
-    void chess_set_current_team(void*, Team);
-    Move chess_get_latest_move(void*);
+    enum MoveResult {
+        Illegal=0,
+        MoveOk,
+        Check,
+        Checkmate,
+        Stalemate,
+        Draw, // fifty move rule, threefold repetition or insufficient material
+        PromotionRequired // a pawn reaches the last rank, promotion was Empty
+    };
+
+    // Returned by every function, details of a failure are given by
+    // chess_last_error_message.
+    enum ChessStatus {
+        Ok=0,
+        NullPointer,
+        OutOfRange, // a row or column outside the board or an invalid enum value
+        NoMove,
+        Panic
+    };
+
+    // Description of the last failed call on this thread or NULL, valid until the next
+    // call fails on this thread.
+    const char *chess_last_error_message();
+
+    ChessStatus chess_create(void **);
+    ChessStatus chess_run(void *);
+    // the move is made unless the result is Illegal or PromotionRequired
+    // enums are passed as uint32_t, OutOfRange for values that are not in the enum
+    ChessStatus chess_move(void *, size_t start_y, size_t start_x, size_t dest_y, size_t dest_x,
+                           uint32_t promotion /* FigureType */, MoveResult *result);
+    // freeing NULL does nothing
+    ChessStatus chess_free(void *);
+    ChessStatus chess_get_current_team(void*, Team *team);
+    ChessStatus chess_set_current_team(void*, uint32_t team /* Team */);
+    // NoMove before the first move
+    ChessStatus chess_get_latest_move(void*, Move *move);
+
 
+    ChessStatus chess_set_figure_at(void*, size_t y, size_t x, uint32_t figure /* FigureType */,
+                                    uint32_t team /* Team */);
+    ChessStatus chess_get_figure_at(void*, size_t y, size_t x, FigureType *figure);
+    ChessStatus chess_remove_figure_at(void*, size_t y, size_t x);
 
-    void chess_set_figure_at(void*, size_t y, size_t x, FigureType figure, Team team);
-    FigureType chess_get_figure_at(void*, size_t y, size_t x);
-    void chess_remove_figure_at(void*, size_t y, size_t x);
+    ChessStatus chess_engine_create(void **);
+    ChessStatus chess_engine_free(void *);
+    // 0 is the weakest level, 20 plays at full strength
+    ChessStatus chess_engine_set_skill_level(void *engine, uint8_t level);
+    // NoMove if the position has no legal move
+    ChessStatus chess_engine_best_move(void *engine, void *chess, uint64_t movetime_ms, Move *move);
 
 
 
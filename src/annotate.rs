@@ -0,0 +1,247 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    pgn::{Game, PgnError},
+    search::{mate_in, SearchLimits, Searcher, DEFAULT_HASH_MB, MATE},
+    Chess, Move, Team,
+};
+
+/// Losses in centipawns from which a move counts as an inaccuracy, a mistake or a
+/// blunder.
+pub const INACCURACY_LOSS: i32 = 50;
+pub const MISTAKE_LOSS: i32 = 100;
+pub const BLUNDER_LOSS: i32 = 300;
+
+/// Scores are capped before comparing them, so that picking the slower of two winning
+/// lines is no blunder.
+const SCORE_CAP: i32 = 1000;
+
+pub const DEFAULT_ANNOTATION_DEPTH: usize = 6;
+
+/// How much a move gives away compared to the engine's choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoveClass {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass {
+    /// Class of a move that is not the best one and loses `loss` centipawns.
+    pub fn from_loss(loss: i32) -> MoveClass {
+        match loss {
+            _ if loss >= BLUNDER_LOSS => MoveClass::Blunder,
+            _ if loss >= MISTAKE_LOSS => MoveClass::Mistake,
+            _ if loss >= INACCURACY_LOSS => MoveClass::Inaccuracy,
+            _ => MoveClass::Good,
+        }
+    }
+
+    /// The glyph "?!", "?" or "??" for bad moves.
+    pub fn nag(&self) -> Option<u8> {
+        match self {
+            MoveClass::Inaccuracy => Some(6),
+            MoveClass::Mistake => Some(2),
+            MoveClass::Blunder => Some(4),
+            MoveClass::Best | MoveClass::Good => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MoveClass::Best => "Best",
+            MoveClass::Good => "Good",
+            MoveClass::Inaccuracy => "Inaccuracy",
+            MoveClass::Mistake => "Mistake",
+            MoveClass::Blunder => "Blunder",
+        }
+    }
+}
+
+impl Display for MoveClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The verdict on one move of a game. Scores are in centipawns from the view of the
+/// player making the move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveAnalysis {
+    pub mv: Move,
+    /// The engine's choice, `None` only if the search found no move in time.
+    pub best_move: Option<Move>,
+    pub best_score: i32,
+    /// Score after the played move.
+    pub score: i32,
+    pub class: MoveClass,
+}
+
+impl MoveAnalysis {
+    /// Centipawns given away by the played move, never negative.
+    pub fn loss(&self) -> i32 {
+        (self.best_score.clamp(-SCORE_CAP, SCORE_CAP) - self.score.clamp(-SCORE_CAP, SCORE_CAP))
+            .max(0)
+    }
+}
+
+/// Score as written in annotations, in pawns or as "#n" from white's view.
+pub fn format_score(score: i32, player: Team) -> String {
+    let score = match player {
+        Team::White => score,
+        Team::Black => -score,
+    };
+    match mate_in(score) {
+        Some(moves) => format!("#{moves}"),
+        None => format!("{:+.2}", score as f64 / 100.),
+    }
+}
+
+/// Runs the engine over every position of a game to find the moves that lose ground.
+#[derive(Debug)]
+pub struct Annotator {
+    searcher: Searcher,
+    pub limits: SearchLimits,
+}
+
+impl Default for Annotator {
+    fn default() -> Self {
+        Annotator::new(SearchLimits::depth(DEFAULT_ANNOTATION_DEPTH))
+    }
+}
+
+impl Annotator {
+    pub fn new(limits: SearchLimits) -> Self {
+        Annotator {
+            searcher: Searcher::new(DEFAULT_HASH_MB),
+            limits,
+        }
+    }
+
+    /// Score and best move of the player to move, game ends are scored without a search.
+    fn search(&mut self, chess: &mut Chess, limits: SearchLimits) -> (i32, Option<Move>) {
+        if chess.legal_moves().is_empty() {
+            let score = if chess.check_check(chess.player).is_some() {
+                -MATE
+            } else {
+                0
+            };
+            return (score, None);
+        }
+        let result = self.searcher.search(chess, limits);
+        (result.score, result.best_move)
+    }
+
+    /// Classifies every move of the main line. A move other than the engine's choice is
+    /// scored by searching the position it leads to one ply shallower, so that both
+    /// scores look equally far ahead.
+    pub fn analyse(&mut self, game: &Game) -> Result<Vec<MoveAnalysis>, PgnError> {
+        let (mut chess, moves) = game.replay()?;
+        self.searcher.new_game();
+        let reply_limits = SearchLimits {
+            depth: self
+                .limits
+                .depth
+                .map(|depth| depth.saturating_sub(1).max(1)),
+            ..self.limits
+        };
+
+        let mut analysis = Vec::with_capacity(moves.len());
+        for mv in moves {
+            let (best_score, best_move) = self.search(&mut chess, self.limits);
+            chess.make_move(mv);
+            let (score, class) = if best_move == Some(mv) {
+                (best_score, MoveClass::Best)
+            } else {
+                let score = -self.search(&mut chess, reply_limits).0;
+                (score, MoveClass::Good)
+            };
+            let mut entry = MoveAnalysis {
+                mv,
+                best_move,
+                best_score,
+                score,
+                class,
+            };
+            if class != MoveClass::Best {
+                entry.class = MoveClass::from_loss(entry.loss());
+            }
+            analysis.push(entry);
+        }
+        Ok(analysis)
+    }
+
+    /// A copy of `game` with "?!", "?" or "??" and the better move attached to every
+    /// inaccuracy, mistake and blunder. Existing annotations are kept.
+    pub fn annotate(&mut self, game: &Game) -> Result<Game, PgnError> {
+        let analysis = self.analyse(game)?;
+        let mut annotated = game.clone();
+        let mut chess = game.start_position()?;
+
+        for (pgn_move, analysis) in annotated.moves.iter_mut().zip(analysis) {
+            if let (Some(nag), Some(best_move)) = (analysis.class.nag(), analysis.best_move) {
+                if !pgn_move.nags.contains(&nag) {
+                    pgn_move.nags.push(nag);
+                }
+                let comment = format!(
+                    "{} ({}). {} was best ({}).",
+                    analysis.class,
+                    format_score(analysis.score, chess.player),
+                    chess.to_san(best_move),
+                    format_score(analysis.best_score, chess.player),
+                );
+                pgn_move.comment = Some(match pgn_move.comment.take() {
+                    Some(old) => format!("{old} {comment}"),
+                    None => comment,
+                });
+            }
+            chess.make_move(analysis.mv);
+        }
+        annotated.set_tag("Annotator", "chess-rs");
+        Ok(annotated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        annotate::{Annotator, MoveClass},
+        pgn::parse_pgn,
+        search::SearchLimits,
+    };
+
+    #[test]
+    fn test_move_classes() {
+        assert_eq!(MoveClass::from_loss(0), MoveClass::Good);
+        assert_eq!(MoveClass::from_loss(60), MoveClass::Inaccuracy);
+        assert_eq!(MoveClass::from_loss(150), MoveClass::Mistake);
+        assert_eq!(MoveClass::from_loss(2000), MoveClass::Blunder);
+        assert_eq!(MoveClass::Blunder.nag(), Some(4));
+        assert_eq!(MoveClass::Good.nag(), None);
+    }
+
+    #[test]
+    fn test_finds_blunder() {
+        let games = parse_pgn("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0").unwrap();
+        let mut annotator = Annotator::new(SearchLimits::depth(3));
+
+        let analysis = annotator.analyse(&games[0]).unwrap();
+        assert_eq!(analysis.len(), 7);
+        assert_eq!(analysis[5].class, MoveClass::Blunder);
+        assert_eq!(analysis[6].class, MoveClass::Best);
+
+        let annotated = annotator.annotate(&games[0]).unwrap();
+        let blunder = &annotated.moves[5];
+        assert_eq!(blunder.nags, [4]);
+        assert!(blunder
+            .comment
+            .as_ref()
+            .unwrap()
+            .starts_with("Blunder (#1)."));
+        let pgn = annotated.to_pgn();
+        assert!(pgn.contains("Nf6 $4") && pgn.contains("{Blunder (#1)."));
+        assert_eq!(annotated.tag("Annotator"), Some("chess-rs"));
+    }
+}
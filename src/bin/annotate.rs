@@ -0,0 +1,55 @@
+use std::{process::exit, str::FromStr, time::Duration};
+
+use chess_rs::{
+    annotate::{Annotator, DEFAULT_ANNOTATION_DEPTH},
+    pgn::parse_pgn,
+    search::SearchLimits,
+};
+
+const USAGE: &str = "usage: annotate <games.pgn> [--depth N] [--movetime MS]";
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Marks the inaccuracies, mistakes and blunders of every game and prints the annotated
+/// PGN.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some(pgn_path) = args.first() else {
+        fail(USAGE);
+    };
+
+    let mut limits = SearchLimits::depth(DEFAULT_ANNOTATION_DEPTH);
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--depth" => limits.depth = Some(parse(option, value)),
+            "--movetime" => {
+                limits.depth = None;
+                limits.movetime = Some(Duration::from_millis(parse(option, value)));
+            }
+            _ => fail(USAGE),
+        }
+    }
+
+    let text = std::fs::read_to_string(pgn_path)
+        .unwrap_or_else(|err| fail(format!("cannot read {pgn_path}: {err}")));
+    let games = parse_pgn(&text).unwrap_or_else(|err| fail(err));
+
+    let mut annotator = Annotator::new(limits);
+    for (idx, game) in games.iter().enumerate() {
+        match annotator.annotate(game) {
+            Ok(annotated) => println!("{annotated}"),
+            Err(err) => eprintln!("skipping game {}: {err}", idx + 1),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::{process::exit, str::FromStr};
+
+use chess_rs::{book::BookBuilder, pgn::parse_pgn};
+
+const USAGE: &str =
+    "usage: makebook <games.pgn> <book.bin> [--min-games N] [--min-score S] [--depth PLIES]";
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Builds a Polyglot opening book from a PGN collection.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (Some(pgn_path), Some(book_path)) = (args.first(), args.get(1)) else {
+        fail(USAGE);
+    };
+
+    let mut builder = BookBuilder::new();
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--min-games" => builder.min_games = parse(option, value),
+            "--min-score" => builder.min_score = parse(option, value),
+            "--depth" => builder.max_depth = parse(option, value),
+            _ => fail(USAGE),
+        }
+    }
+
+    let text = std::fs::read_to_string(pgn_path)
+        .unwrap_or_else(|err| fail(format!("cannot read {pgn_path}: {err}")));
+    let games = parse_pgn(&text).unwrap_or_else(|err| fail(err));
+
+    let mut used = 0;
+    for (idx, game) in games.iter().enumerate() {
+        match builder.add_game(game) {
+            Ok(true) => used += 1,
+            Ok(false) => {}
+            Err(err) => eprintln!("skipping game {}: {err}", idx + 1),
+        }
+    }
+
+    let book = builder.build();
+    book.save(book_path)
+        .unwrap_or_else(|err| fail(format!("cannot write {book_path}: {err}")));
+    println!(
+        "{used} of {} games, {} book entries written to {book_path}",
+        games.len(),
+        book.len()
+    );
+}
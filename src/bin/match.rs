@@ -0,0 +1,153 @@
+use std::{fs::File, io::Write, iter, process::exit, str::FromStr, sync::Arc, time::Duration};
+
+use chess_rs::{
+    match_runner::{load_openings, EngineConfig, Match, Sprt, SprtStatus, TimeControl},
+    syzygy::Tablebase,
+};
+
+const USAGE: &str = "usage: match --engine cmd=PATH [name=NAME] [arg=ARG]... [tc=TC] \
+     [option.NAME=VALUE]... --engine ... [--tc TC] [--games N] [--openings FILE] \
+     [--syzygy PATH] [--pgn FILE] [--margin MS] [--event NAME] \
+     [--sprt [elo0=E] [elo1=E] [alpha=A] [beta=B]]\n\
+     time controls: 40/60+0.6, 10+0.1, st=0.5, depth=8, nodes=10000";
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Plays a match between two UCI engines and writes the games as PGN.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut engines: Vec<(EngineConfig, Option<TimeControl>)> = vec![];
+    let mut time_control = TimeControl::default();
+    let mut openings = None;
+    let mut syzygy = None;
+    let mut pgn_path = None;
+    let mut games = None;
+    let mut margin = None;
+    let mut event = None;
+    let mut sprt = None;
+
+    let mut options = args.iter().peekable();
+    while let Some(option) = options.next() {
+        if option == "--engine" {
+            let settings = iter::from_fn(|| options.next_if(|arg| !arg.starts_with("--")));
+            let engine = EngineConfig::parse_settings(settings)
+                .unwrap_or_else(|err| fail(format!("{err}\n{USAGE}")));
+            engines.push(engine);
+            continue;
+        }
+        if option == "--sprt" {
+            let mut test = Sprt::default();
+            while let Some(setting) = options.next_if(|arg| !arg.starts_with("--")) {
+                let (key, value) = setting.split_once('=').unwrap_or_else(|| fail(USAGE));
+                match key {
+                    "elo0" => test.elo0 = parse(key, value),
+                    "elo1" => test.elo1 = parse(key, value),
+                    "alpha" => test.alpha = parse(key, value),
+                    "beta" => test.beta = parse(key, value),
+                    _ => fail(USAGE),
+                }
+            }
+            sprt = Some(test);
+            continue;
+        }
+
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--tc" => time_control = parse(option, value),
+            "--games" => games = Some(parse(option, value)),
+            "--openings" => openings = Some(value),
+            "--syzygy" => syzygy = Some(value),
+            "--pgn" => pgn_path = Some(value),
+            "--margin" => margin = Some(Duration::from_millis(parse(option, value))),
+            "--event" => event = Some(value.clone()),
+            _ => fail(USAGE),
+        }
+    }
+
+    let [first, second] = <[_; 2]>::try_from(engines).unwrap_or_else(|_| fail(USAGE));
+    let configure = |(mut config, tc): (EngineConfig, Option<TimeControl>)| {
+        config.time_control = tc.unwrap_or(time_control);
+        config
+    };
+    let mut runner = Match::new(configure(first), configure(second));
+    if let Some(path) = openings {
+        runner.openings = load_openings(path)
+            .unwrap_or_else(|err| fail(format!("cannot read openings {path}: {err}")));
+    }
+    runner.games = games.unwrap_or(2 * runner.openings.len().max(1));
+    if let Some(path) = syzygy {
+        let tablebase = Tablebase::open(path)
+            .unwrap_or_else(|err| fail(format!("cannot open tablebases {path}: {err}")));
+        runner.settings.tablebase = Some(Arc::new(tablebase));
+    }
+    if let Some(margin) = margin {
+        runner.settings.time_margin = margin;
+    }
+    if let Some(event) = event {
+        runner.settings.event = event;
+    }
+    let mut pgn = pgn_path.map(|path| {
+        File::create(path).unwrap_or_else(|err| fail(format!("cannot write {path}: {err}")))
+    });
+
+    let score = runner
+        .run(|played, score| {
+            let game = &played.game;
+            println!(
+                "Game {} ({} vs {}): {} {{{}}}, score {score}",
+                played.number + 1,
+                game.tag("White").unwrap_or("?"),
+                game.tag("Black").unwrap_or("?"),
+                game.result,
+                played.termination,
+            );
+            if let Some(pgn) = &mut pgn {
+                writeln!(pgn, "{}", game.to_pgn())
+                    .unwrap_or_else(|err| fail(format!("cannot write PGN: {err}")));
+            }
+            let Some(sprt) = sprt else {
+                return true;
+            };
+            let status = sprt.status(score);
+            if status != SprtStatus::Continue {
+                println!("SPRT: {status}");
+            }
+            status == SprtStatus::Continue
+        })
+        .unwrap_or_else(|err| fail(format!("match aborted: {err}")));
+
+    let names = runner.engines.each_ref().map(|config| {
+        if config.name.is_empty() {
+            config.command.as_str()
+        } else {
+            config.name.as_str()
+        }
+    });
+    println!(
+        "{} vs {}: {} wins, {} draws, {} losses, {score}",
+        names[0], names[1], score.wins, score.draws, score.losses
+    );
+    if let Some(elo) = score.elo() {
+        println!("{elo}");
+    }
+    if let Some(sprt) = sprt {
+        println!(
+            "SPRT ({} to {} Elo): LLR {:.2} ({:.2}, {:.2}), {}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.llr(&score),
+            sprt.lower_bound(),
+            sprt.upper_bound(),
+            sprt.status(&score)
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::{process::exit, str::FromStr};
+
+use chess_rs::{
+    pgn::parse_pgn,
+    puzzle::{PuzzleMiner, CSV_HEADER},
+    search::SearchLimits,
+};
+
+const USAGE: &str = "usage: puzzles <games.pgn> [--depth N] [--min-ply N]";
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Prints the tactical puzzles found in a PGN collection as CSV.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some(pgn_path) = args.first() else {
+        fail(USAGE);
+    };
+
+    let mut miner = PuzzleMiner::default();
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--depth" => miner.limits = SearchLimits::depth(parse(option, value)),
+            "--min-ply" => miner.min_ply = parse(option, value),
+            _ => fail(USAGE),
+        }
+    }
+
+    let text = std::fs::read_to_string(pgn_path)
+        .unwrap_or_else(|err| fail(format!("cannot read {pgn_path}: {err}")));
+    let games = parse_pgn(&text).unwrap_or_else(|err| fail(err));
+
+    println!("{CSV_HEADER}");
+    for (idx, game) in games.iter().enumerate() {
+        match miner.mine(game) {
+            Ok(puzzles) => puzzles
+                .iter()
+                .for_each(|puzzle| println!("{}", puzzle.to_csv())),
+            Err(err) => eprintln!("skipping game {}: {err}", idx + 1),
+        }
+    }
+}
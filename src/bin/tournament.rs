@@ -0,0 +1,126 @@
+use std::{fs::File, io::Write, iter, process::exit, str::FromStr, sync::Arc};
+
+use chess_rs::{
+    match_runner::{load_openings, EngineConfig, GameSettings, TimeControl},
+    syzygy::Tablebase,
+    tournament::{Format, Tournament},
+};
+
+const USAGE: &str = "usage: tournament --engine cmd=PATH [name=NAME] [arg=ARG]... [tc=TC] \
+     [option.NAME=VALUE]... --engine ... [--format rr|drr|swiss] [--rounds N] [--tc TC] \
+     [--openings FILE] [--syzygy PATH] [--pgn FILE] [--crosstable FILE] [--event NAME]";
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Plays a tournament between UCI engines and writes the games and the crosstable.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut engines: Vec<(EngineConfig, Option<TimeControl>)> = vec![];
+    let mut time_control = TimeControl::default();
+    let mut format = "rr";
+    let mut rounds = None;
+    let mut openings = None;
+    let mut syzygy = None;
+    let mut pgn_path = None;
+    let mut crosstable_path = None;
+    let mut event = "Engine tournament".to_string();
+
+    let mut options = args.iter().peekable();
+    while let Some(option) = options.next() {
+        if option == "--engine" {
+            let settings = iter::from_fn(|| options.next_if(|arg| !arg.starts_with("--")));
+            let engine = EngineConfig::parse_settings(settings)
+                .unwrap_or_else(|err| fail(format!("{err}\n{USAGE}")));
+            engines.push(engine);
+            continue;
+        }
+
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--format" => format = value,
+            "--rounds" => rounds = Some(parse(option, value)),
+            "--tc" => time_control = parse(option, value),
+            "--openings" => openings = Some(value),
+            "--syzygy" => syzygy = Some(value),
+            "--pgn" => pgn_path = Some(value),
+            "--crosstable" => crosstable_path = Some(value),
+            "--event" => event = value.clone(),
+            _ => fail(USAGE),
+        }
+    }
+    if engines.len() < 2 {
+        fail(USAGE);
+    }
+
+    let engines = engines
+        .into_iter()
+        .map(|(mut config, tc)| {
+            config.time_control = tc.unwrap_or(time_control);
+            config
+        })
+        .collect::<Vec<_>>();
+    let names = engines
+        .iter()
+        .map(|config| match config.name.is_empty() {
+            true => config.command.clone(),
+            false => config.name.clone(),
+        })
+        .collect::<Vec<_>>();
+    let format = match format {
+        "rr" => Format::RoundRobin,
+        "drr" => Format::DoubleRoundRobin,
+        "swiss" => Format::Swiss {
+            rounds: rounds.unwrap_or_else(|| fail("--rounds is needed for a Swiss tournament")),
+        },
+        _ => fail(USAGE),
+    };
+    let mut tournament = Tournament::new(event, names, format);
+
+    let openings = match openings {
+        Some(path) => load_openings(path)
+            .unwrap_or_else(|err| fail(format!("cannot read openings {path}: {err}"))),
+        None => vec![],
+    };
+    let mut settings = GameSettings::default();
+    if let Some(path) = syzygy {
+        let tablebase = Tablebase::open(path)
+            .unwrap_or_else(|err| fail(format!("cannot open tablebases {path}: {err}")));
+        settings.tablebase = Some(Arc::new(tablebase));
+    }
+    let mut pgn = pgn_path.map(|path| {
+        File::create(path).unwrap_or_else(|err| fail(format!("cannot write {path}: {err}")))
+    });
+
+    println!("{} ({format})", tournament.name);
+    tournament
+        .play_engines(&engines, &openings, &settings, |round, game| {
+            println!(
+                "Round {}: {} - {} {}",
+                round + 1,
+                game.tag("White").unwrap_or("?"),
+                game.tag("Black").unwrap_or("?"),
+                game.result
+            );
+            if let Some(pgn) = &mut pgn {
+                writeln!(pgn, "{}", game.to_pgn())
+                    .unwrap_or_else(|err| fail(format!("cannot write PGN: {err}")));
+            }
+        })
+        .unwrap_or_else(|err| fail(format!("tournament aborted: {err}")));
+
+    let crosstable = tournament.crosstable();
+    println!("\n{crosstable}");
+    if let Some(path) = crosstable_path {
+        std::fs::write(path, crosstable)
+            .unwrap_or_else(|err| fail(format!("cannot write {path}: {err}")));
+    }
+}
@@ -0,0 +1,102 @@
+use std::{fs, process::exit, str::FromStr};
+
+use chess_rs::{
+    eval::EvalParams,
+    pgn::parse_pgn,
+    tune::{parse_epd, quiet_positions, Tuner, DEFAULT_SKIP_PLIES},
+};
+
+const USAGE: &str = "usage: tune <positions.epd|games.pgn> <params.txt|params.rs> [--epochs N] \
+[--threads N] [--rate CP] [--params FILE] [--skip-plies N]";
+
+/// Epochs between two reports and saves of the current parameters.
+const REPORT_INTERVAL: usize = 50;
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    exit(1)
+}
+
+fn parse<T: FromStr>(option: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(format!("invalid value {value} for {option}")))
+}
+
+/// Writes a Rust constant for `.rs` outputs and a parameter file for anything else.
+fn save(params: &EvalParams, path: &str) {
+    let text = if path.ends_with(".rs") {
+        format!(
+            "use chess_rs::eval::EvalParams;\n\n{}",
+            params.to_rust_source("TUNED_PARAMS")
+        )
+    } else {
+        params.to_config()
+    };
+    fs::write(path, text).unwrap_or_else(|err| fail(format!("cannot write {path}: {err}")));
+}
+
+/// Tunes the evaluation parameters on result-annotated positions with Texel's method.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (Some(data_path), Some(out_path)) = (args.first(), args.get(1)) else {
+        fail(USAGE);
+    };
+
+    let mut epochs = 1000;
+    let mut threads = None;
+    let mut rate = None;
+    let mut params = EvalParams::default();
+    let mut skip_plies = DEFAULT_SKIP_PLIES;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().unwrap_or_else(|| fail(USAGE));
+        match option.as_str() {
+            "--epochs" => epochs = parse(option, value),
+            "--threads" => threads = Some(parse(option, value)),
+            "--rate" => rate = Some(parse(option, value)),
+            "--params" => {
+                params = EvalParams::load(value)
+                    .unwrap_or_else(|err| fail(format!("cannot read {value}: {err}")))
+            }
+            "--skip-plies" => skip_plies = parse(option, value),
+            _ => fail(USAGE),
+        }
+    }
+
+    let text = fs::read_to_string(data_path)
+        .unwrap_or_else(|err| fail(format!("cannot read {data_path}: {err}")));
+    let positions = if data_path.ends_with(".pgn") {
+        let games = parse_pgn(&text).unwrap_or_else(|err| fail(err));
+        quiet_positions(&games, skip_plies)
+    } else {
+        parse_epd(&text).unwrap_or_else(|err| fail(err))
+    };
+    if positions.is_empty() {
+        fail("no positions to tune with");
+    }
+
+    let mut tuner = Tuner::new(positions, &params);
+    if let Some(threads) = threads {
+        tuner.threads = threads;
+    }
+    if let Some(rate) = rate {
+        tuner.learning_rate = rate;
+    }
+    let k = tuner.optimize_k();
+    println!(
+        "{} positions, {} threads, K = {k:.4}",
+        tuner.len(),
+        tuner.threads
+    );
+    println!("start error {:.6}", tuner.error());
+
+    for epoch in 1..=epochs {
+        let error = tuner.epoch();
+        if epoch % REPORT_INTERVAL == 0 || epoch == epochs {
+            println!("epoch {epoch}: error {error:.6}");
+            save(&tuner.params(), out_path);
+        }
+    }
+    println!("parameters written to {out_path}");
+}
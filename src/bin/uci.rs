@@ -0,0 +1,17 @@
+use std::io::BufRead;
+
+use chess_rs::uci::Uci;
+
+/// UCI engine over stdin and stdout, for chess GUIs and test harnesses.
+fn main() {
+    let mut uci = Uci::new(|line| println!("{line}"));
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !uci.handle(&line) {
+            break;
+        }
+    }
+}
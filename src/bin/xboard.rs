@@ -0,0 +1,17 @@
+use std::io::BufRead;
+
+use chess_rs::xboard::XBoard;
+
+/// Chess Engine Communication Protocol engine over stdin and stdout.
+fn main() {
+    let mut xboard = XBoard::new(|line| println!("{line}"));
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !xboard.handle(&line) {
+            break;
+        }
+    }
+}
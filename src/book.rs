@@ -0,0 +1,424 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::{
+    pgn::{Game, PgnError},
+    rng::Rng,
+    Chess, FigureType, Move, Team, COLS,
+};
+
+/// Size of one entry in a Polyglot `.bin` file.
+const ENTRY_SIZE: usize = 16;
+
+/// Promotion figures in the order of the Polyglot move encoding.
+const PROMOTIONS: [FigureType; 5] = [
+    FigureType::Empty,
+    FigureType::Knight,
+    FigureType::Bishop,
+    FigureType::Rook,
+    FigureType::Queen,
+];
+
+pub const DEFAULT_BOOK_DEPTH: usize = 20;
+
+/// One entry of a Polyglot book, all fields are stored big endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    /// Zobrist key of the position, see `zobrist.rs`.
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn read(bytes: &[u8]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.key.to_be_bytes());
+        bytes.extend(self.mv.to_be_bytes());
+        bytes.extend(self.weight.to_be_bytes());
+        bytes.extend(self.learn.to_be_bytes());
+    }
+}
+
+/// Polyglot squares count from a1 = 0 to h8 = 63.
+fn square((row, col): (usize, usize)) -> u16 {
+    ((7 - row) * COLS + col) as u16
+}
+
+fn field(square: u16) -> (usize, usize) {
+    (7 - (square / 8) as usize, (square % 8) as usize)
+}
+
+/// Encodes a move of `chess`. Polyglot writes castling as the king capturing its rook.
+pub fn encode_move(chess: &Chess, mv: Move) -> u16 {
+    let is_king =
+        matches!(chess.field(mv.from()).figure, Some(figure) if figure.figure == FigureType::King);
+    let mut to = mv.to();
+    if is_king && mv.start_col.abs_diff(mv.end_col) == 2 {
+        to.1 = if mv.end_col > mv.start_col {
+            COLS - 1
+        } else {
+            0
+        };
+    }
+    let promotion = PROMOTIONS
+        .iter()
+        .position(|figure| *figure == mv.promotion)
+        .unwrap_or(0) as u16;
+    promotion << 12 | square(mv.from()) << 6 | square(to)
+}
+
+/// Decodes a Polyglot move in the position `chess`. The king capturing its own rook is
+/// turned into the king moving two fields.
+pub fn decode_move(chess: &Chess, bits: u16) -> Move {
+    let from = field(bits >> 6 & 63);
+    let mut to = field(bits & 63);
+    let promotion = PROMOTIONS[(bits >> 12 & 7) as usize % PROMOTIONS.len()];
+
+    let castles = match (chess.field(from).figure, chess.field(to).figure) {
+        (Some(king), Some(rook)) => {
+            king.figure == FigureType::King
+                && rook.figure == FigureType::Rook
+                && king.team == rook.team
+                && from.1 == 4
+        }
+        _ => false,
+    };
+    if castles {
+        to.1 = if to.1 > from.1 { 6 } else { 2 };
+    }
+    Move::with_promotion(from, to, promotion)
+}
+
+/// How a move is chosen if the book knows several for a position.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    /// Random, with a probability proportional to the weight of the move.
+    #[default]
+    WeightedRandom,
+    /// Always the move with the highest weight.
+    BestWeight,
+}
+
+/// A Polyglot opening book held in memory. The entries are sorted by key, so all moves
+/// of a position are found with a binary search.
+#[derive(Debug, Clone)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+    pub selection: BookSelection,
+    /// Plies of the game after which the book is no longer used.
+    pub max_depth: usize,
+    rng: Rng,
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        Book {
+            entries: vec![],
+            selection: BookSelection::default(),
+            max_depth: DEFAULT_BOOK_DEPTH,
+            rng: Rng::default(),
+        }
+    }
+}
+
+impl Book {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Book> {
+        let mut book = Book::default();
+        book.load(path)?;
+        Ok(book)
+    }
+
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Book {
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight)));
+        Book {
+            entries,
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the entries with the ones of the `.bin` file at `path`, the selection
+    /// settings are kept. Returns the number of entries.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let bytes = fs::read(path)?;
+        if bytes.len() % ENTRY_SIZE != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the size of a Polyglot book is a multiple of 16 bytes",
+            ));
+        }
+        let mut entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::read)
+            .collect::<Vec<_>>();
+        // books are sorted by key already, but not every tool writes them that way
+        entries.sort_by_key(|entry| entry.key);
+        self.entries = entries;
+        Ok(self.entries.len())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            entry.write(&mut bytes);
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    /// Fixes the random numbers of `WeightedRandom`, mostly for tests.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// All legal book moves of the position with their weights.
+    pub fn moves(&self, chess: &mut Chess) -> Vec<(Move, u16)> {
+        let start = self.entries.partition_point(|entry| entry.key < chess.hash);
+        let entries = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == chess.hash)
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return vec![];
+        }
+
+        let legal = chess.legal_moves();
+        entries
+            .into_iter()
+            .map(|entry| (decode_move(chess, entry.mv), entry.weight))
+            .filter(|(mv, _)| legal.contains(mv))
+            .collect()
+    }
+
+    /// Number of plies played since the start of the game.
+    fn game_ply(chess: &Chess) -> usize {
+        (chess.fullmove_number.max(1) - 1) * 2 + (chess.player == Team::Black) as usize
+    }
+
+    /// A book move for the position, `None` if the book has no move for it or the game is
+    /// past the book depth.
+    pub fn pick(&mut self, chess: &mut Chess) -> Option<Move> {
+        if Book::game_ply(chess) >= self.max_depth {
+            return None;
+        }
+        let moves = self.moves(chess);
+
+        match self.selection {
+            BookSelection::BestWeight => moves
+                .iter()
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(mv, _)| *mv),
+            BookSelection::WeightedRandom => {
+                let total = moves.iter().map(|(_, weight)| *weight as u64).sum::<u64>();
+                if total == 0 {
+                    return moves.first().map(|(mv, _)| *mv);
+                }
+                let mut pick = self.rng.below(total);
+                moves.into_iter().find_map(|(mv, weight)| {
+                    if pick < weight as u64 {
+                        return Some(mv);
+                    }
+                    pick -= weight as u64;
+                    None
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct MoveStats {
+    games: usize,
+    /// Points of the side that played the move, in half points.
+    half_points: usize,
+}
+
+/// Builds a Polyglot book from PGN games. Every move played in the first `max_depth`
+/// plies is counted, moves played in fewer than `min_games` games or scoring below
+/// `min_score` for the side that played them are left out.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    pub min_games: usize,
+    /// Share of the points, from 0.0 to 1.0.
+    pub min_score: f64,
+    pub max_depth: usize,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        BookBuilder::new()
+    }
+}
+
+impl BookBuilder {
+    pub fn new() -> Self {
+        BookBuilder {
+            min_games: 1,
+            min_score: 0.,
+            max_depth: DEFAULT_BOOK_DEPTH,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Counts the moves of `game`. Games without a result are skipped, returns whether
+    /// the game was used.
+    pub fn add_game(&mut self, game: &Game) -> Result<bool, PgnError> {
+        if game.result.score(Team::White).is_none() {
+            return Ok(false);
+        }
+        let (mut chess, moves) = game.replay()?;
+        for mv in moves.into_iter().take(self.max_depth) {
+            let half_points = (game.result.score(chess.player).unwrap_or_default() * 2.) as usize;
+            let stats = self
+                .stats
+                .entry((chess.hash, encode_move(&chess, mv)))
+                .or_default();
+            stats.games += 1;
+            stats.half_points += half_points;
+            chess.make_move(mv);
+        }
+        Ok(true)
+    }
+
+    /// Weights are the points of the move in half points, as Polyglot's own book maker
+    /// counts them, but at least 1 so that every move passing the filters stays playable.
+    pub fn build(&self) -> Book {
+        let entries = self
+            .stats
+            .iter()
+            .filter(|(_, stats)| {
+                stats.games >= self.min_games
+                    && stats.half_points as f64 / (2 * stats.games) as f64 >= self.min_score
+            })
+            .map(|((key, mv), stats)| BookEntry {
+                key: *key,
+                mv: *mv,
+                weight: stats.half_points.clamp(1, u16::MAX as usize) as u16,
+                learn: 0,
+            })
+            .collect();
+        Book::from_entries(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        book::{decode_move, encode_move, Book, BookBuilder, BookSelection},
+        pgn::parse_pgn,
+        Chess, Move,
+    };
+
+    const GAMES: &str =
+        "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 0-1\n\n1. e4 e5 1/2-1/2\n\n1. d4 d5 0-1\n\n1. c4 *\n";
+
+    fn build(min_games: usize, min_score: f64) -> Book {
+        let mut builder = BookBuilder {
+            min_games,
+            min_score,
+            ..Default::default()
+        };
+        for game in parse_pgn(GAMES).unwrap() {
+            builder.add_game(&game).unwrap();
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_move_encoding() {
+        let chess = Chess::new(None);
+        let e2e4 = Move::new((6, 4), (4, 4));
+        // from e2 = 12, to e4 = 28
+        assert_eq!(encode_move(&chess, e2e4), 12 << 6 | 28);
+        assert_eq!(decode_move(&chess, 12 << 6 | 28), e2e4);
+
+        let chess = Chess::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = Move::new((7, 4), (7, 6));
+        assert_eq!(encode_move(&chess, castle), 4 << 6 | 7);
+        assert_eq!(decode_move(&chess, 4 << 6 | 7), castle);
+        assert_eq!(decode_move(&chess, 4 << 6), Move::new((7, 4), (7, 2)));
+    }
+
+    #[test]
+    fn test_start_position_key() {
+        // the key every Polyglot book uses for the start position
+        assert_eq!(Chess::new(None).hash, 0x463b96181691fc9c);
+    }
+
+    #[test]
+    fn test_build_and_probe() {
+        let book = build(1, 0.);
+        let mut chess = Chess::new(None);
+        let moves = book.moves(&mut chess);
+        // the unfinished game is skipped
+        assert_eq!(moves.len(), 2);
+        // e4 scored 1 + 0 + 0.5 points for white
+        assert!(moves.contains(&(Move::new((6, 4), (4, 4)), 3)));
+        assert!(moves.contains(&(Move::new((6, 3), (4, 3)), 1)));
+
+        let book = build(2, 0.);
+        assert_eq!(book.moves(&mut chess).len(), 1);
+
+        let book = build(1, 0.6);
+        chess.make_move(Move::new((6, 4), (4, 4)));
+        // c5 won its only game, e5 scored half a point out of 2 for black
+        assert_eq!(book.moves(&mut chess), vec![(Move::new((1, 2), (3, 2)), 2)]);
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut book = build(1, 0.);
+        book.selection = BookSelection::BestWeight;
+        let mut chess = Chess::new(None);
+        assert_eq!(book.pick(&mut chess), Some(Move::new((6, 4), (4, 4))));
+
+        book.selection = BookSelection::WeightedRandom;
+        book.set_seed(7);
+        for _ in 0..20 {
+            let mv = book.pick(&mut chess).unwrap();
+            assert!([Move::new((6, 4), (4, 4)), Move::new((6, 3), (4, 3))].contains(&mv));
+        }
+
+        book.max_depth = 0;
+        assert_eq!(book.pick(&mut chess), None);
+        assert_eq!(
+            book.pick(&mut Chess::from_fen("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let book = build(1, 0.);
+        let path = std::env::temp_dir().join(format!("chess-rs-book-{}.bin", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = Book::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), book.len());
+        assert_eq!(loaded.entries(), book.entries());
+    }
+}
@@ -1,10 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use macroquad::prelude::*;
 
 use crate::{
-    figure::Figure, Field, FigureType, Selection, Team, COLS, ROWS, ROWS_MAX_IDX, SIZE, X_DIST,
-    Y_DIST,
+    fen::{field_name, figure_to_char},
+    figure::Figure,
+    Field, FigureType, Selection, Team, Undo, COLS, ROWS, ROWS_MAX_IDX, SIZE, X_DIST, Y_DIST,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,9 +18,56 @@ pub struct Move {
     pub start_col: usize,
     pub end_row: usize,
     pub end_col: usize,
+    /// The figure a pawn turns into, `FigureType::Empty` for every other move.
+    pub promotion: FigureType,
+}
+
+impl Move {
+    pub fn new((start_row, start_col): (usize, usize), (end_row, end_col): (usize, usize)) -> Move {
+        Move {
+            start_row,
+            start_col,
+            end_row,
+            end_col,
+            promotion: FigureType::Empty,
+        }
+    }
+
+    pub fn with_promotion(from: (usize, usize), to: (usize, usize), promotion: FigureType) -> Move {
+        Move {
+            promotion,
+            ..Move::new(from, to)
+        }
+    }
+
+    #[inline]
+    pub fn from(&self) -> (usize, usize) {
+        (self.start_row, self.start_col)
+    }
+
+    #[inline]
+    pub fn to(&self) -> (usize, usize) {
+        (self.end_row, self.end_col)
+    }
+}
+
+/// Long algebraic notation as used by UCI, e.g. "e2e4" or "e7e8q".
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", field_name(self.from()), field_name(self.to()))?;
+        if self.promotion != FigureType::Empty {
+            let promotion = Figure {
+                figure: self.promotion,
+                team: Team::Black,
+                first_move: false,
+            };
+            write!(f, "{}", figure_to_char(promotion))?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Chess {
     pub fields: [[Field; COLS]; ROWS],
     pub sprites: Option<[Texture2D; 12]>,
@@ -26,6 +77,14 @@ pub struct Chess {
     pub state: State,
 
     pub en_passants: HashMap<(usize, usize, Team), (usize, usize)>,
+
+    /// Polyglot compatible zobrist key of the position, see `zobrist.rs`.
+    pub hash: u64,
+    /// Undo information of every move applied with `make_move`.
+    pub history: Vec<Undo>,
+    /// Half moves since the last capture or pawn move.
+    pub halfmove_clock: usize,
+    pub fullmove_number: usize,
 }
 
 unsafe impl Send for Chess {}
@@ -62,7 +121,8 @@ pub fn spawn_figure(fields: &mut [[Field; COLS]; ROWS], col: usize, figure_type:
 }
 
 impl Chess {
-    pub fn new(sprites: Option<[Texture2D; 12]>) -> Chess {
+    /// A board without any figures on it.
+    pub fn empty(sprites: Option<[Texture2D; 12]>) -> Chess {
         let mut fields = [[Field::default(); COLS]; ROWS];
 
         for row in 0..ROWS {
@@ -77,17 +137,38 @@ impl Chess {
             }
         }
 
-        spawn_figure(&mut fields, 0, FigureType::Rook);
-        spawn_figure(&mut fields, ROWS - 1, FigureType::Rook);
+        let mut chess = Chess {
+            fields,
+            selection: Default::default(),
+            sprites,
+            player: Team::White,
+            latest_move: None,
+            state: State::Select,
+            en_passants: Default::default(),
+            hash: 0,
+            history: Vec::new(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        };
+        chess.refresh_hash();
+        chess
+    }
+
+    pub fn new(sprites: Option<[Texture2D; 12]>) -> Chess {
+        let mut chess = Chess::empty(sprites);
+        let fields = &mut chess.fields;
+
+        spawn_figure(fields, 0, FigureType::Rook);
+        spawn_figure(fields, ROWS - 1, FigureType::Rook);
 
-        spawn_figure(&mut fields, 1, FigureType::Knight);
-        spawn_figure(&mut fields, ROWS - 2, FigureType::Knight);
+        spawn_figure(fields, 1, FigureType::Knight);
+        spawn_figure(fields, ROWS - 2, FigureType::Knight);
 
-        spawn_figure(&mut fields, 2, FigureType::Bishop);
-        spawn_figure(&mut fields, ROWS - 3, FigureType::Bishop);
+        spawn_figure(fields, 2, FigureType::Bishop);
+        spawn_figure(fields, ROWS - 3, FigureType::Bishop);
 
-        spawn_figure(&mut fields, 3, FigureType::Queen);
-        spawn_figure(&mut fields, 4, FigureType::King);
+        spawn_figure(fields, 3, FigureType::Queen);
+        spawn_figure(fields, 4, FigureType::King);
 
         for col in 0..COLS {
             let field = &mut fields[1][col];
@@ -105,15 +186,8 @@ impl Chess {
             })
         }
 
-        Chess {
-            fields,
-            selection: Default::default(),
-            sprites,
-            player: Team::White,
-            latest_move: None,
-            state: State::Select,
-            en_passants: Default::default(),
-        }
+        chess.refresh_hash();
+        chess
     }
 
     pub fn draw(&self) {
@@ -196,12 +270,7 @@ impl Chess {
     }
 
     pub fn move_figure(&mut self, from: (usize, usize), (row_to, col_to): (usize, usize)) {
-        self.latest_move = Some(Move {
-            start_row: from.0,
-            start_col: from.1,
-            end_row: row_to,
-            end_col: col_to,
-        });
+        self.latest_move = Some(Move::new(from, (row_to, col_to)));
 
         let mut figure = &mut self.field_mut(from).figure;
         if let Some(figure) = &mut figure {
@@ -233,6 +302,7 @@ impl Chess {
             }
 
             self.player = !self.player;
+            self.refresh_hash();
             self.selection.unselect_field();
             return;
         }
@@ -276,6 +346,7 @@ impl Chess {
 
             self.selection.unselect_field();
             self.player = !self.player;
+            self.refresh_hash();
             return;
         }
 
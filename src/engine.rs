@@ -0,0 +1,382 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{runtime::Runtime, task::JoinHandle};
+
+use crate::{
+    book::{Book, BookSelection},
+    eval::{EvalParams, Handcrafted},
+    search::{PvLine, SearchLimits, SearchResult, Searcher, Skill, DEFAULT_HASH_MB},
+    syzygy::Tablebase,
+    Chess,
+};
+
+/// Owns the current game position and runs searches on a background task, so that
+/// protocol frontends can keep reading commands while the engine thinks.
+pub struct Engine {
+    pub chess: Chess,
+    searcher: Arc<Mutex<Searcher>>,
+    stop: Arc<AtomicBool>,
+    /// Number of search threads, a single thread searches deterministically.
+    threads: usize,
+    book: Book,
+    /// Whether moves are taken from the book before searching.
+    own_book: bool,
+    runtime: Runtime,
+    search: Option<JoinHandle<()>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let searcher = Searcher::new(DEFAULT_HASH_MB);
+        Engine {
+            chess: Chess::new(None),
+            stop: searcher.stop_flag(),
+            searcher: Arc::new(Mutex::new(searcher)),
+            threads: 1,
+            book: Book::default(),
+            own_book: false,
+            runtime: Runtime::new().expect("Failed to create the search runtime."),
+            search: None,
+        }
+    }
+
+    pub fn new_game(&mut self) {
+        self.stop();
+        self.chess = Chess::new(None);
+        self.searcher.lock().unwrap().new_game();
+    }
+
+    /// Resizes the transposition table, which also clears it.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.stop();
+        self.searcher.lock().unwrap().set_hash_size(size_mb.max(1));
+    }
+
+    pub fn set_move_overhead(&mut self, overhead: Duration) {
+        self.stop();
+        self.searcher.lock().unwrap().move_overhead = overhead;
+    }
+
+    /// Number of best moves searched with exact scores, for analysis. Costs strength if
+    /// more than one.
+    pub fn set_multipv(&mut self, multipv: usize) {
+        self.stop();
+        self.searcher.lock().unwrap().multipv = multipv.max(1);
+    }
+
+    /// Weakens the engine, `Skill::default()` plays at full strength.
+    pub fn set_skill(&mut self, skill: Skill) {
+        self.stop();
+        self.searcher.lock().unwrap().skill = skill;
+    }
+
+    /// Helper threads run a lazy SMP search, sharing only the transposition table
+    /// with the main thread.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.stop();
+        self.threads = threads.max(1);
+    }
+
+    /// Uses the Syzygy tables in `path`, several directories are separated like in the
+    /// `PATH` variable. An empty path disables the tablebase. Returns the number of tables
+    /// found.
+    pub fn set_syzygy_path(&mut self, path: &str) -> io::Result<usize> {
+        self.stop();
+        let mut searcher = self.searcher.lock().unwrap();
+        if path.trim().is_empty() || path == "<empty>" {
+            searcher.tablebase = None;
+            return Ok(0);
+        }
+        let tablebase = Tablebase::open(path)?;
+        let tables = tablebase.len();
+        searcher.tablebase = Some(Arc::new(tablebase));
+        Ok(tables)
+    }
+
+    /// Loads the Polyglot book at `path`, an empty path unloads the book. Returns the
+    /// number of book entries.
+    pub fn set_book_file(&mut self, path: &str) -> io::Result<usize> {
+        self.stop();
+        if path.trim().is_empty() || path == "<empty>" {
+            self.book.clear();
+            return Ok(0);
+        }
+        self.book.load(path.trim())
+    }
+
+    /// Evaluates with the parameters in `path`, written by the tuner. An empty path goes
+    /// back to the default parameters.
+    pub fn set_eval_file(&mut self, path: &str) -> io::Result<()> {
+        self.stop();
+        let params = if path.trim().is_empty() || path == "<empty>" {
+            EvalParams::default()
+        } else {
+            EvalParams::load(path.trim())?
+        };
+        *self.searcher.lock().unwrap().evaluator_mut() = Handcrafted::new(params);
+        Ok(())
+    }
+
+    pub fn set_own_book(&mut self, own_book: bool) {
+        self.stop();
+        self.own_book = own_book;
+    }
+
+    /// Plies of the game in which the book is used.
+    pub fn set_book_depth(&mut self, depth: usize) {
+        self.stop();
+        self.book.max_depth = depth;
+    }
+
+    pub fn set_book_selection(&mut self, selection: BookSelection) {
+        self.stop();
+        self.book.selection = selection;
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.stop();
+        self.searcher.lock().unwrap().tt.clear();
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search
+            .as_ref()
+            .is_some_and(|search| !search.is_finished())
+    }
+
+    /// Starts searching the current position in the background. `info` is called after
+    /// every completed iteration and `done` with the final result. Infinite searches
+    /// only finish after `stop` was called. Book moves are played without a search,
+    /// except when analysing infinitely.
+    pub fn go(
+        &mut self,
+        limits: SearchLimits,
+        mut info: impl FnMut(&SearchResult) + Send + 'static,
+        done: impl FnOnce(SearchResult) + Send + 'static,
+    ) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+
+        if self.own_book && !limits.infinite {
+            if let Some(mv) = self.book.pick(&mut self.chess) {
+                done(SearchResult {
+                    best_move: Some(mv),
+                    pv: vec![mv],
+                    ..Default::default()
+                });
+                return;
+            }
+        }
+
+        let helper_stop = Arc::new(AtomicBool::new(false));
+        let helpers = {
+            let searcher = self.searcher.lock().unwrap();
+            // helpers search until the main thread is done
+            let helper_limits = SearchLimits {
+                depth: limits.depth,
+                infinite: true,
+                ..Default::default()
+            };
+            (1..self.threads)
+                .map(|id| {
+                    let mut helper = searcher.helper(id, helper_stop.clone());
+                    let mut chess = self.chess.clone();
+                    self.runtime.spawn_blocking(move || {
+                        helper.search(&mut chess, helper_limits);
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut chess = self.chess.clone();
+        let searcher = self.searcher.clone();
+        let stop = self.stop.clone();
+
+        self.search = Some(self.runtime.spawn_blocking(move || {
+            let result = searcher
+                .lock()
+                .unwrap()
+                .search_with_info(&mut chess, limits, &mut info);
+
+            helper_stop.store(true, Ordering::Relaxed);
+            for helper in helpers {
+                while !helper.is_finished() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+
+            while limits.infinite && !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            done(result);
+        }));
+    }
+
+    /// Searches the current position and blocks until the search is done.
+    pub fn search(&mut self, limits: SearchLimits) -> SearchResult {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.go(
+            limits,
+            |_| {},
+            move |result| {
+                sender.send(result).ok();
+            },
+        );
+        let result = receiver
+            .recv()
+            .expect("The search task should send a result.");
+        self.wait();
+        result
+    }
+
+    /// Searches the current position and returns the best moves ranked from best to
+    /// worst, as many as set with `set_multipv`.
+    pub fn analyse(&mut self, limits: SearchLimits) -> Vec<PvLine> {
+        self.search(limits).lines
+    }
+
+    /// Signals the running search to stop and waits until it has finished.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Waits until the running search has finished on its own.
+    pub fn wait(&mut self) {
+        // polling instead of `block_on` keeps this usable from within async code
+        if let Some(search) = self.search.take() {
+            while !search.is_finished() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::channel,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        book::{BookBuilder, BookSelection},
+        engine::Engine,
+        pgn::parse_pgn,
+        search::SearchLimits,
+        Chess, Move,
+    };
+
+    #[test]
+    fn test_search_in_background() {
+        let mut engine = Engine::new();
+        let result = engine.search(SearchLimits::depth(2));
+        assert!(result.best_move.is_some());
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    fn test_stop_infinite_search() {
+        let mut engine = Engine::new();
+        let (sender, receiver) = channel();
+        engine.go(
+            SearchLimits {
+                infinite: true,
+                ..Default::default()
+            },
+            |_| {},
+            move |result| sender.send(result).unwrap(),
+        );
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(engine.is_searching());
+
+        let start = Instant::now();
+        engine.stop();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(receiver.recv().unwrap().best_move.is_some());
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let mut engine = Engine::new();
+        engine.set_threads(4);
+        engine.chess = Chess::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = engine.search(SearchLimits::depth(3));
+        assert_eq!(result.best_move, Some(Move::new((7, 0), (0, 0))));
+
+        // helpers are stopped together with the main thread
+        engine.chess = Chess::new(None);
+        engine.go(
+            SearchLimits {
+                infinite: true,
+                ..Default::default()
+            },
+            |_| {},
+            |_| {},
+        );
+        std::thread::sleep(Duration::from_millis(50));
+        let start = Instant::now();
+        engine.stop();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn test_analyse() {
+        let mut engine = Engine::new();
+        engine.set_multipv(3);
+        engine.chess = Chess::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+
+        let lines = engine.analyse(SearchLimits::depth(2));
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].best_move(), Some(Move::new((7, 0), (0, 0))));
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn test_book_move() {
+        let mut builder = BookBuilder::new();
+        for game in parse_pgn("1. a3 e5 1-0\n").unwrap() {
+            builder.add_game(&game).unwrap();
+        }
+        let path =
+            std::env::temp_dir().join(format!("chess-rs-engine-book-{}.bin", std::process::id()));
+        builder.build().save(&path).unwrap();
+
+        let mut engine = Engine::new();
+        assert_eq!(engine.set_book_file(path.to_str().unwrap()).unwrap(), 2);
+        std::fs::remove_file(&path).unwrap();
+        engine.set_book_selection(BookSelection::BestWeight);
+
+        // the book is only used with OwnBook
+        let a3 = Some(Move::new((6, 0), (5, 0)));
+        assert_ne!(engine.search(SearchLimits::depth(1)).best_move, a3);
+        engine.set_own_book(true);
+        let result = engine.search(SearchLimits::depth(1));
+        assert_eq!(result.best_move, a3);
+        assert_eq!(result.depth, 0);
+
+        engine.set_book_depth(0);
+        assert_ne!(engine.search(SearchLimits::depth(1)).best_move, a3);
+    }
+}
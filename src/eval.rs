@@ -0,0 +1,466 @@
+use std::{
+    fmt::{Display, Formatter, Write},
+    fs, io,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{Chess, FigureType, Move, Team, ROWS_MAX_IDX};
+
+// Piece values and tables from the "Simplified Evaluation Function".
+// The tables are written from white's view, the first row is the eighth rank.
+
+#[rustfmt::skip]
+const PAWN_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [ 50,  50,  50,  50,  50,  50,  50,  50],
+    [ 10,  10,  20,  30,  30,  20,  10,  10],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [[i32; 8]; 8] = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  0,   0,   0,   5,   5,   0,   0,   0],
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [[i32; 8]; 8] = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [[i32; 8]; 8] = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [ 20,  20,   0,   0,   0,   0,  20,  20],
+    [ 20,  30,  10,   0,   0,  10,  30,  20],
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [[i32; 8]; 8] = [
+    [-50, -40, -30, -20, -20, -30, -40, -50],
+    [-30, -20, -10,   0,   0, -10, -20, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -30,   0,   0,   0,   0, -30, -30],
+    [-50, -30, -30, -30, -30, -30, -30, -50],
+];
+
+/// Game phase weights, the start position has a phase of `MAX_PHASE`.
+pub const MAX_PHASE: i32 = 24;
+
+pub fn figure_value(figure: FigureType) -> i32 {
+    match figure {
+        FigureType::Pawn => 100,
+        FigureType::Knight => 320,
+        FigureType::Bishop => 330,
+        FigureType::Rook => 500,
+        FigureType::Queen => 900,
+        FigureType::King | FigureType::Empty => 0,
+    }
+}
+
+fn phase_weight(figure: FigureType) -> i32 {
+    match figure {
+        FigureType::Knight | FigureType::Bishop => 1,
+        FigureType::Rook => 2,
+        FigureType::Queen => 4,
+        _ => 0,
+    }
+}
+
+/// Figure types with evaluation parameters, in the order of `EvalParams::values` and
+/// `EvalParams::tables`.
+pub const EVAL_FIGURES: [FigureType; 6] = [
+    FigureType::Pawn,
+    FigureType::Knight,
+    FigureType::Bishop,
+    FigureType::Rook,
+    FigureType::Queen,
+    FigureType::King,
+];
+
+/// A piece square table from white's view, the first row is the eighth rank.
+pub type Table = [[i32; 8]; 8];
+
+/// Everything the handcrafted evaluation can be tuned with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    /// Material of every figure type in the middlegame and the endgame.
+    pub values: [[i32; 2]; 6],
+    /// Piece square tables for the middlegame and the endgame.
+    pub tables: [[Table; 2]; 6],
+}
+
+/// The hand-picked parameters `evaluate` uses.
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    values: [
+        [100, 100],
+        [320, 320],
+        [330, 330],
+        [500, 500],
+        [900, 900],
+        [0, 0],
+    ],
+    tables: [
+        [PAWN_TABLE, PAWN_TABLE],
+        [KNIGHT_TABLE, KNIGHT_TABLE],
+        [BISHOP_TABLE, BISHOP_TABLE],
+        [ROOK_TABLE, ROOK_TABLE],
+        [QUEEN_TABLE, QUEEN_TABLE],
+        [KING_TABLE, KING_ENDGAME_TABLE],
+    ],
+};
+
+/// Index of `figure` in `EVAL_FIGURES`.
+pub fn eval_index(figure: FigureType) -> Option<usize> {
+    EVAL_FIGURES.iter().position(|other| *other == figure)
+}
+
+/// Game phase of the position, `MAX_PHASE` with all pieces on the board and zero
+/// with pawns and kings only.
+pub fn game_phase(chess: &Chess) -> i32 {
+    chess
+        .fields
+        .iter()
+        .flatten()
+        .filter_map(|field| field.figure)
+        .map(|figure| phase_weight(figure.figure))
+        .sum::<i32>()
+        .min(MAX_PHASE)
+}
+
+/// Table row of a figure standing in `row`, the tables are written from white's view.
+pub fn table_row(team: Team, row: usize) -> usize {
+    match team {
+        Team::White => row,
+        Team::Black => ROWS_MAX_IDX - row,
+    }
+}
+
+fn figure_name(idx: usize) -> &'static str {
+    ["pawn", "knight", "bishop", "rook", "queen", "king"][idx]
+}
+
+/// The line of a parameter file that could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamsError {
+    pub line: usize,
+}
+
+impl Display for ParamsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid evaluation parameters in line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+impl EvalParams {
+    /// Static evaluation in centipawns from the view of the player to move.
+    pub fn evaluate(&self, chess: &Chess) -> i32 {
+        let mut middlegame = 0;
+        let mut endgame = 0;
+        let mut phase = 0;
+
+        for field in chess.fields.iter().flatten() {
+            let Some(figure) = field.figure else {
+                continue;
+            };
+            let Some(idx) = eval_index(figure.figure) else {
+                continue;
+            };
+            let (row, col) = field.idxs;
+            let row = table_row(figure.team, row);
+            let sign = match figure.team {
+                Team::White => 1,
+                Team::Black => -1,
+            };
+
+            let [mg_value, eg_value] = self.values[idx];
+            let [mg_table, eg_table] = &self.tables[idx];
+            middlegame += sign * (mg_value + mg_table[row][col]);
+            endgame += sign * (eg_value + eg_table[row][col]);
+            phase += phase_weight(figure.figure);
+        }
+
+        let phase = phase.min(MAX_PHASE);
+        let score = (middlegame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+
+        match chess.player {
+            Team::White => score,
+            Team::Black => -score,
+        }
+    }
+
+    /// Reads parameters written by `to_config`. Parameters missing in the file keep
+    /// their default value.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<EvalParams> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_config())
+    }
+
+    /// The parameters as a text file with one line per value pair and table, e.g.
+    /// "knight value 320 320" or "king endgame" followed by 64 numbers.
+    pub fn to_config(&self) -> String {
+        let mut out = String::from("# chess-rs evaluation parameters\n");
+        for idx in 0..EVAL_FIGURES.len() {
+            let name = figure_name(idx);
+            let [mg, eg] = self.values[idx];
+            writeln!(out, "{name} value {mg} {eg}").unwrap();
+            for (phase, table) in ["middlegame", "endgame"].iter().zip(&self.tables[idx]) {
+                write!(out, "{name} {phase}").unwrap();
+                for value in table.iter().flatten() {
+                    write!(out, " {value}").unwrap();
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// The parameters as a Rust constant named `name`, to be compiled into the engine.
+    pub fn to_rust_source(&self, name: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "#[rustfmt::skip]").unwrap();
+        writeln!(out, "pub const {name}: EvalParams = EvalParams {{").unwrap();
+        writeln!(out, "    values: {:?},", self.values).unwrap();
+        writeln!(out, "    tables: [").unwrap();
+        for (idx, tables) in self.tables.iter().enumerate() {
+            writeln!(out, "        // {}", figure_name(idx)).unwrap();
+            writeln!(out, "        [").unwrap();
+            for table in tables {
+                writeln!(out, "            [").unwrap();
+                for row in table {
+                    let row = row.map(|value| format!("{value:4}")).join(",");
+                    writeln!(out, "                [{row}],").unwrap();
+                }
+                writeln!(out, "            ],").unwrap();
+            }
+            writeln!(out, "        ],").unwrap();
+        }
+        writeln!(out, "    ],").unwrap();
+        writeln!(out, "}};").unwrap();
+        out
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = ParamsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = DEFAULT_PARAMS;
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = ParamsError { line: line_idx + 1 };
+            let mut parts = line.split_whitespace();
+            let (Some(name), Some(kind)) = (parts.next(), parts.next()) else {
+                return Err(err);
+            };
+            let idx = (0..EVAL_FIGURES.len())
+                .find(|idx| figure_name(*idx) == name)
+                .ok_or(err)?;
+            let numbers = parts
+                .map(|number| number.parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| err)?;
+
+            match (kind, numbers.len()) {
+                ("value", 2) => params.values[idx] = [numbers[0], numbers[1]],
+                ("middlegame" | "endgame", 64) => {
+                    let table = &mut params.tables[idx][usize::from(kind == "endgame")];
+                    for (field, value) in table.iter_mut().flatten().zip(numbers) {
+                        *field = value;
+                    }
+                }
+                _ => return Err(err),
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Static evaluation in centipawns from the view of the player to move.
+pub fn evaluate(chess: &Chess) -> i32 {
+    DEFAULT_PARAMS.evaluate(chess)
+}
+
+/// A static evaluation the search can be run with, see `Searcher::with_evaluator`.
+///
+/// Evaluators that keep incremental state are told about every move the search makes
+/// and takes back. Moves made only to test their legality are not reported, they are
+/// taken back before the next evaluation.
+pub trait Evaluator: Send {
+    /// Score in centipawns from the view of the player to move. Mate scores are left
+    /// to the search.
+    fn evaluate(&mut self, chess: &Chess) -> i32;
+
+    /// Called with the root position before every search.
+    fn reset(&mut self, _chess: &Chess) {}
+
+    /// Called after `mv` was applied, `chess.history` holds what it moved and captured.
+    fn on_make_move(&mut self, _chess: &Chess, _mv: Move) {}
+
+    /// Called right before `mv` is taken back.
+    fn on_unmake_move(&mut self, _chess: &Chess, _mv: Move) {}
+}
+
+/// Material and piece square tables, tapered between middlegame and endgame. By default
+/// this is the evaluation `evaluate` computes, tuned parameters can be swapped in.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Handcrafted {
+    params: Arc<EvalParams>,
+}
+
+impl Handcrafted {
+    pub fn new(params: EvalParams) -> Self {
+        Handcrafted {
+            params: Arc::new(params),
+        }
+    }
+
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+}
+
+impl Evaluator for Handcrafted {
+    fn evaluate(&mut self, chess: &Chess) -> i32 {
+        self.params.evaluate(chess)
+    }
+}
+
+/// Counts material only, mostly useful as a baseline for other evaluations.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Material;
+
+impl Evaluator for Material {
+    fn evaluate(&mut self, chess: &Chess) -> i32 {
+        chess
+            .fields
+            .iter()
+            .flatten()
+            .filter_map(|field| field.figure)
+            .map(|figure| {
+                let value = figure_value(figure.figure);
+                if figure.team == chess.player {
+                    value
+                } else {
+                    -value
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eval::{evaluate, EvalParams, Evaluator, Handcrafted, Material, ParamsError},
+        Chess,
+    };
+
+    #[test]
+    fn test_start_position_is_balanced() {
+        assert_eq!(evaluate(&Chess::new(None)), 0);
+    }
+
+    #[test]
+    fn test_material_advantage() {
+        let chess = Chess::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(evaluate(&chess) > 800);
+
+        let chess = Chess::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(evaluate(&chess) < -800);
+    }
+
+    #[test]
+    fn test_material_evaluator() {
+        let chess = Chess::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(Material.evaluate(&chess), -900);
+        assert_eq!(Material.evaluate(&Chess::new(None)), 0);
+    }
+
+    #[test]
+    fn test_params_config() {
+        let mut params = EvalParams::default();
+        params.values[1] = [300, 280];
+        params.tables[5][1][7][6] = -7;
+        assert_eq!(params.to_config().parse::<EvalParams>(), Ok(params.clone()));
+
+        // missing parameters keep their default
+        let partial = "pawn value 90 120\n".parse::<EvalParams>().unwrap();
+        assert_eq!(partial.values[0], [90, 120]);
+        assert_eq!(partial.tables, EvalParams::default().tables);
+        assert_eq!(
+            "# header\npawn middlegame 1 2 3".parse::<EvalParams>(),
+            Err(ParamsError { line: 2 })
+        );
+
+        let chess = Chess::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap();
+        assert!(Handcrafted::new(params).evaluate(&chess) < evaluate(&chess));
+    }
+}
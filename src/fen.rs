@@ -0,0 +1,296 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{Chess, Figure, FigureType, Team, COLS, ROWS};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    InvalidBoard,
+    InvalidTeam,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidMoveNumber,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            FenError::InvalidBoard => "invalid piece placement",
+            FenError::InvalidTeam => "invalid side to move",
+            FenError::InvalidCastling => "invalid castling rights",
+            FenError::InvalidEnPassant => "invalid en passant field",
+            FenError::InvalidMoveNumber => "invalid move number",
+        };
+        write!(f, "{msg}")
+    }
+}
+
+impl std::error::Error for FenError {}
+
+pub fn figure_from_char(c: char) -> Option<Figure> {
+    let figure = match c.to_ascii_lowercase() {
+        'p' => FigureType::Pawn,
+        'n' => FigureType::Knight,
+        'b' => FigureType::Bishop,
+        'r' => FigureType::Rook,
+        'q' => FigureType::Queen,
+        'k' => FigureType::King,
+        _ => return None,
+    };
+    let team = if c.is_ascii_uppercase() {
+        Team::White
+    } else {
+        Team::Black
+    };
+    Some(Figure {
+        figure,
+        team,
+        first_move: false,
+    })
+}
+
+pub fn figure_to_char(figure: Figure) -> char {
+    let c = match figure.figure {
+        FigureType::Pawn => 'p',
+        FigureType::Knight => 'n',
+        FigureType::Bishop => 'b',
+        FigureType::Rook => 'r',
+        FigureType::Queen => 'q',
+        FigureType::King => 'k',
+        FigureType::Empty => '?',
+    };
+    match figure.team {
+        Team::White => c.to_ascii_uppercase(),
+        Team::Black => c,
+    }
+}
+
+/// Converts a field like "e4" into (row, col) indices.
+pub fn parse_field(field: &str) -> Option<(usize, usize)> {
+    let mut chars = field.chars();
+    let col = (chars.next()? as usize).checked_sub('a' as usize)?;
+    let rank = (chars.next()? as usize).checked_sub('1' as usize)?;
+    if chars.next().is_some() || col >= COLS || rank >= ROWS {
+        return None;
+    }
+    Some((ROWS - 1 - rank, col))
+}
+
+pub fn field_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, ROWS - row)
+}
+
+impl Chess {
+    pub fn from_fen(fen: &str) -> Result<Chess, FenError> {
+        let mut parts = fen.split_whitespace();
+        let mut chess = Chess::empty(None);
+
+        let board = parts.next().ok_or(FenError::InvalidBoard)?;
+        let rows = board.split('/').collect::<Vec<_>>();
+        if rows.len() != ROWS {
+            return Err(FenError::InvalidBoard);
+        }
+        for (row, figures) in rows.into_iter().enumerate() {
+            let mut col = 0;
+            for c in figures.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    col += empty as usize;
+                    continue;
+                }
+                if col >= COLS {
+                    return Err(FenError::InvalidBoard);
+                }
+                let mut figure = figure_from_char(c).ok_or(FenError::InvalidBoard)?;
+                figure.first_move = figure.figure == FigureType::Pawn
+                    && matches!((figure.team, row), (Team::White, 6) | (Team::Black, 1));
+                chess.fields[row][col].figure = Some(figure);
+                col += 1;
+            }
+            if col != COLS {
+                return Err(FenError::InvalidBoard);
+            }
+        }
+
+        chess.player = match parts.next() {
+            Some("w") | None => Team::White,
+            Some("b") => Team::Black,
+            _ => return Err(FenError::InvalidTeam),
+        };
+
+        let castling = parts.next().unwrap_or("-");
+        for c in castling.chars().filter(|c| *c != '-') {
+            let (team, row, rook_col) = match c {
+                'K' => (Team::White, ROWS - 1, COLS - 1),
+                'Q' => (Team::White, ROWS - 1, 0),
+                'k' => (Team::Black, 0, COLS - 1),
+                'q' => (Team::Black, 0, 0),
+                _ => return Err(FenError::InvalidCastling),
+            };
+            // the king and the rook of the right team have to stand on their start fields
+            for (col, figure_type) in [(4, FigureType::King), (rook_col, FigureType::Rook)] {
+                match chess.fields[row][col].figure.as_mut() {
+                    Some(figure) if figure.figure == figure_type && figure.team == team => {
+                        figure.first_move = true;
+                    }
+                    _ => return Err(FenError::InvalidCastling),
+                }
+            }
+        }
+
+        let en_passant = parts.next().unwrap_or("-");
+        if en_passant != "-" {
+            let (row, col) = parse_field(en_passant).ok_or(FenError::InvalidEnPassant)?;
+            let pawn_row = match (chess.player, row) {
+                (Team::White, 2) => 3,
+                (Team::Black, 5) => 4,
+                _ => return Err(FenError::InvalidEnPassant),
+            };
+            for capture_col in [col.wrapping_sub(1), col + 1] {
+                if capture_col >= COLS {
+                    continue;
+                }
+                if let Some(Figure {
+                    figure: FigureType::Pawn,
+                    team,
+                    ..
+                }) = chess.fields[pawn_row][capture_col].figure
+                {
+                    if team == chess.player {
+                        chess
+                            .en_passants
+                            .insert((pawn_row, capture_col, team), (pawn_row, col));
+                    }
+                }
+            }
+        }
+
+        if let Some(halfmove_clock) = parts.next() {
+            chess.halfmove_clock = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidMoveNumber)?;
+        }
+        if let Some(fullmove_number) = parts.next() {
+            chess.fullmove_number = fullmove_number
+                .parse()
+                .map_err(|_| FenError::InvalidMoveNumber)?;
+        }
+
+        chess.refresh_hash();
+        Ok(chess)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for (row, fields) in self.fields.iter().enumerate() {
+            let mut empty = 0;
+            for field in fields {
+                match field.figure {
+                    Some(figure) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(figure_to_char(figure));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if row != ROWS - 1 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.player {
+            Team::White => " w ",
+            Team::Black => " b ",
+        });
+
+        let castling = self
+            .castling_rights()
+            .into_iter()
+            .zip(['K', 'Q', 'k', 'q'])
+            .filter_map(|(right, c)| right.then_some(c))
+            .collect::<String>();
+        if castling.is_empty() {
+            fen.push('-');
+        } else {
+            fen.push_str(&castling);
+        }
+
+        match self.en_passant_col() {
+            Some(col) => {
+                let row = match self.player {
+                    Team::White => 2,
+                    Team::Black => 5,
+                };
+                fen.push(' ');
+                fen.push_str(&field_name((row, col)));
+            }
+            None => fen.push_str(" -"),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fen::{FenError, START_FEN},
+        Chess, Move,
+    };
+
+    #[test]
+    fn test_start_fen() {
+        let chess = Chess::from_fen(START_FEN).unwrap();
+        assert_eq!(chess.hash, Chess::new(None).hash);
+        assert_eq!(Chess::new(None).to_fen(), START_FEN);
+    }
+
+    #[test]
+    fn test_fen_roundtrip() {
+        let mut chess = Chess::new(None);
+        for mv in [
+            Move::new((6, 4), (4, 4)),
+            Move::new((1, 2), (3, 2)),
+            Move::new((4, 4), (3, 4)),
+            Move::new((1, 3), (3, 3)),
+        ] {
+            chess.make_move(mv);
+        }
+        let fen = chess.to_fen();
+        assert_eq!(
+            fen,
+            "rnbqkbnr/pp2pppp/8/2ppP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"
+        );
+        assert_eq!(Chess::from_fen(&fen).unwrap().hash, chess.hash);
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        assert!(Chess::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1").is_err());
+        assert!(Chess::from_fen("8/8/8/8/8/8/8/8 x - - 0 1").is_err());
+        // castling needs the king and the rook of the team on their start fields
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K2N w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K2r w K - 0 1",
+            "5k1r/8/8/8/8/8/8/4K2R w Kk - 0 1",
+        ] {
+            assert_eq!(
+                Chess::from_fen(fen).err(),
+                Some(FenError::InvalidCastling),
+                "{fen}"
+            );
+        }
+        assert!(Chess::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CString},
+    fmt::{Display, Formatter},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{COLS, ROWS};
+
+/// Returned by every function of the C API. Details of a failure are available from
+/// `chess_last_error_message`.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum ChessStatus {
+    Ok = 0,
+    NullPointer,
+    /// A row or column outside the board or an invalid enum value.
+    OutOfRange,
+    /// No move was made yet or the engine found none.
+    NoMove,
+    /// The library panicked, the object may be left in an inconsistent state.
+    Panic,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A failed call of the C API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfiError {
+    pub status: ChessStatus,
+    pub message: String,
+}
+
+impl FfiError {
+    pub fn new(status: ChessStatus, message: impl Into<String>) -> Self {
+        FfiError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn null(argument: &str) -> Self {
+        FfiError::new(
+            ChessStatus::NullPointer,
+            format!("{argument} is a null pointer"),
+        )
+    }
+}
+
+impl Display for FfiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FfiError {}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs the body of the C API function `function`. Errors and panics become a status,
+/// their description is kept for `chess_last_error_message`.
+pub(crate) fn ffi_call(function: &str, body: impl FnOnce() -> Result<(), FfiError>) -> ChessStatus {
+    let err = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return ChessStatus::Ok,
+        Ok(Err(err)) => err,
+        Err(payload) => FfiError::new(
+            ChessStatus::Panic,
+            format!("panicked: {}", panic_message(payload.as_ref())),
+        ),
+    };
+    let message = format!("{function}: {err}").replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+    err.status
+}
+
+/// The object behind a pointer passed in by the caller, who guarantees that a non-null
+/// `ptr` is valid.
+pub(crate) fn deref_mut<'a, T>(ptr: *mut T, argument: &str) -> Result<&'a mut T, FfiError> {
+    unsafe { ptr.as_mut() }.ok_or_else(|| FfiError::null(argument))
+}
+
+/// Stores `value` in the out-parameter `out`.
+pub(crate) fn write_out<T>(out: *mut T, value: T, argument: &str) -> Result<(), FfiError> {
+    *deref_mut(out, argument)? = value;
+    Ok(())
+}
+
+/// Drops an object handed out by `Box::into_raw`, null pointers are ignored.
+pub(crate) fn free<T>(ptr: *mut T) {
+    if !ptr.is_null() {
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+}
+
+pub(crate) fn check_field(row: usize, col: usize) -> Result<(), FfiError> {
+    if row < ROWS && col < COLS {
+        Ok(())
+    } else {
+        Err(FfiError::new(
+            ChessStatus::OutOfRange,
+            format!("field ({row}, {col}) is not on the board"),
+        ))
+    }
+}
+
+/// Converts the value of a C enum passed as `argument`. Enums are taken as integers from
+/// C since an invalid value in a Rust enum is undefined behaviour.
+pub(crate) fn enum_arg<T: TryFrom<u32>>(value: u32, argument: &str) -> Result<T, FfiError> {
+    T::try_from(value).map_err(|_| {
+        FfiError::new(
+            ChessStatus::OutOfRange,
+            format!("{value} is not a valid value of {argument}"),
+        )
+    })
+}
+
+/// Description of the last failed call on this thread, or null if every call
+/// succeeded. The string stays valid until the next call fails on this thread.
+#[no_mangle]
+pub extern "C" fn chess_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use crate::ffi::{check_field, chess_last_error_message, ffi_call, ChessStatus};
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(chess_last_error_message()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_ffi_call() {
+        assert_eq!(ffi_call("ok", || Ok(())), ChessStatus::Ok);
+        assert_eq!(
+            ffi_call("range", || check_field(3, 8)),
+            ChessStatus::OutOfRange
+        );
+        assert_eq!(last_error(), "range: field (3, 8) is not on the board");
+
+        let status = ffi_call("panicking", || panic!("broken board"));
+        assert_eq!(status, ChessStatus::Panic);
+        assert_eq!(last_error(), "panicking: panicked: broken board");
+    }
+}
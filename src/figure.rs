@@ -87,6 +87,19 @@ impl Not for Team {
     }
 }
 
+/// Checks a value from the C API, out of range values are returned as the error.
+impl TryFrom<u32> for Team {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Team::White),
+            1 => Ok(Team::Black),
+            _ => Err(value),
+        }
+    }
+}
+
 impl ToString for Team {
     fn to_string(&self) -> String {
         match self {
@@ -107,3 +120,21 @@ pub enum FigureType {
     Bishop,
     Empty, // only for lib
 }
+
+/// Checks a value from the C API, out of range values are returned as the error.
+impl TryFrom<u32> for FigureType {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => FigureType::Pawn,
+            1 => FigureType::King,
+            2 => FigureType::Queen,
+            3 => FigureType::Knight,
+            4 => FigureType::Rook,
+            5 => FigureType::Bishop,
+            6 => FigureType::Empty,
+            _ => return Err(value),
+        })
+    }
+}
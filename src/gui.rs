@@ -0,0 +1,904 @@
+use std::{
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+use macroquad::prelude::*;
+
+use crate::{
+    annotate::format_score,
+    engine::Engine,
+    search::{mate_in, SearchLimits, SearchResult, Skill, MAX_SKILL_LEVEL},
+    Chess, FigureType, Move, MoveResult, Position, State, Team, COLS, ROWS, SIZE, X_DIST, Y_DIST,
+};
+
+/// The evaluation bar stands between the board and the panel.
+const EVAL_BAR_X: f32 = X_DIST * 2. + COLS as f32 * SIZE;
+const EVAL_BAR_WIDTH: f32 = SIZE / 3.;
+/// Left edge of the panel next to the board.
+const PANEL_X: f32 = EVAL_BAR_X + EVAL_BAR_WIDTH + X_DIST;
+/// The analysis is written below the board.
+const ANALYSIS_Y: f32 = Y_DIST * 2. + ROWS as f32 * SIZE;
+/// Characters of the best line per row of the analysis.
+const ANALYSIS_LINE_LEN: usize = 48;
+const BUTTON_WIDTH: f32 = 220.;
+const BUTTON_HEIGHT: f32 = 32.;
+const BUTTON_GAP: f32 = 8.;
+const ARROW_WIDTH: f32 = 8.;
+const ARROW_HEAD: f32 = 22.;
+/// Thinking time for hints and threats.
+const HINT_TIME: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerKind {
+    Human,
+    Engine,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    HumanVsHuman,
+    /// The human plays the given team, the engine the other one.
+    HumanVsEngine(Team),
+    EngineVsEngine,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::HumanVsHuman,
+        GameMode::HumanVsEngine(Team::White),
+        GameMode::HumanVsEngine(Team::Black),
+        GameMode::EngineVsEngine,
+    ];
+
+    pub fn player(&self, team: Team) -> PlayerKind {
+        match self {
+            GameMode::HumanVsHuman => PlayerKind::Human,
+            GameMode::HumanVsEngine(human) if *human == team => PlayerKind::Human,
+            GameMode::HumanVsEngine(_) | GameMode::EngineVsEngine => PlayerKind::Engine,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::HumanVsHuman => "Human vs Human",
+            GameMode::HumanVsEngine(Team::White) => "White vs Engine",
+            GameMode::HumanVsEngine(Team::Black) => "Black vs Engine",
+            GameMode::EngineVsEngine => "Engine vs Engine",
+        }
+    }
+}
+
+/// How long the engine may think for one move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Depth(usize),
+    MoveTime(Duration),
+}
+
+impl Difficulty {
+    /// From the weakest to the strongest setting.
+    pub const LEVELS: [Difficulty; 8] = [
+        Difficulty::Depth(1),
+        Difficulty::Depth(2),
+        Difficulty::Depth(3),
+        Difficulty::Depth(4),
+        Difficulty::MoveTime(Duration::from_millis(500)),
+        Difficulty::MoveTime(Duration::from_secs(1)),
+        Difficulty::MoveTime(Duration::from_secs(2)),
+        Difficulty::MoveTime(Duration::from_secs(5)),
+    ];
+
+    pub fn limits(&self) -> SearchLimits {
+        match self {
+            Difficulty::Depth(depth) => SearchLimits::depth(*depth),
+            Difficulty::MoveTime(movetime) => SearchLimits {
+                movetime: Some(*movetime),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Difficulty::Depth(depth) => format!("depth {depth}"),
+            Difficulty::MoveTime(movetime) => format!("{:.1}s per move", movetime.as_secs_f32()),
+        }
+    }
+}
+
+/// Moves the engine can suggest with an arrow on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintKind {
+    /// The best move of the player to move, shown with "H".
+    BestMove,
+    /// What the opponent would play if the player to move passed, shown with "T".
+    Threat,
+}
+
+impl HintKind {
+    fn color(&self) -> Color {
+        match self {
+            HintKind::BestMove => Color::new(0.1, 0.7, 0.2, 0.75),
+            HintKind::Threat => Color::new(0.85, 0.1, 0.1, 0.75),
+        }
+    }
+}
+
+fn field_center((row, col): (usize, usize)) -> Vec2 {
+    vec2(
+        X_DIST + (col as f32 + 0.5) * SIZE,
+        Y_DIST + (row as f32 + 0.5) * SIZE,
+    )
+}
+
+fn draw_arrow(mv: Move, color: Color) {
+    let (from, to) = (field_center(mv.from()), field_center(mv.to()));
+    let direction = (to - from).normalize();
+    let normal = vec2(-direction.y, direction.x) * ARROW_HEAD / 2.;
+    let head = to - direction * ARROW_HEAD;
+    draw_line(from.x, from.y, head.x, head.y, ARROW_WIDTH, color);
+    draw_triangle(to, head + normal, head - normal, color);
+}
+
+/// Share of the evaluation bar filled for white, the expected score for the
+/// evaluation `score` from white's view.
+pub fn white_share(score: i32) -> f32 {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => 1.,
+        Some(_) => 0.,
+        None => 1. / (1. + 10f32.powf(-score as f32 / 400.)),
+    }
+}
+
+/// Splits `text` at spaces into rows of at most `len` characters.
+fn wrap(text: &str, len: usize) -> Vec<String> {
+    let mut rows: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match rows.last_mut() {
+            Some(row) if row.len() + 1 + word.len() <= len => {
+                row.push(' ');
+                row.push_str(word);
+            }
+            _ => rows.push(word.to_string()),
+        }
+    }
+    rows
+}
+
+/// What the analysis found for one position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub hash: u64,
+    pub result: SearchResult,
+    /// The principal variation in standard algebraic notation.
+    pub line: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Checkmate(Team),
+    Stalemate,
+}
+
+/// The playable window: the board, a panel to start new games and the engine, which
+/// thinks on a background task so that the render loop never blocks.
+pub struct GuiGame {
+    pub chess: Chess,
+    pub mode: GameMode,
+    /// Index into `Difficulty::LEVELS`.
+    pub level: usize,
+    /// Skill level of the engine, `MAX_SKILL_LEVEL` plays at full strength.
+    pub skill: u8,
+    engine: Engine,
+    thinking: Option<Receiver<SearchResult>>,
+    /// Game end of the position with the given hash, cached as it needs all legal moves.
+    outcome: Option<(u64, Option<Outcome>)>,
+    /// Searches for hints and threats, so that they never hold up the playing engine.
+    analyst: Engine,
+    pub hint_limits: SearchLimits,
+    /// A running hint search for the position with the given hash.
+    hint_search: Option<(HintKind, u64, Receiver<SearchResult>)>,
+    /// Arrows shown for the position with the hash `hints_hash`.
+    hints: Vec<(HintKind, Move)>,
+    hints_hash: u64,
+    /// Whether the analyst searches the current position without a limit.
+    analysing: bool,
+    /// The running analysis of the position with the given hash.
+    analysis_search: Option<(u64, Receiver<SearchResult>)>,
+    analysis: Option<Analysis>,
+}
+
+impl GuiGame {
+    pub fn new(chess: Chess) -> Self {
+        GuiGame {
+            chess,
+            mode: GameMode::default(),
+            level: 2,
+            skill: MAX_SKILL_LEVEL,
+            engine: Engine::new(),
+            thinking: None,
+            outcome: None,
+            analyst: Engine::new(),
+            hint_limits: SearchLimits {
+                movetime: Some(HINT_TIME),
+                ..Default::default()
+            },
+            hint_search: None,
+            hints: vec![],
+            hints_hash: 0,
+            analysing: false,
+            analysis_search: None,
+            analysis: None,
+        }
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::LEVELS[self.level.min(Difficulty::LEVELS.len() - 1)]
+    }
+
+    /// Starts a new game from the start position, a running search is dropped.
+    pub fn new_game(&mut self, mode: GameMode) {
+        self.engine.stop();
+        self.thinking = None;
+        self.engine.new_game();
+        self.chess = Chess::new(self.chess.sprites);
+        self.mode = mode;
+        self.outcome = None;
+    }
+
+    pub fn is_thinking(&self) -> bool {
+        self.thinking.is_some()
+    }
+
+    pub fn outcome(&mut self) -> Option<Outcome> {
+        match self.outcome {
+            Some((hash, outcome)) if hash == self.chess.hash => outcome,
+            _ => {
+                let outcome = if !self.chess.legal_moves().is_empty() {
+                    None
+                } else if self.chess.check_check(self.chess.player).is_some() {
+                    Some(Outcome::Checkmate(!self.chess.player))
+                } else {
+                    Some(Outcome::Stalemate)
+                };
+                self.outcome = Some((self.chess.hash, outcome));
+                outcome
+            }
+        }
+    }
+
+    /// Starts a search if the engine is to move and plays its move once it is found.
+    /// Never blocks, it is called once per frame.
+    pub fn poll_engine(&mut self) {
+        if let Some(receiver) = &self.thinking {
+            let Ok(result) = receiver.try_recv() else {
+                return;
+            };
+            self.thinking = None;
+            if let Some(mv) = result.best_move {
+                self.chess.selection.unselect_field();
+                self.chess.make_move(mv);
+            }
+            return;
+        }
+
+        if self.mode.player(self.chess.player) != PlayerKind::Engine
+            || self.chess.state != State::Select
+            || self.outcome().is_some()
+        {
+            return;
+        }
+
+        let (sender, receiver) = channel();
+        self.engine.set_skill(Skill::new(self.skill));
+        self.engine.chess = self.chess.clone();
+        self.engine.chess.sprites = None;
+        self.engine.go(
+            self.difficulty().limits(),
+            |_| {},
+            move |result| {
+                sender.send(result).ok();
+            },
+        );
+        self.thinking = Some(receiver);
+    }
+
+    /// The arrows shown for the current position.
+    pub fn hints(&self) -> &[(HintKind, Move)] {
+        if self.hints_hash == self.chess.hash {
+            &self.hints
+        } else {
+            &[]
+        }
+    }
+
+    /// Starts searching for a hint or threat in the background, or hides the arrow if it
+    /// is already shown. There is no threat while the player to move is in check. While
+    /// analysing, the hint is the best move found so far.
+    pub fn request_hint(&mut self, kind: HintKind) {
+        let hash = self.chess.hash;
+        if self.hints_hash == hash && self.hints.iter().any(|(shown, _)| *shown == kind) {
+            self.hints.retain(|(shown, _)| *shown != kind);
+            return;
+        }
+        if kind == HintKind::BestMove {
+            if let Some(mv) = self
+                .analysis()
+                .and_then(|analysis| analysis.result.best_move)
+            {
+                self.hints_hash = hash;
+                self.hints.push((kind, mv));
+                return;
+            }
+        }
+
+        let mut position = self.chess.clone();
+        position.sprites = None;
+        if kind == HintKind::Threat {
+            if position.check_check(position.player).is_some() {
+                return;
+            }
+            position.pass_turn();
+        }
+        if position.legal_moves().is_empty() {
+            return;
+        }
+
+        let (sender, receiver) = channel();
+        // the analysis continues once the hint is found
+        self.analysis_search = None;
+        self.analyst.chess = position;
+        self.analyst.go(
+            self.hint_limits,
+            |_| {},
+            move |result| {
+                sender.send(result).ok();
+            },
+        );
+        self.hint_search = Some((kind, hash, receiver));
+    }
+
+    /// Picks up a finished hint search, never blocks.
+    pub fn poll_hints(&mut self) {
+        if self.hints_hash != self.chess.hash {
+            self.hints.clear();
+            self.hints_hash = self.chess.hash;
+        }
+        let Some((kind, hash, receiver)) = &self.hint_search else {
+            return;
+        };
+        let Ok(result) = receiver.try_recv() else {
+            return;
+        };
+        if let (true, Some(mv)) = (*hash == self.chess.hash, result.best_move) {
+            let kind = *kind;
+            self.hints.retain(|(shown, _)| *shown != kind);
+            self.hints.push((kind, mv));
+        }
+        self.hint_search = None;
+    }
+
+    pub fn is_analysing(&self) -> bool {
+        self.analysing
+    }
+
+    /// Switches the analysis of the current position on or off.
+    pub fn toggle_analysis(&mut self) {
+        self.analysing = !self.analysing;
+        if !self.analysing && self.analysis_search.take().is_some() {
+            self.analyst.stop();
+        }
+    }
+
+    /// The latest analysis of the current position.
+    pub fn analysis(&self) -> Option<&Analysis> {
+        self.analysis
+            .as_ref()
+            .filter(|analysis| analysis.hash == self.chess.hash)
+    }
+
+    /// Picks up new iterations of the analysis and restarts it when the position
+    /// changed. Never blocks.
+    pub fn poll_analysis(&mut self) {
+        if !self.analysing {
+            return;
+        }
+        if let Some((hash, receiver)) = &self.analysis_search {
+            if let Some(result) = receiver.try_iter().last() {
+                let mut chess = self.analyst.chess.clone();
+                let line = chess.san_line(&result.pv);
+                self.analysis = Some(Analysis {
+                    hash: *hash,
+                    result,
+                    line,
+                });
+            }
+        }
+
+        let current = self
+            .analysis_search
+            .as_ref()
+            .is_some_and(|(hash, _)| *hash == self.chess.hash);
+        if current || self.hint_search.is_some() {
+            return;
+        }
+        self.analysis_search = None;
+        let mut position = self.chess.clone();
+        position.sprites = None;
+        if position.legal_moves().is_empty() {
+            self.analyst.stop();
+            return;
+        }
+
+        let (sender, receiver) = channel();
+        self.analyst.chess = position;
+        self.analyst.go(
+            SearchLimits {
+                infinite: true,
+                ..Default::default()
+            },
+            move |result| {
+                sender.send(result.clone()).ok();
+            },
+            |_| {},
+        );
+        self.analysis_search = Some((self.chess.hash, receiver));
+    }
+
+    fn handle_board_click(&mut self) {
+        if self.mode.player(self.chess.player) != PlayerKind::Human || self.is_thinking() {
+            return;
+        }
+        match self.chess.state {
+            State::Promote(Position { row, col }) => {
+                if let Some(figure) = self
+                    .chess
+                    .has_clicked_promotion(Position { row, col }, mouse_position())
+                {
+                    self.promote((row, col), figure);
+                }
+            }
+            State::Select => {
+                if let Some(clicked) = self.chess.has_clicked_field(mouse_position()) {
+                    self.click_field(clicked);
+                }
+            }
+        }
+    }
+
+    /// Moves the selected figure onto `clicked` if the rules allow it, otherwise selects
+    /// the figure on `clicked`. A rochade is made by moving the king two fields or by
+    /// clicking its rook. Promotions wait in `State::Promote` for the chosen figure.
+    fn click_field(&mut self, clicked: (usize, usize)) {
+        if let Some(from) = self.chess.selection.selected_field {
+            let to = self.rochade_target(from, clicked).unwrap_or(clicked);
+            match self.chess.try_move(from, to, FigureType::Empty) {
+                MoveResult::Illegal => {}
+                MoveResult::PromotionRequired => {
+                    self.chess.state = State::Promote(Position {
+                        row: to.0,
+                        col: to.1,
+                    });
+                    return;
+                }
+                _ => return,
+            }
+        }
+        self.select_field(clicked);
+    }
+
+    /// Completes the promotion on `to` of the selected pawn.
+    fn promote(&mut self, to: (usize, usize), figure: FigureType) {
+        self.chess.state = State::Select;
+        if let Some(from) = self.chess.selection.selected_field {
+            self.chess.try_move(from, to, figure);
+        }
+        self.chess.selection.unselect_field();
+    }
+
+    /// The field the king on `from` moves to when its own rook on `clicked` was clicked.
+    fn rochade_target(
+        &self,
+        from: (usize, usize),
+        clicked: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let king = self.chess.field(from).figure?;
+        let rook = self.chess.field(clicked).figure?;
+        if king.figure != FigureType::King || rook.figure != FigureType::Rook || from.0 != clicked.0
+        {
+            return None;
+        }
+        Some(match clicked.1 > from.1 {
+            true => (from.0, from.1 + 2),
+            false => (from.0, from.1.checked_sub(2)?),
+        })
+    }
+
+    /// Selects the figure on `clicked` if it belongs to the player to move and highlights
+    /// the fields it can legally move to. Clicking the selected figure again unselects it.
+    fn select_field(&mut self, clicked: (usize, usize)) {
+        let selection = &mut self.chess.selection;
+        if selection.selected_field == Some(clicked) {
+            selection.unselect_field();
+            return;
+        }
+        selection.unselect_field();
+        if self
+            .chess
+            .field(clicked)
+            .figure
+            .is_none_or(|figure| figure.team != self.chess.player)
+        {
+            return;
+        }
+        let mut moves = self
+            .chess
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from() == clicked)
+            .map(|mv| mv.to())
+            .collect::<Vec<_>>();
+        moves.sort();
+        moves.dedup();
+        self.chess.selection.selected_field = Some(clicked);
+        self.chess.selection.moves = moves;
+    }
+
+    fn button_rect(idx: usize) -> Rect {
+        Rect::new(
+            PANEL_X,
+            Y_DIST + idx as f32 * (BUTTON_HEIGHT + BUTTON_GAP),
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    /// The panel buttons: one per game mode, then the search limit, the skill level and
+    /// the analysis.
+    fn buttons(&self) -> Vec<(Rect, String)> {
+        let mut buttons = GameMode::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, mode)| (GuiGame::button_rect(idx), mode.name().to_string()))
+            .collect::<Vec<_>>();
+        let idx = GameMode::ALL.len() + 1;
+        buttons.push((GuiGame::button_rect(idx), "Weaker".to_string()));
+        buttons.push((GuiGame::button_rect(idx + 1), "Stronger".to_string()));
+        buttons.push((GuiGame::button_rect(idx + 2), "Skill -".to_string()));
+        buttons.push((GuiGame::button_rect(idx + 3), "Skill +".to_string()));
+        let analysis = if self.analysing {
+            "Stop analysis"
+        } else {
+            "Analyse"
+        };
+        buttons.push((GuiGame::button_rect(idx + 4), analysis.to_string()));
+        buttons
+    }
+
+    fn handle_panel_click(&mut self) {
+        let (x, y) = mouse_position();
+        let Some(idx) = self
+            .buttons()
+            .iter()
+            .position(|(rect, _)| rect.contains(vec2(x, y)))
+        else {
+            return;
+        };
+        match idx.checked_sub(GameMode::ALL.len()) {
+            None => self.new_game(GameMode::ALL[idx]),
+            Some(0) => self.level = self.level.saturating_sub(1),
+            Some(1) => self.level = (self.level + 1).min(Difficulty::LEVELS.len() - 1),
+            Some(2) => self.skill = self.skill.saturating_sub(1),
+            Some(3) => self.skill = (self.skill + 1).min(MAX_SKILL_LEVEL),
+            Some(_) => self.toggle_analysis(),
+        }
+    }
+
+    /// Handles the input of one frame and lets the engine move.
+    pub fn update(&mut self) {
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if mouse_position().0 >= PANEL_X {
+                self.handle_panel_click();
+            } else {
+                self.handle_board_click();
+            }
+        }
+        if is_key_pressed(KeyCode::H) {
+            self.request_hint(HintKind::BestMove);
+        }
+        if is_key_pressed(KeyCode::T) {
+            self.request_hint(HintKind::Threat);
+        }
+        if is_key_pressed(KeyCode::A) {
+            self.toggle_analysis();
+        }
+        self.poll_engine();
+        self.poll_hints();
+        self.poll_analysis();
+    }
+
+    pub fn draw(&mut self) {
+        self.chess.draw();
+        if let State::Promote(to_promote) = self.chess.state {
+            self.chess.draw_promote_selection(to_promote);
+        }
+        for (kind, mv) in self.hints() {
+            draw_arrow(*mv, kind.color());
+        }
+
+        for (rect, label) in self.buttons() {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, GRAY);
+            draw_text(&label, rect.x + 10., rect.y + rect.h * 0.7, 22., BLACK);
+        }
+
+        let status = match self.outcome() {
+            Some(Outcome::Checkmate(Team::White)) => "White wins by checkmate".to_string(),
+            Some(Outcome::Checkmate(Team::Black)) => "Black wins by checkmate".to_string(),
+            Some(Outcome::Stalemate) => "Draw by stalemate".to_string(),
+            None if self.is_thinking() => "Engine is thinking...".to_string(),
+            None => format!("{:?} to move", self.chess.player),
+        };
+        let y = GuiGame::button_rect(GameMode::ALL.len() + 6).y + BUTTON_HEIGHT;
+        let lines = [
+            self.mode.name().to_string(),
+            format!("Engine: {}", self.difficulty().name()),
+            format!("Skill level: {}/{MAX_SKILL_LEVEL}", self.skill),
+            status,
+            "H: hint, T: threat, A: analysis".to_string(),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            draw_text(line, PANEL_X, y + idx as f32 * 26., 22., WHITE);
+        }
+
+        self.draw_eval_bar();
+        if self.analysing {
+            self.draw_analysis();
+        }
+    }
+
+    /// White's share of the bar grows from the bottom, the side white plays from.
+    fn draw_eval_bar(&self) {
+        let height = ROWS as f32 * SIZE;
+        let score = self.analysis().map(|analysis| {
+            let score = analysis.result.score;
+            match self.chess.player {
+                Team::White => score,
+                Team::Black => -score,
+            }
+        });
+        let white = height * white_share(score.unwrap_or(0));
+        draw_rectangle(EVAL_BAR_X, Y_DIST, EVAL_BAR_WIDTH, height, BLACK);
+        draw_rectangle(
+            EVAL_BAR_X,
+            Y_DIST + height - white,
+            EVAL_BAR_WIDTH,
+            white,
+            WHITE,
+        );
+        if let Some(score) = score {
+            let label = format_score(score, Team::White);
+            draw_text(&label, EVAL_BAR_X - 4., Y_DIST + height + 16., 18., WHITE);
+        }
+    }
+
+    fn draw_analysis(&self) {
+        let Some(analysis) = self.analysis() else {
+            draw_text("Analysing...", X_DIST, ANALYSIS_Y, 20., WHITE);
+            return;
+        };
+        let result = &analysis.result;
+        let header = format!(
+            "Depth {}  {}  {:.1} kN/s",
+            result.depth,
+            format_score(result.score, self.chess.player),
+            result.nps() as f32 / 1000.
+        );
+        draw_text(&header, X_DIST, ANALYSIS_Y, 20., WHITE);
+        for (idx, row) in wrap(&analysis.line, ANALYSIS_LINE_LEN)
+            .iter()
+            .take(3)
+            .enumerate()
+        {
+            draw_text(row, X_DIST, ANALYSIS_Y + (idx + 1) as f32 * 20., 20., WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        gui::{white_share, wrap, Difficulty, GameMode, GuiGame, HintKind, Outcome, PlayerKind},
+        search::{SearchLimits, MATE},
+        Chess, FigureType, Move, Position, State, Team,
+    };
+
+    #[test]
+    fn test_game_modes() {
+        let mode = GameMode::HumanVsEngine(Team::Black);
+        assert_eq!(mode.player(Team::Black), PlayerKind::Human);
+        assert_eq!(mode.player(Team::White), PlayerKind::Engine);
+        assert_eq!(
+            GameMode::HumanVsHuman.player(Team::White),
+            PlayerKind::Human
+        );
+        assert_eq!(
+            GameMode::EngineVsEngine.player(Team::Black),
+            PlayerKind::Engine
+        );
+    }
+
+    #[test]
+    fn test_difficulty_limits() {
+        assert_eq!(Difficulty::Depth(3).limits().depth, Some(3));
+        let limits = Difficulty::MoveTime(Duration::from_secs(1)).limits();
+        assert_eq!(limits.movetime, Some(Duration::from_secs(1)));
+        assert_eq!(limits.depth, None);
+    }
+
+    #[test]
+    fn test_engine_moves_in_background() {
+        let mut game = GuiGame::new(Chess::new(None));
+        game.level = 0;
+        game.new_game(GameMode::EngineVsEngine);
+
+        let start = Instant::now();
+        while game.chess.fullmove_number < 3 {
+            game.poll_engine();
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(game.chess.history.len(), 4);
+
+        // the engine waits while the human is to move
+        game.new_game(GameMode::HumanVsEngine(Team::White));
+        game.poll_engine();
+        assert!(!game.is_thinking());
+    }
+
+    #[test]
+    fn test_human_and_engine_move() {
+        let mut game = GuiGame::new(Chess::new(None));
+        game.level = 0;
+        game.new_game(GameMode::HumanVsEngine(Team::White));
+
+        // a black figure cannot be selected, an illegal target only changes the selection
+        game.click_field((1, 4));
+        assert_eq!(game.chess.selection.selected_field, None);
+        game.click_field((6, 4));
+        assert_eq!(game.chess.selection.moves, [(4, 4), (5, 4)]);
+        game.click_field((3, 4));
+        assert_eq!(game.chess.selection.selected_field, None);
+        game.click_field((6, 4));
+        game.click_field((4, 4));
+        assert_eq!(game.chess.history.len(), 1);
+        assert_eq!(
+            game.chess.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+
+        let start = Instant::now();
+        while game.chess.player == Team::Black {
+            game.poll_engine();
+            assert!(start.elapsed() < Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(game.chess.history.len(), 2);
+        let mut replayed = Chess::new(None);
+        for undo in &game.chess.history {
+            replayed.make_move(undo.mv);
+        }
+        assert_eq!(game.chess.to_fen(), replayed.to_fen());
+        assert!(game.chess.to_fen().ends_with(" 2"));
+    }
+
+    #[test]
+    fn test_human_promotion_and_rochade() {
+        let chess = Chess::from_fen("7k/4P3/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mut game = GuiGame::new(chess.clone());
+        game.click_field((1, 4));
+        game.click_field((0, 4));
+        assert_eq!(
+            game.chess.state,
+            State::Promote(Position { row: 0, col: 4 })
+        );
+        assert!(game.chess.history.is_empty());
+        game.promote((0, 4), FigureType::Knight);
+        assert_eq!(game.chess.state, State::Select);
+        assert_eq!(game.chess.to_fen(), "4N2k/8/8/8/8/8/8/4K2R b K - 0 1");
+
+        // the king moves onto its rook to make the rochade
+        let mut game = GuiGame::new(chess);
+        game.click_field((7, 4));
+        game.click_field((7, 7));
+        assert_eq!(game.chess.to_fen(), "7k/4P3/8/8/8/8/8/5RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut game = GuiGame::new(Chess::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap());
+        assert_eq!(game.outcome(), Some(Outcome::Checkmate(Team::White)));
+        game.mode = GameMode::EngineVsEngine;
+        game.poll_engine();
+        assert!(!game.is_thinking());
+    }
+
+    #[test]
+    fn test_hint_and_threat() {
+        let chess = Chess::from_fen("6k1/8/8/8/7n/5q2/6PP/7K w - - 0 1").unwrap();
+        let mut game = GuiGame::new(chess);
+        game.hint_limits = SearchLimits::depth(2);
+
+        let start = Instant::now();
+        for kind in [HintKind::Threat, HintKind::BestMove] {
+            game.request_hint(kind);
+            while game.hints().iter().all(|(shown, _)| *shown != kind) {
+                game.poll_hints();
+                assert!(start.elapsed() < Duration::from_secs(10));
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        // Qxg2# is threatened
+        assert_eq!(
+            game.hints()[0],
+            (HintKind::Threat, Move::new((5, 5), (6, 6)))
+        );
+        let (_, hint) = game.hints()[1];
+        assert!(game.chess.legal_moves().contains(&hint));
+
+        // asking again hides the arrow, moving clears all of them
+        game.request_hint(HintKind::Threat);
+        assert_eq!(game.hints().len(), 1);
+        game.chess.make_move(hint);
+        game.poll_hints();
+        assert!(game.hints().is_empty());
+    }
+
+    #[test]
+    fn test_eval_bar_and_wrap() {
+        assert_eq!(white_share(0), 0.5);
+        assert!(white_share(400) > 0.9);
+        assert!(white_share(-400) < 0.1);
+        assert_eq!(white_share(MATE - 3), 1.);
+        assert_eq!(white_share(-MATE + 3), 0.);
+
+        assert_eq!(wrap("1. e4 e5 2. Nf3", 8), ["1. e4 e5", "2. Nf3"]);
+    }
+
+    #[test]
+    fn test_analysis_follows_position() {
+        let mut game = GuiGame::new(Chess::new(None));
+        game.toggle_analysis();
+        assert!(game.is_analysing());
+
+        let start = Instant::now();
+        for mv in [None, Some(Move::new((6, 4), (4, 4)))] {
+            if let Some(mv) = mv {
+                game.chess.make_move(mv);
+            }
+            while game
+                .analysis()
+                .is_none_or(|analysis| analysis.result.depth < 2)
+            {
+                game.poll_analysis();
+                assert!(start.elapsed() < Duration::from_secs(20));
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        }
+        let analysis = game.analysis().unwrap().clone();
+        assert!(analysis.line.starts_with("1... "));
+
+        // a hint comes straight from the analysis
+        game.request_hint(HintKind::BestMove);
+        assert_eq!(
+            game.hints(),
+            [(HintKind::BestMove, analysis.result.best_move.unwrap())]
+        );
+
+        game.toggle_analysis();
+        assert!(!game.is_analysing());
+    }
+}
@@ -1,15 +1,40 @@
+pub mod annotate;
+pub mod book;
 mod chess;
+pub mod engine;
+pub mod eval;
+pub mod fen;
+mod ffi;
 mod figure;
+pub mod gui;
 mod valid_moves;
 pub use chess::Chess;
 mod field;
+mod make_move;
+pub mod match_runner;
+mod movegen;
+pub mod pgn;
+pub mod puzzle;
+mod rng;
+mod san;
+pub mod search;
 mod selection;
 mod special_moves;
+pub mod syzygy;
+pub mod tournament;
+pub mod tune;
+pub mod uci;
+pub mod xboard;
+pub mod zobrist;
 
 pub use chess::*;
+use engine::Engine;
+pub use ffi::*;
 pub use field::*;
 pub use figure::*;
 use macroquad::prelude::*;
+pub use make_move::*;
+use search::{SearchLimits, Skill};
 pub use selection::*;
 use tokio::runtime::Runtime;
 
@@ -41,10 +66,10 @@ pub async fn sprites() -> [Texture2D; 12] {
 }
 
 #[no_mangle]
-pub extern "C" fn chess_create(chess: *mut *mut Chess) {
-    unsafe {
-        *chess = Box::into_raw(Box::new(Chess::new(None)));
-    }
+pub extern "C" fn chess_create(chess: *mut *mut Chess) -> ChessStatus {
+    ffi_call("chess_create", || {
+        write_out(chess, Box::into_raw(Box::new(Chess::new(None))), "chess")
+    })
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -55,29 +80,50 @@ unsafe impl Send for ChessWrapper {}
 unsafe impl Sync for ChessWrapper {}
 
 #[no_mangle]
-pub extern "C" fn chess_get_current_team(chess: *mut Chess) -> Team {
-    unsafe { (*chess).player }
+pub extern "C" fn chess_get_current_team(chess: *mut Chess, team: *mut Team) -> ChessStatus {
+    ffi_call("chess_get_current_team", || {
+        let chess = deref_mut(chess, "chess")?;
+        write_out(team, chess.player, "team")
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn chess_set_current_team(chess: *mut Chess, team: Team) {
-    unsafe { (*chess).player = team }
+pub extern "C" fn chess_set_current_team(chess: *mut Chess, team: u32) -> ChessStatus {
+    ffi_call("chess_set_current_team", || {
+        let chess = deref_mut(chess, "chess")?;
+        chess.player = enum_arg(team, "team")?;
+        chess.refresh_hash();
+        Ok(())
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn chess_get_latest_move(chess: *mut Chess) -> Move {
-    unsafe { (*chess).latest_move.expect("No move is available.") }
+pub extern "C" fn chess_get_latest_move(chess: *mut Chess, mv: *mut Move) -> ChessStatus {
+    ffi_call("chess_get_latest_move", || {
+        let latest_move = deref_mut(chess, "chess")?
+            .latest_move
+            .ok_or_else(|| FfiError::new(ChessStatus::NoMove, "no move was made yet"))?;
+        write_out(mv, latest_move, "mv")
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn chess_get_state(chess: *mut Chess) -> State {
-    unsafe { (*chess).state }
+pub extern "C" fn chess_get_state(chess: *mut Chess, state: *mut State) -> ChessStatus {
+    ffi_call("chess_get_state", || {
+        let chess = deref_mut(chess, "chess")?;
+        write_out(state, chess.state, "state")
+    })
 }
+
 #[no_mangle]
-pub extern "C" fn chess_remove_figure_at(chess: *mut Chess, row: usize, col: usize) {
-    unsafe {
-        (*chess).fields[row][col].figure = None;
-    }
+pub extern "C" fn chess_remove_figure_at(chess: *mut Chess, row: usize, col: usize) -> ChessStatus {
+    ffi_call("chess_remove_figure_at", || {
+        let chess = deref_mut(chess, "chess")?;
+        check_field(row, col)?;
+        chess.fields[row][col].figure = None;
+        chess.refresh_hash();
+        Ok(())
+    })
 }
 
 #[no_mangle]
@@ -85,73 +131,93 @@ pub extern "C" fn chess_set_figure_at(
     chess: *mut Chess,
     row: usize,
     col: usize,
-    figure: FigureType,
-    team: Team,
-) {
-    unsafe {
-        (*chess).fields[row][col].figure = Some(Figure {
+    figure: u32,
+    team: u32,
+) -> ChessStatus {
+    ffi_call("chess_set_figure_at", || {
+        let chess = deref_mut(chess, "chess")?;
+        check_field(row, col)?;
+        let (figure, team) = (enum_arg(figure, "figure")?, enum_arg(team, "team")?);
+        chess.fields[row][col].figure = (figure != FigureType::Empty).then_some(Figure {
             figure,
             team,
             first_move: true,
-        })
-    }
+        });
+        chess.refresh_hash();
+        Ok(())
+    })
 }
 
+/// Writes `Empty` for an empty field.
 #[no_mangle]
-pub extern "C" fn chess_get_figure_at(chess: *mut Chess, row: usize, col: usize) -> FigureType {
-    unsafe {
-        if (*chess).fields[row][col].figure.is_none() {
-            return figure::FigureType::Empty;
-        } else {
-            return (*chess).fields[row][col].figure.unwrap().figure;
-        }
-    }
+pub extern "C" fn chess_get_figure_at(
+    chess: *mut Chess,
+    row: usize,
+    col: usize,
+    figure: *mut FigureType,
+) -> ChessStatus {
+    ffi_call("chess_get_figure_at", || {
+        let chess = deref_mut(chess, "chess")?;
+        check_field(row, col)?;
+        let value = chess.fields[row][col]
+            .figure
+            .map_or(FigureType::Empty, |figure| figure.figure);
+        write_out(figure, value, "figure")
+    })
 }
 
 #[no_mangle]
-pub extern "C" fn chess_run(chess: ChessWrapper) {
-    Runtime::new().unwrap().block_on(async {
-        //tokio::task::spawn(async move {
-        macroquad::Window::from_config(Conf::default(), async move {
-            let chess = chess;
-
-            unsafe { &mut *chess.0 }.sprites = Some(sprites().await);
-
-            // let chess = unsafe {&mut *chess.0};
-            loop {
-                unsafe { &mut *chess.0 }.draw();
-
-                match unsafe { &mut *chess.0 }.state {
-                    State::Promote(to_promote) => {
-                        unsafe { &mut *chess.0 }.draw_promote_selection(to_promote);
-                        if is_mouse_button_pressed(MouseButton::Left) {
-                            if let Some(figure) = unsafe { &mut *chess.0 }
-                                .has_clicked_promotion(to_promote, mouse_position())
-                            {
-                                unsafe { &mut *chess.0 }.handle_promote_selection(
-                                    (to_promote.row, to_promote.col),
-                                    figure,
-                                )
+pub extern "C" fn chess_run(chess: ChessWrapper) -> ChessStatus {
+    ffi_call("chess_run", || {
+        deref_mut(chess.0, "chess")?;
+        Runtime::new()
+            .map_err(|err| FfiError::new(ChessStatus::Panic, err.to_string()))?
+            .block_on(async {
+                //tokio::task::spawn(async move {
+                macroquad::Window::from_config(Conf::default(), async move {
+                    let chess = chess;
+
+                    unsafe { &mut *chess.0 }.sprites = Some(sprites().await);
+
+                    // let chess = unsafe {&mut *chess.0};
+                    loop {
+                        unsafe { &mut *chess.0 }.draw();
+
+                        match unsafe { &mut *chess.0 }.state {
+                            State::Promote(to_promote) => {
+                                unsafe { &mut *chess.0 }.draw_promote_selection(to_promote);
+                                if is_mouse_button_pressed(MouseButton::Left) {
+                                    if let Some(figure) = unsafe { &mut *chess.0 }
+                                        .has_clicked_promotion(to_promote, mouse_position())
+                                    {
+                                        unsafe { &mut *chess.0 }.handle_promote_selection(
+                                            (to_promote.row, to_promote.col),
+                                            figure,
+                                        )
+                                    }
+                                }
                             }
-                        }
-                    }
-                    State::Select => {
-                        if is_mouse_button_pressed(MouseButton::Left) {
-                            let field =
-                                unsafe { &mut *chess.0 }.has_clicked_field(mouse_position());
-                            if let Some(clicked) = field {
-                                unsafe { &mut *chess.0 }.select_or_move(clicked)
+                            State::Select => {
+                                if is_mouse_button_pressed(MouseButton::Left) {
+                                    let field = unsafe { &mut *chess.0 }
+                                        .has_clicked_field(mouse_position());
+                                    if let Some(clicked) = field {
+                                        unsafe { &mut *chess.0 }.select_or_move(clicked)
+                                    }
+                                }
                             }
                         }
+                        next_frame().await;
                     }
-                }
-                next_frame().await;
-            }
-        });
-        //})
-    });
+                });
+                //})
+            });
+        Ok(())
+    })
 }
 
+/// Makes the move of the player to move if the rules allow it and writes the outcome to
+/// `result`. `promotion` is `Empty` unless a pawn promotes.
 #[no_mangle]
 pub extern "C" fn chess_move(
     chess: ChessWrapper,
@@ -159,35 +225,210 @@ pub extern "C" fn chess_move(
     start_col: usize,
     end_row: usize,
     end_col: usize,
-) {
-    let chess = unsafe { &mut *chess.0 };
-    chess.move_figure((start_row, start_col), (end_row, end_col));
-
-    chess.player = !chess.player;
+    promotion: u32,
+    result: *mut MoveResult,
+) -> ChessStatus {
+    ffi_call("chess_move", || {
+        let chess = deref_mut(chess.0, "chess")?;
+        let result = deref_mut(result, "result")?;
+        check_field(start_row, start_col)?;
+        check_field(end_row, end_col)?;
+        let promotion = enum_arg(promotion, "promotion")?;
+        *result = chess.try_move((start_row, start_col), (end_row, end_col), promotion);
+        Ok(())
+    })
 }
 
 // pub extern fn chess_last_sele
 
 #[no_mangle]
-pub extern "C" fn chess_free(chess: *mut Chess) {
-    unsafe {
-        drop(Box::from_raw(chess));
-    };
+pub extern "C" fn chess_engine_create(engine: *mut *mut Engine) -> ChessStatus {
+    ffi_call("chess_engine_create", || {
+        write_out(engine, Box::into_raw(Box::new(Engine::new())), "engine")
+    })
+}
+
+/// Levels from 0 to `MAX_SKILL_LEVEL`, which plays at full strength.
+#[no_mangle]
+pub extern "C" fn chess_engine_set_skill_level(engine: *mut Engine, level: u8) -> ChessStatus {
+    ffi_call("chess_engine_set_skill_level", || {
+        deref_mut(engine, "engine")?.set_skill(Skill::new(level));
+        Ok(())
+    })
+}
+
+/// Searches the position of `chess` for `movetime_ms` milliseconds and writes the move
+/// the engine plays to `mv`.
+#[no_mangle]
+pub extern "C" fn chess_engine_best_move(
+    engine: *mut Engine,
+    chess: *mut Chess,
+    movetime_ms: u64,
+    mv: *mut Move,
+) -> ChessStatus {
+    ffi_call("chess_engine_best_move", || {
+        let engine = deref_mut(engine, "engine")?;
+        engine.chess = deref_mut(chess, "chess")?.clone();
+        engine.chess.sprites = None;
+        let limits = SearchLimits {
+            movetime: Some(std::time::Duration::from_millis(movetime_ms)),
+            ..Default::default()
+        };
+        let best_move = engine
+            .search(limits)
+            .best_move
+            .ok_or_else(|| FfiError::new(ChessStatus::NoMove, "the position has no legal move"))?;
+        write_out(mv, best_move, "mv")
+    })
+}
+
+/// Freeing a null pointer does nothing.
+#[no_mangle]
+pub extern "C" fn chess_engine_free(engine: *mut Engine) -> ChessStatus {
+    ffi_call("chess_engine_free", || {
+        free(engine);
+        Ok(())
+    })
+}
+
+/// Freeing a null pointer does nothing.
+#[no_mangle]
+pub extern "C" fn chess_free(chess: *mut Chess) -> ChessStatus {
+    ffi_call("chess_free", || {
+        free(chess);
+        Ok(())
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::ptr::null_mut;
+    use std::{ffi::CStr, ptr::null_mut};
 
-    use crate::{chess_create, chess_run, Chess, ChessWrapper};
+    use crate::{
+        chess_create, chess_free, chess_get_current_team, chess_get_figure_at,
+        chess_get_latest_move, chess_last_error_message, chess_move, chess_run,
+        chess_set_current_team, chess_set_figure_at, Chess, ChessStatus, ChessWrapper, FigureType,
+        Move, MoveResult, Team,
+    };
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(chess_last_error_message()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
 
     #[test]
     #[ignore = ""]
     fn test_raw_lib() {
         let mut chess: *mut Chess = null_mut();
-        chess_create(&mut chess);
+        assert_eq!(chess_create(&mut chess), ChessStatus::Ok);
 
-        chess_run(ChessWrapper(chess));
+        assert_eq!(chess_run(ChessWrapper(chess)), ChessStatus::Ok);
         loop {}
     }
+
+    #[test]
+    fn test_ffi_status() {
+        let mut chess: *mut Chess = null_mut();
+        assert_eq!(chess_create(&mut chess), ChessStatus::Ok);
+
+        let mut mv = Move::new((0, 0), (0, 0));
+        assert_eq!(chess_get_latest_move(chess, &mut mv), ChessStatus::NoMove);
+        assert_eq!(
+            chess_get_latest_move(null_mut(), &mut mv),
+            ChessStatus::NullPointer
+        );
+        assert_eq!(
+            chess_set_figure_at(chess, 8, 0, FigureType::Queen as u32, Team::White as u32),
+            ChessStatus::OutOfRange
+        );
+
+        let mut figure = FigureType::Empty;
+        assert_eq!(
+            chess_get_figure_at(chess, 7, 3, &mut figure),
+            ChessStatus::Ok
+        );
+        assert_eq!(figure, FigureType::Queen);
+        assert_eq!(
+            chess_get_figure_at(chess, 7, 3, null_mut()),
+            ChessStatus::NullPointer
+        );
+
+        let mut result = MoveResult::Ok;
+        let status = chess_move(
+            ChessWrapper(chess),
+            6,
+            4,
+            3,
+            4,
+            FigureType::Empty as u32,
+            &mut result,
+        );
+        assert_eq!((status, result), (ChessStatus::Ok, MoveResult::Illegal));
+        let status = chess_move(
+            ChessWrapper(chess),
+            6,
+            4,
+            4,
+            4,
+            FigureType::Empty as u32,
+            &mut result,
+        );
+        assert_eq!((status, result), (ChessStatus::Ok, MoveResult::Ok));
+        assert_eq!(chess_get_latest_move(chess, &mut mv), ChessStatus::Ok);
+        assert_eq!(mv, Move::new((6, 4), (4, 4)));
+        let status = chess_move(
+            ChessWrapper(chess),
+            1,
+            4,
+            3,
+            4,
+            FigureType::Empty as u32,
+            null_mut(),
+        );
+        assert_eq!(status, ChessStatus::NullPointer);
+
+        assert_eq!(chess_free(chess), ChessStatus::Ok);
+        assert_eq!(chess_free(null_mut()), ChessStatus::Ok);
+    }
+
+    #[test]
+    fn test_ffi_invalid_enums() {
+        let mut chess: *mut Chess = null_mut();
+        assert_eq!(chess_create(&mut chess), ChessStatus::Ok);
+
+        assert_eq!(chess_set_current_team(chess, 2), ChessStatus::OutOfRange);
+        assert_eq!(
+            last_error(),
+            "chess_set_current_team: 2 is not a valid value of team"
+        );
+        assert_eq!(
+            chess_set_figure_at(chess, 4, 4, 7, Team::White as u32),
+            ChessStatus::OutOfRange
+        );
+        assert_eq!(
+            chess_set_figure_at(chess, 4, 4, FigureType::Queen as u32, u32::MAX),
+            ChessStatus::OutOfRange
+        );
+        let mut result = MoveResult::Ok;
+        let status = chess_move(ChessWrapper(chess), 6, 4, 4, 4, 9, &mut result);
+        assert_eq!(status, ChessStatus::OutOfRange);
+        assert_eq!(
+            last_error(),
+            "chess_move: 9 is not a valid value of promotion"
+        );
+
+        // nothing was changed
+        let mut team = Team::Black;
+        assert_eq!(chess_get_current_team(chess, &mut team), ChessStatus::Ok);
+        assert_eq!(team, Team::White);
+        let mut figure = FigureType::Queen;
+        assert_eq!(
+            chess_get_figure_at(chess, 4, 4, &mut figure),
+            ChessStatus::Ok
+        );
+        assert_eq!(figure, FigureType::Empty);
+        assert_eq!(chess_free(chess), ChessStatus::Ok);
+    }
 }
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use crate::{
+    zobrist::{piece_key, turn_key},
+    Chess, Figure, FigureType, Move, Team, COLS,
+};
+
+/// Outcome of `Chess::try_move`, also returned by the C API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub enum MoveResult {
+    /// The move breaks the rules and was not made.
+    Illegal = 0,
+    Ok,
+    Check,
+    Checkmate,
+    Stalemate,
+    /// Drawn by the fifty move rule, threefold repetition or insufficient material.
+    Draw,
+    /// A pawn reaches the last rank but no promotion figure was given. The move was not
+    /// made.
+    PromotionRequired,
+}
+
+/// Everything needed to take back a move applied with `make_move`.
+#[derive(Debug, Clone)]
+pub struct Undo {
+    pub mv: Move,
+    pub moved: Figure,
+    pub captured: Option<(Figure, (usize, usize))>,
+    en_passants: HashMap<(usize, usize, Team), (usize, usize)>,
+    latest_move: Option<Move>,
+    pub(crate) hash: u64,
+    halfmove_clock: usize,
+    fullmove_number: usize,
+}
+
+/// Returns the start and end field of the rook if `mv` is a rochade of `moved`.
+fn rochade_rook(moved: Figure, mv: Move) -> Option<((usize, usize), (usize, usize))> {
+    if moved.figure != FigureType::King || mv.start_col.abs_diff(mv.end_col) != 2 {
+        return None;
+    }
+    let row = mv.start_row;
+    if mv.end_col > mv.start_col {
+        Some(((row, COLS - 1), (row, mv.end_col - 1)))
+    } else {
+        Some(((row, 0), (row, mv.end_col + 1)))
+    }
+}
+
+impl Chess {
+    /// Applies a move without any validation and updates the zobrist key incrementally.
+    /// Rochades are encoded as the king moving two fields, en passant captures as the
+    /// pawn moving diagonally onto the empty field.
+    pub fn make_move(&mut self, mv: Move) {
+        let (from, to) = (mv.from(), mv.to());
+        let moved = self
+            .field(from)
+            .figure
+            .expect("A figure should be on the start field of the move.");
+
+        let mut captured = self.field(to).figure.map(|figure| (figure, to));
+        if moved.figure == FigureType::Pawn && from.1 != to.1 && captured.is_none() {
+            let en_passant_pawn = (from.0, to.1);
+            captured = self
+                .field(en_passant_pawn)
+                .figure
+                .map(|figure| (figure, en_passant_pawn));
+        }
+
+        // remove the old castling rights and en passant file, flip the side to move
+        let mut hash = self.hash ^ self.state_key() ^ turn_key();
+
+        self.history.push(Undo {
+            mv,
+            moved,
+            captured,
+            en_passants: std::mem::take(&mut self.en_passants),
+            latest_move: self.latest_move,
+            hash: self.hash,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+        });
+
+        if moved.figure == FigureType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if moved.team == Team::Black {
+            self.fullmove_number += 1;
+        }
+
+        if let Some((figure, pos)) = captured {
+            hash ^= piece_key(figure, pos);
+            self.field_mut(pos).figure = None;
+        }
+
+        let mut placed = moved;
+        placed.first_move = false;
+        if mv.promotion != FigureType::Empty {
+            placed.figure = mv.promotion;
+        }
+        hash ^= piece_key(moved, from) ^ piece_key(placed, to);
+        self.field_mut(from).figure = None;
+        self.field_mut(to).figure = Some(placed);
+
+        if let Some((rook_from, rook_to)) = rochade_rook(moved, mv) {
+            let mut rook = self
+                .field_mut(rook_from)
+                .figure
+                .take()
+                .expect("Rook should be there");
+            rook.first_move = false;
+            hash ^= piece_key(rook, rook_from) ^ piece_key(rook, rook_to);
+            self.field_mut(rook_to).figure = Some(rook);
+        }
+
+        if moved.figure == FigureType::Pawn && from.0.abs_diff(to.0) == 2 {
+            for col in [to.1.wrapping_sub(1), to.1 + 1] {
+                if col >= COLS {
+                    continue;
+                }
+                if let Some(Figure {
+                    figure: FigureType::Pawn,
+                    team,
+                    ..
+                }) = self.field((to.0, col)).figure
+                {
+                    if team != moved.team {
+                        self.en_passants.insert((to.0, col, team), to);
+                    }
+                }
+            }
+        }
+
+        self.player = !self.player;
+        self.latest_move = Some(mv);
+        self.hash = hash ^ self.state_key();
+    }
+
+    /// Takes back the last move applied with `make_move`.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        let (from, to) = (undo.mv.from(), undo.mv.to());
+
+        if let Some((rook_from, rook_to)) = rochade_rook(undo.moved, undo.mv) {
+            let mut rook = self
+                .field_mut(rook_to)
+                .figure
+                .take()
+                .expect("Rook should be there");
+            rook.first_move = true;
+            self.field_mut(rook_from).figure = Some(rook);
+        }
+
+        self.field_mut(to).figure = None;
+        self.field_mut(from).figure = Some(undo.moved);
+        if let Some((figure, pos)) = undo.captured {
+            self.field_mut(pos).figure = Some(figure);
+        }
+
+        self.player = !self.player;
+        self.en_passants = undo.en_passants;
+        self.latest_move = undo.latest_move;
+        self.hash = undo.hash;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        Some(undo.mv)
+    }
+
+    /// How often the current position occurred before, only positions since the last
+    /// capture or pawn move are taken into account.
+    pub fn repetitions(&self) -> usize {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock)
+            .skip(1)
+            .step_by(2)
+            .filter(|undo| undo.hash == self.hash)
+            .count()
+    }
+
+    /// Neither side can mate: bare kings with at most one minor figure, or only bishops on
+    /// fields of one colour.
+    pub fn insufficient_material(&self) -> bool {
+        let figures = self
+            .fields
+            .iter()
+            .flatten()
+            .filter_map(|field| Some((field.idxs, field.figure?)))
+            .filter(|(_, figure)| figure.figure != FigureType::King)
+            .collect::<Vec<_>>();
+        match figures.as_slice() {
+            [] => true,
+            [(_, figure)] => matches!(figure.figure, FigureType::Knight | FigureType::Bishop),
+            [((row, col), _), rest @ ..] => {
+                figures
+                    .iter()
+                    .all(|(_, figure)| figure.figure == FigureType::Bishop)
+                    && rest.iter().all(|((other_row, other_col), _)| {
+                        (row + col) % 2 == (other_row + other_col) % 2
+                    })
+            }
+        }
+    }
+
+    /// Makes the move from `from` to `to` of the player to move if the rules allow it,
+    /// including rochades, en passant and promotions. `promotion` is `Empty` for every
+    /// move that does not promote.
+    pub fn try_move(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: FigureType,
+    ) -> MoveResult {
+        let candidates = self
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from() == from && mv.to() == to)
+            .collect::<Vec<_>>();
+        if promotion == FigureType::Empty
+            && candidates
+                .iter()
+                .any(|mv| mv.promotion != FigureType::Empty)
+        {
+            return MoveResult::PromotionRequired;
+        }
+        let Some(mv) = candidates.into_iter().find(|mv| mv.promotion == promotion) else {
+            return MoveResult::Illegal;
+        };
+
+        self.make_move(mv);
+        self.selection.unselect_field();
+        let check = self.check_check(self.player).is_some();
+        if self.legal_moves().is_empty() {
+            if check {
+                MoveResult::Checkmate
+            } else {
+                MoveResult::Stalemate
+            }
+        } else if self.halfmove_clock >= 100
+            || self.repetitions() >= 2
+            || self.insufficient_material()
+        {
+            MoveResult::Draw
+        } else if check {
+            MoveResult::Check
+        } else {
+            MoveResult::Ok
+        }
+    }
+
+    /// Hands the turn to the other player without moving, a null move. En passant
+    /// captures lapse. Cannot be taken back with `unmake_move`.
+    pub fn pass_turn(&mut self) {
+        self.player = !self.player;
+        self.en_passants.clear();
+        self.latest_move = None;
+        self.refresh_hash();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chess, FigureType, Move, MoveResult, Team};
+
+    #[test]
+    fn test_unmake_restores_position() {
+        let mut chess = Chess::new(None);
+        let hash = chess.hash;
+
+        chess.make_move(Move::new((6, 4), (4, 4)));
+        chess.make_move(Move::new((1, 3), (3, 3)));
+        chess.make_move(Move::new((4, 4), (3, 3)));
+        assert_eq!(chess.player, Team::Black);
+        assert_eq!(chess.fields[3][3].figure.unwrap().team, Team::White);
+
+        for _ in 0..3 {
+            chess.unmake_move().unwrap();
+        }
+
+        assert_eq!(chess.hash, hash);
+        assert_eq!(chess.player, Team::White);
+        assert!(chess.fields[6][4].figure.unwrap().first_move);
+        assert_eq!(chess.fields[1][3].figure.unwrap().figure, FigureType::Pawn);
+        assert!(chess.fields[3][3].figure.is_none());
+    }
+
+    #[test]
+    fn test_en_passant_capture() {
+        let mut chess = Chess::new(None);
+        for mv in [
+            Move::new((6, 4), (4, 4)),
+            Move::new((1, 0), (2, 0)),
+            Move::new((4, 4), (3, 4)),
+            Move::new((1, 3), (3, 3)),
+        ] {
+            chess.make_move(mv);
+        }
+
+        chess.make_move(Move::new((3, 4), (2, 3)));
+        assert!(chess.fields[3][3].figure.is_none());
+        assert_eq!(chess.hash, chess.compute_hash());
+
+        chess.unmake_move();
+        assert_eq!(chess.fields[3][3].figure.unwrap().team, Team::Black);
+        assert_eq!(chess.hash, chess.compute_hash());
+    }
+
+    #[test]
+    fn test_pass_turn() {
+        let mut chess =
+            Chess::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3")
+                .unwrap();
+        chess.pass_turn();
+        assert_eq!(chess.player, Team::Black);
+        assert!(chess.en_passants.is_empty());
+        assert_eq!(chess.hash, chess.compute_hash());
+
+        chess.pass_turn();
+        assert_eq!(
+            chess.to_fen(),
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3"
+        );
+    }
+
+    #[test]
+    fn test_try_move() {
+        let mut chess = Chess::new(None);
+        assert_eq!(
+            chess.try_move((6, 4), (3, 4), FigureType::Empty),
+            MoveResult::Illegal
+        );
+        assert_eq!(
+            chess.try_move((1, 4), (3, 4), FigureType::Empty),
+            MoveResult::Illegal
+        );
+        assert_eq!(chess.player, Team::White);
+        for (from, to) in [((6, 5), (5, 5)), ((1, 4), (3, 4)), ((6, 6), (4, 6))] {
+            assert_eq!(chess.try_move(from, to, FigureType::Empty), MoveResult::Ok);
+        }
+        assert_eq!(
+            chess.try_move((0, 3), (4, 7), FigureType::Empty),
+            MoveResult::Checkmate
+        );
+
+        let mut chess = Chess::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            chess.try_move((1, 1), (0, 1), FigureType::Empty),
+            MoveResult::PromotionRequired
+        );
+        assert_eq!(
+            chess.try_move((1, 1), (0, 1), FigureType::King),
+            MoveResult::Illegal
+        );
+        assert_eq!(
+            chess.try_move((7, 4), (7, 6), FigureType::Queen),
+            MoveResult::Illegal
+        );
+        assert_eq!(
+            chess.try_move((7, 4), (7, 6), FigureType::Empty),
+            MoveResult::Ok
+        );
+        assert_eq!(chess.field((7, 5)).figure.unwrap().figure, FigureType::Rook);
+        chess.unmake_move();
+        assert_eq!(
+            chess.try_move((1, 1), (0, 1), FigureType::Queen),
+            MoveResult::Check
+        );
+        assert_eq!(
+            chess.field((0, 1)).figure.unwrap().figure,
+            FigureType::Queen
+        );
+
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/3n4/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            chess.try_move((7, 4), (6, 3), FigureType::Empty),
+            MoveResult::Draw
+        );
+    }
+}
@@ -0,0 +1,896 @@
+mod process;
+mod stats;
+
+use std::{
+    fmt::{Display, Formatter},
+    fs, io,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub use process::{EngineMove, UciProcess};
+pub use stats::{elo_difference, expected_score, EloEstimate, Sprt, SprtStatus};
+
+use crate::{
+    fen::{FenError, START_FEN},
+    pgn::{parse_pgn, Game, GameResult, PgnError, PgnMove},
+    search::Clock,
+    syzygy::{Tablebase, Wdl},
+    Chess, Move, Team,
+};
+
+/// How long an engine may overrun its clock before it loses on time.
+pub const DEFAULT_TIME_MARGIN: Duration = Duration::from_millis(100);
+/// Longest wait for a move under a depth or node limit.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Limits of one side, in the notation of cutechess-cli.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    /// "40/60+0.6": `base` for every `moves` moves or the whole game, plus `increment`
+    /// per move.
+    Clock {
+        moves: Option<usize>,
+        base: Duration,
+        increment: Duration,
+    },
+    /// "st=0.1": a fixed time per move.
+    MoveTime(Duration),
+    /// "depth=8"
+    Depth(usize),
+    /// "nodes=10000"
+    Nodes(u64),
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl::Clock {
+            moves: None,
+            base: Duration::from_secs(10),
+            increment: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTimeControl;
+
+impl Display for InvalidTimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid time control")
+    }
+}
+
+impl std::error::Error for InvalidTimeControl {}
+
+fn parse_seconds(seconds: &str) -> Result<Duration, InvalidTimeControl> {
+    seconds
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or(InvalidTimeControl)
+}
+
+impl FromStr for TimeControl {
+    type Err = InvalidTimeControl;
+
+    fn from_str(tc: &str) -> Result<Self, Self::Err> {
+        if let Some(seconds) = tc.strip_prefix("st=") {
+            return Ok(TimeControl::MoveTime(parse_seconds(seconds)?));
+        }
+        if let Some(depth) = tc.strip_prefix("depth=") {
+            return depth
+                .parse()
+                .map(TimeControl::Depth)
+                .map_err(|_| InvalidTimeControl);
+        }
+        if let Some(nodes) = tc.strip_prefix("nodes=") {
+            return nodes
+                .parse()
+                .map(TimeControl::Nodes)
+                .map_err(|_| InvalidTimeControl);
+        }
+
+        let (moves, rest) = match tc.split_once('/') {
+            Some((moves, rest)) => (Some(moves.parse().map_err(|_| InvalidTimeControl)?), rest),
+            None => (None, tc),
+        };
+        let (base, increment) = match rest.split_once('+') {
+            Some((base, increment)) => (parse_seconds(base)?, parse_seconds(increment)?),
+            None => (parse_seconds(rest)?, Duration::ZERO),
+        };
+        if moves == Some(0) || base.is_zero() {
+            return Err(InvalidTimeControl);
+        }
+        Ok(TimeControl::Clock {
+            moves,
+            base,
+            increment,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeControl::Clock {
+                moves,
+                base,
+                increment,
+            } => {
+                if let Some(moves) = moves {
+                    write!(f, "{moves}/")?;
+                }
+                write!(f, "{}", base.as_secs_f64())?;
+                if !increment.is_zero() {
+                    write!(f, "+{}", increment.as_secs_f64())?;
+                }
+                Ok(())
+            }
+            TimeControl::MoveTime(time) => write!(f, "st={}", time.as_secs_f64()),
+            TimeControl::Depth(depth) => write!(f, "depth={depth}"),
+            TimeControl::Nodes(nodes) => write!(f, "nodes={nodes}"),
+        }
+    }
+}
+
+impl TimeControl {
+    /// The clock at the start of a game, `None` without a clock.
+    fn start_clock(&self) -> Option<Clock> {
+        match *self {
+            TimeControl::Clock {
+                moves,
+                base,
+                increment,
+            } => Some(Clock {
+                time: base,
+                inc: increment,
+                moves_to_go: moves,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// How to start and configure one engine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Empty to use the name the engine reports.
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// UCI options set before the first game.
+    pub options: Vec<(String, String)>,
+    pub time_control: TimeControl,
+}
+
+impl EngineConfig {
+    pub fn new(command: impl Into<String>) -> Self {
+        EngineConfig {
+            command: command.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Parses the `key=value` settings of one engine on the command line:
+    /// `cmd=PATH [name=NAME] [arg=ARG]... [tc=TC] [option.NAME=VALUE]...`. The time
+    /// control is returned separately, it is `None` if the engine uses the default one.
+    pub fn parse_settings<S: AsRef<str>>(
+        settings: impl IntoIterator<Item = S>,
+    ) -> Result<(Self, Option<TimeControl>), EngineSettingError> {
+        let mut config = EngineConfig::default();
+        let mut time_control = None;
+        for setting in settings {
+            let setting = setting.as_ref();
+            let invalid = || EngineSettingError::Invalid(setting.to_string());
+            let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "cmd" => config.command = value.to_string(),
+                "name" => config.name = value.to_string(),
+                "arg" => config.args.push(value.to_string()),
+                "tc" => time_control = Some(value.parse().map_err(|_| invalid())?),
+                _ => {
+                    let name = key.strip_prefix("option.").ok_or_else(invalid)?;
+                    config.options.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+        if config.command.is_empty() {
+            return Err(EngineSettingError::MissingCommand);
+        }
+        Ok((config, time_control))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineSettingError {
+    /// Not `key=value`, an unknown key or an invalid time control.
+    Invalid(String),
+    MissingCommand,
+}
+
+impl Display for EngineSettingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineSettingError::Invalid(setting) => write!(f, "invalid engine setting {setting}"),
+            EngineSettingError::MissingCommand => write!(f, "engine command cmd=PATH is missing"),
+        }
+    }
+}
+
+impl std::error::Error for EngineSettingError {}
+
+/// A start position with the moves leading to the position the engines take over.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Opening {
+    /// `None` for the standard start position.
+    pub fen: Option<String>,
+    pub moves: Vec<Move>,
+}
+
+impl Opening {
+    pub fn start_position(&self) -> Chess {
+        Chess::from_fen(self.fen.as_deref().unwrap_or(START_FEN))
+            .expect("opening FENs are checked while loading")
+    }
+}
+
+/// One opening per line of an EPD file, operations after the four position fields are
+/// ignored.
+pub fn epd_openings(text: &str) -> Result<Vec<Opening>, FenError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fen = line
+                .split_whitespace()
+                .take(4)
+                .collect::<Vec<_>>()
+                .join(" ")
+                + " 0 1";
+            Chess::from_fen(&fen)?;
+            Ok(Opening {
+                fen: Some(fen),
+                moves: vec![],
+            })
+        })
+        .collect()
+}
+
+/// The main line of every game of a PGN file as an opening.
+pub fn pgn_openings(text: &str) -> Result<Vec<Opening>, PgnError> {
+    parse_pgn(text)?
+        .iter()
+        .map(|game| {
+            let (_, moves) = game.replay()?;
+            Ok(Opening {
+                fen: game.tag("FEN").map(str::to_string),
+                moves,
+            })
+        })
+        .collect()
+}
+
+/// Reads openings from a ".pgn" file, every other file is read as EPD.
+pub fn load_openings(path: impl AsRef<Path>) -> io::Result<Vec<Opening>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let pgn = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"));
+    let invalid = |err: &dyn Display| io::Error::new(io::ErrorKind::InvalidData, err.to_string());
+    if pgn {
+        pgn_openings(&text).map_err(|err| invalid(&err))
+    } else {
+        epd_openings(&text).map_err(|err| invalid(&err))
+    }
+}
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoves,
+    Repetition,
+    InsufficientMaterial,
+    Tablebase,
+    TimeForfeit,
+    IllegalMove,
+    /// The engine exited or stopped answering.
+    Disconnect,
+}
+
+impl Termination {
+    /// The value of the PGN "Termination" tag.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Termination::Checkmate
+            | Termination::Stalemate
+            | Termination::FiftyMoves
+            | Termination::Repetition
+            | Termination::InsufficientMaterial => "normal",
+            Termination::Tablebase => "adjudication",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "rules infraction",
+            Termination::Disconnect => "abandoned",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::FiftyMoves => "fifty move rule",
+            Termination::Repetition => "threefold repetition",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Tablebase => "tablebase",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "illegal move",
+            Termination::Disconnect => "disconnect",
+        }
+    }
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+fn team_name(team: Team) -> &'static str {
+    match team {
+        Team::White => "White",
+        Team::Black => "Black",
+    }
+}
+
+fn loss(team: Team) -> GameResult {
+    match team {
+        Team::White => GameResult::BlackWins,
+        Team::Black => GameResult::WhiteWins,
+    }
+}
+
+/// The result of a finished game, from the rules or a tablebase probe.
+pub fn adjudicate(
+    chess: &mut Chess,
+    tablebase: Option<&Tablebase>,
+) -> Option<(GameResult, Termination)> {
+    if chess.legal_moves().is_empty() {
+        return Some(if chess.check_check(chess.player).is_some() {
+            (loss(chess.player), Termination::Checkmate)
+        } else {
+            (GameResult::Draw, Termination::Stalemate)
+        });
+    }
+    if chess.halfmove_clock >= 100 {
+        return Some((GameResult::Draw, Termination::FiftyMoves));
+    }
+    if chess.repetitions() >= 2 {
+        return Some((GameResult::Draw, Termination::Repetition));
+    }
+    if chess.insufficient_material() {
+        return Some((GameResult::Draw, Termination::InsufficientMaterial));
+    }
+    let tablebase = tablebase.filter(|tablebase| tablebase.can_probe(chess))?;
+    let result = match tablebase.probe_wdl(chess)? {
+        Wdl::Win => loss(!chess.player),
+        Wdl::Loss => loss(chess.player),
+        Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => GameResult::Draw,
+    };
+    Some((result, Termination::Tablebase))
+}
+
+/// Settings shared by all games of a match.
+#[derive(Debug, Clone)]
+pub struct GameSettings {
+    pub event: String,
+    pub tablebase: Option<Arc<Tablebase>>,
+    pub time_margin: Duration,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            event: "Engine match".to_string(),
+            tablebase: None,
+            time_margin: DEFAULT_TIME_MARGIN,
+        }
+    }
+}
+
+/// The "go" command for the player to move, with the clocks of both sides.
+fn go_command(time_control: &TimeControl, clocks: &[Option<Clock>; 2], player: Team) -> String {
+    match time_control {
+        TimeControl::Clock { .. } => {
+            let mut go = "go".to_string();
+            for (clock, (time, inc)) in clocks.iter().zip([("wtime", "winc"), ("btime", "binc")]) {
+                if let Some(clock) = clock {
+                    go += &format!(
+                        " {time} {} {inc} {}",
+                        clock.time.as_millis(),
+                        clock.inc.as_millis()
+                    );
+                }
+            }
+            let mover = clocks[player as usize].as_ref();
+            if let Some(moves) = mover.and_then(|clock| clock.moves_to_go) {
+                go += &format!(" movestogo {moves}");
+            }
+            go
+        }
+        TimeControl::MoveTime(time) => format!("go movetime {}", time.as_millis()),
+        TimeControl::Depth(depth) => format!("go depth {depth}"),
+        TimeControl::Nodes(nodes) => format!("go nodes {nodes}"),
+    }
+}
+
+/// Today as "YYYY.MM.DD" for the PGN "Date" tag.
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86400) as i64;
+    // days since 1970-01-01 to a civil date, counted in 400 year eras from March 1st
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+/// Plays one game from `opening`. The engines of `players` play white and black with
+/// their own time controls. Engine failures end the game as a loss of that engine.
+pub fn play_game(
+    players: [(&mut UciProcess, &TimeControl); 2],
+    opening: &Opening,
+    settings: &GameSettings,
+) -> (Game, Termination) {
+    let mut chess = opening.start_position();
+    let mut game = Game::default();
+    game.set_tag("Event", settings.event.as_str());
+    game.set_tag("Site", "?");
+    game.set_tag("Date", today());
+    game.set_tag("Round", "?");
+    game.set_tag("White", players[0].0.name.as_str());
+    game.set_tag("Black", players[1].0.name.as_str());
+    game.set_tag("Result", "*");
+    if let Some(fen) = &opening.fen {
+        game.set_tag("SetUp", "1");
+        game.set_tag("FEN", fen.as_str());
+    }
+    if players[0].1 == players[1].1 {
+        game.set_tag("TimeControl", players[0].1.to_string());
+    }
+
+    let position = match &opening.fen {
+        Some(fen) => format!("position fen {fen} moves"),
+        None => "position startpos moves".to_string(),
+    };
+    let mut moves = vec![];
+    for mv in &opening.moves {
+        let mut pgn_move = PgnMove::new(chess.to_san(*mv));
+        pgn_move.comment = Some("book".to_string());
+        game.moves.push(pgn_move);
+        chess.make_move(*mv);
+        moves.push(mv.to_string());
+    }
+
+    let [(white, white_tc), (black, black_tc)] = players;
+    let mut engines = [white, black];
+    let time_controls = [white_tc, black_tc];
+    let mut clocks = time_controls.map(TimeControl::start_clock);
+
+    let mut ended = None;
+    for (idx, engine) in engines.iter_mut().enumerate() {
+        if engine.new_game().is_err() {
+            let team = if idx == 0 { Team::White } else { Team::Black };
+            ended = Some((loss(team), Termination::Disconnect));
+            break;
+        }
+    }
+
+    let (result, termination) = loop {
+        if let Some(ended) = ended.or_else(|| adjudicate(&mut chess, settings.tablebase.as_deref()))
+        {
+            break ended;
+        }
+        let player = chess.player;
+        let idx = player as usize;
+        let time_control = time_controls[idx];
+        let go = go_command(time_control, &clocks, player);
+        let allowed = match (time_control, &clocks[idx]) {
+            (_, Some(clock)) => clock.time,
+            (TimeControl::MoveTime(time), _) => *time,
+            _ => SEARCH_TIMEOUT,
+        };
+
+        let reply = engines[idx].go(
+            &format!("{position} {}", moves.join(" ")),
+            &go,
+            allowed + settings.time_margin,
+        );
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                // the move may still arrive, it must not be read as the next one
+                engines[idx].send("stop").ok();
+                engines[idx].ready().ok();
+                ended = Some((loss(player), Termination::TimeForfeit));
+                continue;
+            }
+            Err(_) => {
+                ended = Some((loss(player), Termination::Disconnect));
+                continue;
+            }
+        };
+        if reply.time > allowed + settings.time_margin {
+            ended = Some((loss(player), Termination::TimeForfeit));
+            continue;
+        }
+        let Some(mv) = chess.parse_uci_move(&reply.mv) else {
+            ended = Some((loss(player), Termination::IllegalMove));
+            continue;
+        };
+
+        if let (Some(clock), TimeControl::Clock { moves, base, .. }) =
+            (&mut clocks[idx], time_control)
+        {
+            clock.time = clock.time.saturating_sub(reply.time) + clock.inc;
+            clock.moves_to_go = clock.moves_to_go.map(|left| left - 1);
+            if clock.moves_to_go == Some(0) {
+                clock.time += *base;
+                clock.moves_to_go = *moves;
+            }
+        }
+
+        let mut pgn_move = PgnMove::new(chess.to_san(mv));
+        pgn_move.comment = Some(match (reply.score, reply.depth) {
+            (Some(score), Some(depth)) => {
+                format!("{score}/{depth} {:.2}s", reply.time.as_secs_f64())
+            }
+            _ => format!("{:.2}s", reply.time.as_secs_f64()),
+        });
+        game.moves.push(pgn_move);
+        chess.make_move(mv);
+        moves.push(reply.mv);
+    };
+
+    let loser = match result {
+        GameResult::WhiteWins => Team::Black,
+        _ => Team::White,
+    };
+    let reason = match termination {
+        Termination::Checkmate => format!("{} mates", team_name(!loser)),
+        Termination::TimeForfeit => format!("{} loses on time", team_name(loser)),
+        Termination::IllegalMove => format!("{} makes an illegal move", team_name(loser)),
+        Termination::Disconnect => format!("{} disconnects", team_name(loser)),
+        Termination::Tablebase if result != GameResult::Draw => {
+            format!("{} wins by tablebase", team_name(!loser))
+        }
+        _ => format!("Draw by {termination}"),
+    };
+    if let Some(last) = game.moves.last_mut() {
+        last.comment = Some(match last.comment.take() {
+            Some(comment) => format!("{comment}, {reason}"),
+            None => reason,
+        });
+    }
+    game.result = result;
+    game.set_tag("Result", result.as_str());
+    game.set_tag("Termination", termination.tag());
+    (game, termination)
+}
+
+/// Wins, draws and losses of the first engine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.
+    }
+
+    /// Counts a game in which the first engine played `team`.
+    pub fn add(&mut self, result: GameResult, team: Team) {
+        match result.score(team) {
+            Some(1.) => self.wins += 1,
+            Some(0.) => self.losses += 1,
+            Some(_) => self.draws += 1,
+            None => {}
+        }
+    }
+}
+
+impl Display for MatchScore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} ={} -{} ({:.1}/{})",
+            self.wins,
+            self.draws,
+            self.losses,
+            self.points(),
+            self.games()
+        )
+    }
+}
+
+/// A game of a match, passed on as soon as it is finished.
+#[derive(Debug, Clone)]
+pub struct MatchGame {
+    /// Counted from 0.
+    pub number: usize,
+    pub game: Game,
+    pub termination: Termination,
+    /// The color of the first engine.
+    pub first_engine: Team,
+}
+
+/// Games between two engines. Every opening is played twice with swapped colors, the
+/// first engine starts with white.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub engines: [EngineConfig; 2],
+    /// Played in order and repeated once all were used. Empty for the start position.
+    pub openings: Vec<Opening>,
+    pub games: usize,
+    pub settings: GameSettings,
+}
+
+impl Match {
+    pub fn new(first: EngineConfig, second: EngineConfig) -> Self {
+        Match {
+            engines: [first, second],
+            openings: vec![],
+            games: 2,
+            settings: GameSettings::default(),
+        }
+    }
+
+    /// The opening and the color of the first engine in game `number`.
+    pub fn schedule(&self, number: usize) -> (Opening, Team) {
+        let opening = match self.openings.len() {
+            0 => Opening::default(),
+            len => self.openings[number / 2 % len].clone(),
+        };
+        let team = if number.is_multiple_of(2) {
+            Team::White
+        } else {
+            Team::Black
+        };
+        (opening, team)
+    }
+
+    /// Starts the engines and plays all games. `on_game` sees every finished game with the
+    /// score so far and stops the match by returning `false`. Engines that disconnect are
+    /// restarted for the next game.
+    pub fn run(
+        &self,
+        mut on_game: impl FnMut(&MatchGame, &MatchScore) -> bool,
+    ) -> io::Result<MatchScore> {
+        let mut processes = [
+            UciProcess::start(&self.engines[0])?,
+            UciProcess::start(&self.engines[1])?,
+        ];
+        let mut score = MatchScore::default();
+
+        for number in 0..self.games {
+            let (opening, first_engine) = self.schedule(number);
+            let [first, second] = &mut processes;
+            let [first_tc, second_tc] =
+                [&self.engines[0].time_control, &self.engines[1].time_control];
+            let players = match first_engine {
+                Team::White => [(first, first_tc), (second, second_tc)],
+                Team::Black => [(second, second_tc), (first, first_tc)],
+            };
+            let (mut game, termination) = play_game(players, &opening, &self.settings);
+            game.set_tag("Round", (number + 1).to_string());
+
+            if termination == Termination::Disconnect {
+                let loser = match game.result {
+                    GameResult::WhiteWins => Team::Black,
+                    _ => Team::White,
+                };
+                let idx = usize::from(loser != first_engine);
+                processes[idx] = UciProcess::start(&self.engines[idx])?;
+            }
+
+            score.add(game.result, first_engine);
+            let game = MatchGame {
+                number,
+                game,
+                termination,
+                first_engine,
+            };
+            if !on_game(&game, &score) {
+                break;
+            }
+        }
+        Ok(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        match_runner::{
+            adjudicate, epd_openings, go_command, pgn_openings, EngineConfig, EngineSettingError,
+            MatchScore, Termination, TimeControl,
+        },
+        pgn::GameResult,
+        search::Clock,
+        Chess, Move, Team,
+    };
+
+    #[test]
+    fn test_time_control() {
+        assert_eq!(
+            "40/60+0.6".parse(),
+            Ok(TimeControl::Clock {
+                moves: Some(40),
+                base: Duration::from_secs(60),
+                increment: Duration::from_millis(600),
+            })
+        );
+        assert_eq!(
+            "st=0.5".parse(),
+            Ok(TimeControl::MoveTime(Duration::from_millis(500)))
+        );
+        assert_eq!("depth=4".parse(), Ok(TimeControl::Depth(4)));
+        assert_eq!("nodes=1000".parse(), Ok(TimeControl::Nodes(1000)));
+        assert!("0/10".parse::<TimeControl>().is_err());
+        assert!("fast".parse::<TimeControl>().is_err());
+        for tc in ["40/60+0.6", "10", "st=0.1", "depth=3"] {
+            assert_eq!(tc.parse::<TimeControl>().unwrap().to_string(), tc);
+        }
+
+        let clock = Clock {
+            time: Duration::from_secs(10),
+            inc: Duration::from_millis(100),
+            moves_to_go: None,
+        };
+        assert_eq!(
+            go_command(
+                &"10+0.1".parse().unwrap(),
+                &[Some(clock), None],
+                Team::White
+            ),
+            "go wtime 10000 winc 100"
+        );
+        assert_eq!(
+            go_command(&TimeControl::Depth(3), &[Some(clock), None], Team::Black),
+            "go depth 3"
+        );
+    }
+
+    #[test]
+    fn test_parse_engine_settings() {
+        let (config, tc) = EngineConfig::parse_settings([
+            "cmd=./engine",
+            "name=Dev",
+            "arg=--uci",
+            "tc=depth=3",
+            "option.Hash=64",
+        ])
+        .unwrap();
+        assert_eq!(config.command, "./engine");
+        assert_eq!(config.name, "Dev");
+        assert_eq!(config.args, ["--uci"]);
+        assert_eq!(config.options, [("Hash".to_string(), "64".to_string())]);
+        assert_eq!(tc, Some(TimeControl::Depth(3)));
+
+        assert_eq!(
+            EngineConfig::parse_settings(["cmd=./engine"]).unwrap().1,
+            None
+        );
+        assert_eq!(
+            EngineConfig::parse_settings(["name=Dev"]),
+            Err(EngineSettingError::MissingCommand)
+        );
+        for setting in ["cmd", "hash=64", "tc=fast"] {
+            assert_eq!(
+                EngineConfig::parse_settings(["cmd=./engine", setting]),
+                Err(EngineSettingError::Invalid(setting.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_adjudication() {
+        let mut mate = Chess::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(
+            adjudicate(&mut mate, None),
+            Some((GameResult::WhiteWins, Termination::Checkmate))
+        );
+        let mut stalemate = Chess::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            adjudicate(&mut stalemate, None),
+            Some((GameResult::Draw, Termination::Stalemate))
+        );
+        let mut fifty = Chess::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(
+            adjudicate(&mut fifty, None),
+            Some((GameResult::Draw, Termination::FiftyMoves))
+        );
+
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(adjudicate(&mut chess, None), None);
+        for _ in 0..2 {
+            for mv in [((7, 0), (6, 0)), ((0, 4), (0, 3)), ((6, 0), (7, 0))] {
+                chess.make_move(Move::new(mv.0, mv.1));
+            }
+            chess.make_move(Move::new((0, 3), (0, 4)));
+        }
+        assert_eq!(
+            adjudicate(&mut chess, None),
+            Some((GameResult::Draw, Termination::Repetition))
+        );
+
+        for (fen, insufficient) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1", true),
+            ("2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1", true),
+            ("3bk3/8/8/8/8/8/8/3BK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ] {
+            let chess = Chess::from_fen(fen).unwrap();
+            assert_eq!(chess.insufficient_material(), insufficient, "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_openings() {
+        let openings =
+            epd_openings("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 id \"e4\";\n\n")
+                .unwrap();
+        assert_eq!(openings.len(), 1);
+        assert_eq!(
+            openings[0].start_position().to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert!(epd_openings("not a position").is_err());
+
+        let openings = pgn_openings("1. d4 d5 *\n\n1. e4 *").unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].fen, None);
+        assert_eq!(openings[0].moves.len(), 2);
+        assert_eq!(openings[1].moves, [Move::new((6, 4), (4, 4))]);
+    }
+
+    #[test]
+    fn test_match_score() {
+        let mut score = MatchScore::default();
+        score.add(GameResult::WhiteWins, Team::White);
+        score.add(GameResult::WhiteWins, Team::Black);
+        score.add(GameResult::Draw, Team::White);
+        score.add(GameResult::Unknown, Team::White);
+        assert_eq!(
+            score,
+            MatchScore {
+                wins: 1,
+                draws: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(score.to_string(), "+1 =1 -1 (1.5/3)");
+    }
+}
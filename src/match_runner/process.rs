@@ -0,0 +1,181 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::EngineConfig;
+
+/// Time an engine gets to answer "uci" and "isready".
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time an engine gets to exit after "quit" before it is killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The answer to a "go" command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineMove {
+    /// The move in long algebraic notation as sent by the engine.
+    pub mv: String,
+    /// Last reported score in centipawns from the engine's view, or the moves to mate.
+    pub score: Option<String>,
+    pub depth: Option<usize>,
+    pub time: Duration,
+}
+
+/// A UCI engine running as a child process. Its output is read on a separate thread,
+/// so that every wait can time out.
+#[derive(Debug)]
+pub struct UciProcess {
+    /// From the configuration or else the engine's "id name".
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciProcess {
+    /// Starts the engine, sets its options and waits until it is ready.
+    pub fn start(config: &EngineConfig) -> io::Result<UciProcess> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin should be piped");
+        let stdout = child.stdout.take().expect("stdout should be piped");
+
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciProcess {
+            name: config.name.clone(),
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                if engine.name.is_empty() {
+                    engine.name = name.trim().to_string();
+                }
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        for (name, value) in &config.options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+        engine.ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()
+    }
+
+    /// The next line of output, a `TimedOut` error after `deadline` and an
+    /// `UnexpectedEof` error once the engine exited.
+    fn read_line(&mut self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} did not answer in time", self.name),
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{} exited", self.name),
+            )),
+        }
+    }
+
+    pub fn ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.ready()
+    }
+
+    /// Sends `position` and `go`, both full UCI commands, and waits up to `timeout` for
+    /// the best move.
+    pub fn go(&mut self, position: &str, go: &str, timeout: Duration) -> io::Result<EngineMove> {
+        self.send(position)?;
+        let start = Instant::now();
+        self.send(go)?;
+
+        let deadline = start + timeout;
+        let (mut score, mut depth) = (None, None);
+        loop {
+            let line = self.read_line(deadline)?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    while let Some(token) = tokens.next() {
+                        match (token, tokens.clone().next()) {
+                            ("depth", Some(value)) => depth = value.parse().ok().or(depth),
+                            ("cp", Some(value)) => {
+                                if let Ok(cp) = value.parse::<i32>() {
+                                    score = Some(format!("{:+.2}", cp as f64 / 100.));
+                                }
+                            }
+                            ("mate", Some(value)) => {
+                                if let Ok(moves) = value.parse::<i32>() {
+                                    let sign = if moves < 0 { "-" } else { "+" };
+                                    score = Some(format!("{sign}M{}", moves.abs()));
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Some("bestmove") => {
+                    return Ok(EngineMove {
+                        mv: tokens.next().unwrap_or("0000").to_string(),
+                        score,
+                        depth,
+                        time: start.elapsed(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for UciProcess {
+    fn drop(&mut self) {
+        self.send("quit").ok();
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
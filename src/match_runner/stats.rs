@@ -0,0 +1,249 @@
+use std::fmt::{Display, Formatter};
+
+use super::MatchScore;
+
+/// Quantile of the normal distribution for two-sided 95% intervals.
+const Z_95: f64 = 1.959964;
+
+/// Expected score of a player `elo` points stronger than the opponent.
+pub fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+/// The Elo difference that gives the expected score `score`.
+pub fn elo_difference(score: f64) -> f64 {
+    400. * (score / (1. - score)).log10()
+}
+
+/// The error function, with an error below 1.2e-7 (Numerical Recipes, erfc).
+fn erf(x: f64) -> f64 {
+    let t = 1. / (1. + 0.5 * x.abs());
+    let polynomial = -x * x - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let erfc = t * polynomial.exp();
+    if x >= 0. {
+        1. - erfc
+    } else {
+        erfc - 1.
+    }
+}
+
+impl MatchScore {
+    /// Average points per game and their variance per game. The variance counts one
+    /// more win, draw and loss, as fishtest does, so that it is not 0 while all games
+    /// ended alike.
+    fn mean_and_variance(&self) -> (f64, f64) {
+        let mean = self.points() / self.games() as f64;
+        let variance = ((self.wins + 1) as f64 * (1. - mean).powi(2)
+            + (self.draws + 1) as f64 * (0.5 - mean).powi(2)
+            + (self.losses + 1) as f64 * mean.powi(2))
+            / (self.games() + 3) as f64;
+        (mean, variance)
+    }
+
+    /// Elo difference of the first engine with its 95% error margin, `None` before the
+    /// first game. Scores are kept half a point away from a perfect or zero score, whose
+    /// Elo difference would be infinite. The margin never shrinks to 0 since the
+    /// variance is regularised.
+    pub fn elo(&self) -> Option<EloEstimate> {
+        if self.games() == 0 {
+            return None;
+        }
+        let (mean, variance) = self.mean_and_variance();
+        let margin = Z_95 * (variance / self.games() as f64).sqrt();
+        let bound = 0.5 / self.games() as f64;
+        let elo = |score: f64| elo_difference(score.clamp(bound, 1. - bound));
+        let upper = elo(mean + margin);
+        let lower = elo(mean - margin);
+        Some(EloEstimate {
+            elo: elo(mean),
+            error: (upper - lower) / 2.,
+            los: self.los(),
+        })
+    }
+
+    /// Likelihood of superiority: the probability that the first engine is the stronger
+    /// one. Draws carry no information about it.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0. {
+            return 0.5;
+        }
+        0.5 * (1. + erf((self.wins as f64 - self.losses as f64) / (2. * decisive).sqrt()))
+    }
+}
+
+/// Elo difference of a match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    /// Half the width of the 95% confidence interval.
+    pub error: f64,
+    /// Likelihood of superiority, between 0 and 1.
+    pub los: f64,
+}
+
+impl Display for EloEstimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Elo {:+.1} +/- {:.1}, LOS {:.1}%",
+            self.elo,
+            self.error,
+            self.los * 100.
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    Continue,
+    /// The first engine is no more than `elo0` stronger.
+    AcceptH0,
+    /// The first engine is at least `elo1` stronger.
+    AcceptH1,
+}
+
+impl Display for SprtStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            SprtStatus::Continue => "continue",
+            SprtStatus::AcceptH0 => "H0 accepted",
+            SprtStatus::AcceptH1 => "H1 accepted",
+        };
+        write!(f, "{status}")
+    }
+}
+
+/// Sequential probability ratio test of H0 "the Elo difference is `elo0`" against H1
+/// "it is `elo1`". It is checked after every game and stops the match as soon as the
+/// log-likelihood ratio leaves the bounds given by the error rates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Chance of accepting H1 if H0 holds.
+    pub alpha: f64,
+    /// Chance of accepting H0 if H1 holds.
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.,
+            elo1: 5.,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// H0 is accepted once the log-likelihood ratio falls below this value.
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1. - self.alpha)).ln()
+    }
+
+    /// H1 is accepted once the log-likelihood ratio exceeds this value.
+    pub fn upper_bound(&self) -> f64 {
+        ((1. - self.beta) / self.alpha).ln()
+    }
+
+    /// Log-likelihood ratio of H1 against H0, in the normal approximation of the
+    /// trinomial distribution of game results.
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        if score.games() == 0 {
+            return 0.;
+        }
+        let (mean, variance) = score.mean_and_variance();
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (score1 - score0) * (2. * mean - score0 - score1) / (2. * variance) * score.games() as f64
+    }
+
+    pub fn status(&self, score: &MatchScore) -> SprtStatus {
+        let llr = self.llr(score);
+        if llr >= self.upper_bound() {
+            SprtStatus::AcceptH1
+        } else if llr <= self.lower_bound() {
+            SprtStatus::AcceptH0
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::match_runner::{
+        stats::{elo_difference, erf, expected_score},
+        MatchScore, Sprt, SprtStatus,
+    };
+
+    fn score(wins: usize, draws: usize, losses: usize) -> MatchScore {
+        MatchScore {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn test_elo() {
+        assert!((erf(1.) - 0.8427008).abs() < 1e-6);
+        assert!((erf(-0.5) + 0.5204999).abs() < 1e-6);
+        assert!((elo_difference(expected_score(120.)) - 120.).abs() < 1e-9);
+        assert_eq!(score(0, 0, 0).elo(), None);
+
+        let even = score(30, 40, 30).elo().unwrap();
+        assert!(even.elo.abs() < 1e-9);
+        assert!((even.los - 0.5).abs() < 1e-6);
+
+        let estimate = score(60, 20, 20).elo().unwrap();
+        assert!((estimate.elo - 147.19).abs() < 0.01);
+        assert!(estimate.error > 50. && estimate.error < 90.);
+        assert!(estimate.los > 0.999);
+
+        // counted as 9.5 points of 10, ten games are far from certain
+        let perfect = score(10, 0, 0).elo().unwrap();
+        assert!((perfect.elo - 511.5).abs() < 0.1);
+        assert!((perfect.error - 131.).abs() < 1.);
+        let drawn = score(0, 10, 0).elo().unwrap();
+        assert!(drawn.elo.abs() < 1e-9);
+        assert!((drawn.error - 86.2).abs() < 0.1);
+        assert_eq!(drawn.to_string(), "Elo +0.0 +/- 86.2, LOS 50.0%");
+        assert!((score(0, 0, 10).elo().unwrap().elo + perfect.elo).abs() < 1e-9);
+        let nearly = score(9, 1, 0).elo().unwrap();
+        assert!(nearly.elo.is_finite() && nearly.error.is_finite());
+        assert_eq!(
+            estimate.to_string(),
+            format!("Elo +147.2 +/- {:.1}, LOS 100.0%", estimate.error)
+        );
+    }
+
+    #[test]
+    fn test_sprt() {
+        let sprt = Sprt {
+            elo0: 0.,
+            elo1: 10.,
+            ..Default::default()
+        };
+        assert!((sprt.upper_bound() - 2.944).abs() < 0.001);
+        assert!((sprt.lower_bound() + 2.944).abs() < 0.001);
+
+        // a run of wins is conclusive after a few more games
+        assert_eq!(sprt.status(&score(20, 0, 0)), SprtStatus::Continue);
+        assert_eq!(sprt.status(&score(25, 0, 0)), SprtStatus::AcceptH1);
+        assert_eq!(sprt.status(&score(0, 0, 25)), SprtStatus::AcceptH0);
+        assert_eq!(sprt.status(&score(12, 10, 8)), SprtStatus::Continue);
+        assert_eq!(sprt.status(&score(400, 200, 200)), SprtStatus::AcceptH1);
+        assert_eq!(sprt.status(&score(5000, 5000, 5000)), SprtStatus::AcceptH0);
+        assert!(sprt.llr(&score(5000, 5000, 5000)) < 0.);
+    }
+}
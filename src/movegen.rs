@@ -0,0 +1,161 @@
+use crate::{Chess, FigureType, Move, Team};
+
+const PROMOTIONS: [FigureType; 4] = [
+    FigureType::Queen,
+    FigureType::Rook,
+    FigureType::Bishop,
+    FigureType::Knight,
+];
+
+impl Chess {
+    /// Moves of the current player built on `Figure::valid_moves`, the king may still be
+    /// left in check. Pawn moves onto the last row are expanded into every promotion.
+    pub fn pseudo_legal_moves(&mut self) -> Vec<Move> {
+        let mut moves = vec![];
+
+        for field in self.fields.iter().flatten() {
+            let Some(figure) = field.figure else {
+                continue;
+            };
+            if figure.team != self.player {
+                continue;
+            }
+
+            let mut targets = figure.valid_moves(field.idxs, &self.fields, &self.en_passants);
+            // the double pawn move may be reported twice
+            targets.sort_unstable();
+            targets.dedup();
+
+            for to in targets {
+                let last_row = match figure.team {
+                    Team::White => 0,
+                    Team::Black => 7,
+                };
+                if figure.figure == FigureType::Pawn && to.0 == last_row {
+                    moves.extend(
+                        PROMOTIONS
+                            .iter()
+                            .map(|promotion| Move::with_promotion(field.idxs, to, *promotion)),
+                    );
+                } else {
+                    moves.push(Move::new(field.idxs, to));
+                }
+            }
+        }
+
+        moves.extend(self.rochade_moves());
+        moves
+    }
+
+    /// All moves of the current player that do not leave the own king in check.
+    pub fn legal_moves(&mut self) -> Vec<Move> {
+        let team = self.player;
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|mv| {
+            self.make_move(*mv);
+            let legal = self.check_check(team).is_none();
+            self.unmake_move();
+            legal
+        });
+        moves
+    }
+
+    /// Finds the legal move written in long algebraic notation, e.g. "e2e4" or "e7e8q".
+    pub fn parse_uci_move(&mut self, uci: &str) -> Option<Move> {
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.to_string() == uci)
+    }
+
+    /// Whether `mv` captures a figure, including en passant captures.
+    pub fn is_capture(&self, mv: Move) -> bool {
+        if self.field(mv.to()).figure.is_some() {
+            return true;
+        }
+        matches!(self.field(mv.from()).figure, Some(figure) if figure.figure == FigureType::Pawn)
+            && mv.start_col != mv.end_col
+    }
+
+    /// Legal captures and queen promotions, the moves considered by the quiescence search.
+    pub fn legal_tactical_moves(&mut self) -> Vec<Move> {
+        let team = self.player;
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|mv| {
+            if !self.is_capture(*mv) && mv.promotion != FigureType::Queen {
+                return false;
+            }
+            self.make_move(*mv);
+            let legal = self.check_check(team).is_none();
+            self.unmake_move();
+            legal
+        });
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chess, FigureType, Move};
+
+    fn perft(chess: &mut Chess, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for mv in chess.legal_moves() {
+            chess.make_move(mv);
+            assert_eq!(chess.hash, chess.compute_hash());
+            nodes += perft(chess, depth - 1);
+            chess.unmake_move();
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_perft_start_position() {
+        let mut chess = Chess::new(None);
+        assert_eq!(perft(&mut chess, 1), 20);
+        assert_eq!(perft(&mut chess, 2), 400);
+        assert_eq!(perft(&mut chess, 3), 8902);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        // rochades, promotions and en passant captures
+        let mut chess =
+            Chess::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(perft(&mut chess, 1), 48);
+        assert_eq!(perft(&mut chess, 2), 2039);
+    }
+
+    #[test]
+    fn test_uci_moves() {
+        let mut chess = Chess::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            chess.parse_uci_move("b7b8n"),
+            Some(Move::with_promotion((1, 1), (0, 1), FigureType::Knight))
+        );
+        assert_eq!(
+            chess.parse_uci_move("e1g1").unwrap().to_string(),
+            "e1g1".to_string()
+        );
+        assert_eq!(chess.parse_uci_move("b7b8"), None);
+        assert_eq!(chess.parse_uci_move("e1e3"), None);
+    }
+
+    #[test]
+    fn test_rochade_moves() {
+        let mut chess = Chess::new(None);
+        for pos in [(7, 5), (7, 6)] {
+            chess.field_mut(pos).figure = None;
+        }
+        chess.refresh_hash();
+
+        assert!(chess.legal_moves().contains(&Move::new((7, 4), (7, 6))));
+
+        chess.make_move(Move::new((7, 4), (7, 6)));
+        assert!(chess.fields[7][5].figure.is_some());
+        assert_eq!(chess.hash, chess.compute_hash());
+    }
+}
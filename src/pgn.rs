@@ -0,0 +1,425 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    fen::{FenError, START_FEN},
+    Chess, Move, Team,
+};
+
+/// Movetext lines of written games are wrapped at this width, as the PGN standard asks.
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// A tag pair that is not of the form `[Name "value"]`.
+    InvalidTag(String),
+    InvalidFen(FenError),
+    /// The SAN move at `ply` (counted from 0) is not legal in its position.
+    IllegalMove {
+        ply: usize,
+        san: String,
+    },
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag {tag}"),
+            PgnError::InvalidFen(err) => write!(f, "invalid FEN tag: {err}"),
+            PgnError::IllegalMove { ply, san } => write!(f, "illegal move {san} at ply {ply}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(err: FenError) -> Self {
+        PgnError::InvalidFen(err)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// "*", the game is unfinished or the result is unknown.
+    #[default]
+    Unknown,
+}
+
+impl GameResult {
+    pub fn parse(result: &str) -> Option<GameResult> {
+        match result {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+
+    /// Points of `team`: 1 for a win, 0.5 for a draw. `None` if the result is unknown.
+    pub fn score(&self, team: Team) -> Option<f64> {
+        match (self, team) {
+            (GameResult::WhiteWins, Team::White) | (GameResult::BlackWins, Team::Black) => Some(1.),
+            (GameResult::WhiteWins, Team::Black) | (GameResult::BlackWins, Team::White) => Some(0.),
+            (GameResult::Draw, _) => Some(0.5),
+            (GameResult::Unknown, _) => None,
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A move of the main line together with its annotations.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    /// Numeric annotation glyphs, e.g. 2 for "?" or 4 for "??".
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+}
+
+impl PgnMove {
+    pub fn new(san: impl Into<String>) -> Self {
+        PgnMove {
+            san: san.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A single game of a PGN file. Variations are skipped while reading, only the main
+/// line is kept.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl Game {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the value of the tag `name` or appends the tag.
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value,
+            None => self.tags.push((name.to_string(), value)),
+        }
+    }
+
+    /// The position of the "FEN" tag or the start position.
+    pub fn start_position(&self) -> Result<Chess, PgnError> {
+        Ok(Chess::from_fen(self.tag("FEN").unwrap_or(START_FEN))?)
+    }
+
+    /// Plays through the main line, returns the start position and the parsed moves.
+    pub fn replay(&self) -> Result<(Chess, Vec<Move>), PgnError> {
+        let start = self.start_position()?;
+        let mut chess = start.clone();
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for (ply, pgn_move) in self.moves.iter().enumerate() {
+            let mv = chess
+                .parse_san(&pgn_move.san)
+                .ok_or_else(|| PgnError::IllegalMove {
+                    ply,
+                    san: pgn_move.san.clone(),
+                })?;
+            chess.make_move(mv);
+            moves.push(mv);
+        }
+        Ok((start, moves))
+    }
+
+    /// Writes the game in export format: tags, an empty line and the wrapped movetext.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{name} \"{value}\"]\n"));
+        }
+        pgn.push('\n');
+
+        let (mut number, mut player) = self
+            .start_position()
+            .map(|chess| (chess.fullmove_number, chess.player))
+            .unwrap_or((1, Team::White));
+
+        let mut tokens = vec![];
+        for (ply, pgn_move) in self.moves.iter().enumerate() {
+            match player {
+                Team::White => tokens.push(format!("{number}.")),
+                Team::Black if ply == 0 => tokens.push(format!("{number}...")),
+                Team::Black => {}
+            }
+            tokens.push(pgn_move.san.clone());
+            tokens.extend(pgn_move.nags.iter().map(|nag| format!("${nag}")));
+            if let Some(comment) = &pgn_move.comment {
+                tokens.push(format!("{{{}}}", comment.replace('}', "")));
+            }
+            if player == Team::Black {
+                number += 1;
+            }
+            player = !player;
+        }
+        tokens.push(self.result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push('\n');
+        pgn
+    }
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+/// "Nf3!?" is read as "Nf3" with the glyph 5.
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    let nag = match suffix {
+        "!" => 1,
+        "?" => 2,
+        "!!" => 3,
+        "??" => 4,
+        "!?" => 5,
+        "?!" => 6,
+        _ => return None,
+    };
+    Some(nag)
+}
+
+fn parse_tag(line: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(line.to_string());
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+    let (name, value) = inner
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    Ok((
+        name.to_string(),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+/// Reads movetext into `game`. A result token ends the game, it is moved to `games`
+/// and the rest of the text belongs to the next game.
+fn parse_movetext(text: &str, game: &mut Game, games: &mut Vec<Game>) {
+    let mut chars = text.chars().peekable();
+    let mut depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let comment = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                if depth == 0 {
+                    if let Some(last) = game.moves.last_mut() {
+                        last.comment = Some(match last.comment.take() {
+                            Some(old) => format!("{old} {comment}"),
+                            None => comment,
+                        });
+                    }
+                }
+            }
+            ';' => {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+            }
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "{}();".contains(*next) {
+                        break;
+                    }
+                    token.push(*next);
+                    chars.next();
+                }
+                if depth > 0 {
+                    continue;
+                }
+                if let Some(result) = GameResult::parse(&token) {
+                    game.result = result;
+                    games.push(std::mem::take(game));
+                    continue;
+                }
+                parse_token(&token, game);
+            }
+        }
+    }
+}
+
+fn parse_token(token: &str, game: &mut Game) {
+    if let Some(nag) = token.strip_prefix('$') {
+        if let (Ok(nag), Some(last)) = (nag.parse(), game.moves.last_mut()) {
+            last.nags.push(nag);
+        }
+        return;
+    }
+    // move numbers like "12." or "12..." possibly glued to the move, but not "0-0"
+    let number = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    let token = match number.strip_prefix('.') {
+        Some(rest) => rest.trim_start_matches('.'),
+        None => token,
+    };
+    if token.is_empty() {
+        return;
+    }
+    let san = token.trim_end_matches(['!', '?']);
+    let mut pgn_move = PgnMove::new(san);
+    pgn_move.nags.extend(suffix_nag(&token[san.len()..]));
+    game.moves.push(pgn_move);
+}
+
+/// Reads all games of a PGN collection. Moves are not checked for legality here, see
+/// `Game::replay`.
+pub fn parse_pgn(text: &str) -> Result<Vec<Game>, PgnError> {
+    let mut games = vec![];
+    let mut game = Game::default();
+    let mut movetext = String::new();
+
+    let mut finish = |game: &mut Game, movetext: &mut String| {
+        parse_movetext(movetext, game, &mut games);
+        // a game without a result token
+        if !game.tags.is_empty() || !game.moves.is_empty() {
+            games.push(std::mem::take(game));
+        }
+        movetext.clear();
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && !movetext.trim().is_empty() {
+            // a tag after movetext starts the next game
+            finish(&mut game, &mut movetext);
+        }
+        if trimmed.starts_with('[') {
+            game.tags.push(parse_tag(trimmed)?);
+        } else if !trimmed.starts_with('%') {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    finish(&mut game, &mut movetext);
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pgn::{parse_pgn, Game, GameResult, PgnError, PgnMove},
+        Move,
+    };
+
+    const GAMES: &str = r#"[Event "Casual"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {develops} Nc6 (2... d6 3. d4) 3. Bc4 $1 Nd4?
+4. Nxe5 ; a trap
+Qg5 5. Nxf7 Qxg2 6. Rf1 Qxe4+ 7. Be2 Nf3# 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12"]
+
+12... Kd7 13. e4 *
+"#;
+
+    #[test]
+    fn test_parse_games() {
+        let games = parse_pgn(GAMES).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("A"));
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.moves.len(), 14);
+        assert_eq!(game.moves[2].comment.as_deref(), Some("develops"));
+        assert_eq!(game.moves[4].nags, vec![1]);
+        assert_eq!(game.moves[5].san, "Nd4");
+        assert_eq!(game.moves[5].nags, vec![2]);
+
+        let (_, moves) = game.replay().unwrap();
+        assert_eq!(moves[0], Move::new((6, 4), (4, 4)));
+
+        let game = &games[1];
+        assert_eq!(game.result, GameResult::Unknown);
+        assert_eq!(game.replay().unwrap().1.len(), 2);
+    }
+
+    #[test]
+    fn test_illegal_move() {
+        let games = parse_pgn("1. e4 e4 *").unwrap();
+        assert_eq!(
+            games[0].replay().unwrap_err(),
+            PgnError::IllegalMove {
+                ply: 1,
+                san: "e4".to_string()
+            }
+        );
+        assert!(parse_pgn("[Event Casual]\n1. e4 *").is_err());
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let games = parse_pgn(GAMES).unwrap();
+        for game in games {
+            let written = game.to_pgn();
+            assert!(written.lines().all(|line| line.len() <= 80));
+            assert_eq!(parse_pgn(&written).unwrap(), vec![game]);
+        }
+
+        let mut game = Game {
+            result: GameResult::Draw,
+            ..Default::default()
+        };
+        game.set_tag("FEN", "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12");
+        game.moves.push(PgnMove::new("Kd7"));
+        assert!(game.to_pgn().ends_with("\n12... Kd7 1/2-1/2\n"));
+    }
+}
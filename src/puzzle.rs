@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use crate::{
+    eval::figure_value,
+    pgn::{Game, PgnError},
+    search::{mate_in, SearchLimits, Searcher, DEFAULT_HASH_MB, MATE_BOUND},
+    Chess, FigureType, Move,
+};
+
+/// The best move has to leave the solver at least this much ahead.
+pub const WINNING_SCORE: i32 = 200;
+/// Every other move has to be at least this much worse than the solution.
+pub const MIN_GAP: i32 = 300;
+/// Scores from which a puzzle is tagged "crushing" rather than "advantage".
+const CRUSHING_SCORE: i32 = 600;
+/// Most moves of the solver in one puzzle.
+pub const MAX_SOLUTION_MOVES: usize = 4;
+pub const DEFAULT_PUZZLE_DEPTH: usize = 5;
+
+/// Unrated puzzles start with the rating and deviation Lichess gives new puzzles.
+const DEFAULT_RATING: u32 = 1500;
+const DEFAULT_RATING_DEVIATION: u32 = 500;
+
+/// Columns of the Lichess puzzle database.
+pub const CSV_HEADER: &str =
+    "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags";
+
+/// A position where exactly one move wins, in the layout of the Lichess puzzle database:
+/// the position is the one before the opponent's mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub id: String,
+    pub fen: String,
+    /// The opponent's move, then the solution: moves of the solver alternating with the
+    /// best replies.
+    pub moves: Vec<Move>,
+    /// Lichess theme names, e.g. "mateIn2", "fork" or "short".
+    pub themes: Vec<&'static str>,
+    pub game_url: Option<String>,
+}
+
+impl Puzzle {
+    pub fn solution(&self) -> &[Move] {
+        &self.moves[1..]
+    }
+
+    /// One line of the puzzle CSV, see `CSV_HEADER`.
+    pub fn to_csv(&self) -> String {
+        let moves = self
+            .moves
+            .iter()
+            .map(|mv| mv.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let game_url = self.game_url.as_deref().unwrap_or_default();
+        let game_url = if game_url.contains([',', '"']) {
+            format!("\"{}\"", game_url.replace('"', "\"\""))
+        } else {
+            game_url.to_string()
+        };
+        format!(
+            "{},{},{moves},{DEFAULT_RATING},{DEFAULT_RATING_DEVIATION},0,0,{},{game_url},",
+            self.id,
+            self.fen,
+            self.themes.join(" ")
+        )
+    }
+}
+
+/// Whether the best move is the only good one. After the first move of a puzzle any
+/// mate in one is accepted.
+fn is_unique(best: i32, second: Option<i32>, first: bool) -> bool {
+    if best < WINNING_SCORE {
+        return false;
+    }
+    if !first && mate_in(best) == Some(1) {
+        return true;
+    }
+    second.is_none_or(|second| {
+        second <= best - MIN_GAP && !(best >= MATE_BOUND && second >= MATE_BOUND)
+    })
+}
+
+/// Non-pawn figures attacked by the figure on `from`.
+fn attacked_figures(chess: &Chess, from: (usize, usize)) -> Vec<FigureType> {
+    let Some(figure) = chess.field(from).figure else {
+        return vec![];
+    };
+    figure
+        .valid_moves(from, &chess.fields, &HashMap::new())
+        .into_iter()
+        .filter_map(|to| chess.field(to).figure)
+        .filter(|target| target.team != figure.team && target.figure != FigureType::Pawn)
+        .map(|target| target.figure)
+        .collect()
+}
+
+/// A figure forking the king and another figure, or two figures worth more than itself.
+fn is_fork(chess: &Chess, at: (usize, usize)) -> bool {
+    let Some(forker) = chess.field(at).figure else {
+        return false;
+    };
+    let targets = attacked_figures(chess, at);
+    forker.figure != FigureType::King
+        && targets.len() >= 2
+        && (targets.contains(&FigureType::King)
+            || targets
+                .iter()
+                .all(|target| figure_value(*target) > figure_value(forker.figure)))
+}
+
+/// Fields of the figures of the player to move that cannot leave their line to the king.
+fn pinned_figures(chess: &Chess) -> Vec<(usize, usize)> {
+    let team = chess.player;
+    if chess.check_check(team).is_some() {
+        return vec![];
+    }
+    chess
+        .fields
+        .iter()
+        .flatten()
+        .filter_map(|field| Some((field.idxs, field.figure?)))
+        .filter(|(_, figure)| figure.team == team && figure.figure != FigureType::King)
+        .filter(|(pos, _)| {
+            let mut without = chess.clone();
+            without.field_mut(*pos).figure = None;
+            without.check_check(team).is_some()
+        })
+        .map(|(pos, _)| pos)
+        .collect()
+}
+
+/// Themes of the solution `line` played from `chess`, `score` is the score of its first
+/// move.
+fn themes(chess: &mut Chess, line: &[Move], score: i32) -> Vec<&'static str> {
+    let mut themes = vec![];
+    match mate_in(score) {
+        Some(moves) => {
+            themes.push("mate");
+            themes.push(match moves {
+                1 => "mateIn1",
+                2 => "mateIn2",
+                3 => "mateIn3",
+                4 => "mateIn4",
+                _ => "mateIn5",
+            });
+        }
+        None if score >= CRUSHING_SCORE => themes.push("crushing"),
+        None => themes.push("advantage"),
+    }
+    themes.push(match line.len().div_ceil(2) {
+        1 => "oneMove",
+        2 => "short",
+        3 => "long",
+        _ => "veryLong",
+    });
+
+    if line
+        .iter()
+        .step_by(2)
+        .any(|mv| mv.promotion != FigureType::Empty)
+    {
+        themes.push("promotion");
+    }
+    let pinned = pinned_figures(&{
+        let mut opponent = chess.clone();
+        opponent.player = !opponent.player;
+        opponent
+    });
+    chess.make_move(line[0]);
+    // a mating move is no fork or pin
+    if !chess.legal_moves().is_empty() {
+        if is_fork(chess, line[0].to()) {
+            themes.push("fork");
+        }
+        if pinned_figures(chess)
+            .iter()
+            .any(|pos| !pinned.contains(pos))
+        {
+            themes.push("pin");
+        }
+    }
+    chess.unmake_move();
+    themes
+}
+
+/// Scans games for positions with exactly one winning move. Every candidate is
+/// searched with two lines, the puzzle is kept if the second best move is clearly worse.
+#[derive(Debug)]
+pub struct PuzzleMiner {
+    searcher: Searcher,
+    pub limits: SearchLimits,
+    /// Positions before this ply are skipped, they are usually known opening theory.
+    pub min_ply: usize,
+}
+
+impl Default for PuzzleMiner {
+    fn default() -> Self {
+        PuzzleMiner::new(SearchLimits::depth(DEFAULT_PUZZLE_DEPTH))
+    }
+}
+
+impl PuzzleMiner {
+    pub fn new(limits: SearchLimits) -> Self {
+        let mut searcher = Searcher::new(DEFAULT_HASH_MB);
+        searcher.multipv = 2;
+        PuzzleMiner {
+            searcher,
+            limits,
+            min_ply: 0,
+        }
+    }
+
+    /// The best move with its score and the score of the second best move.
+    fn best_moves(&mut self, chess: &mut Chess) -> Option<(Move, i32, Option<i32>)> {
+        if chess.legal_moves().is_empty() {
+            return None;
+        }
+        let result = self.searcher.search(chess, self.limits);
+        let best = result.lines.first()?;
+        Some((
+            best.best_move()?,
+            best.score,
+            result.lines.get(1).map(|line| line.score),
+        ))
+    }
+
+    /// The solution and its score if exactly one move of the player to move wins. The
+    /// line is extended as long as the solver's moves stay unique, mates have to be
+    /// played out to the end.
+    pub fn solve(&mut self, chess: &mut Chess) -> Option<(Vec<Move>, i32)> {
+        let (first, score, second) = self.best_moves(chess)?;
+        if !is_unique(score, second, true) {
+            return None;
+        }
+
+        let mut line = vec![first];
+        chess.make_move(first);
+        while line.len().div_ceil(2) < MAX_SOLUTION_MOVES {
+            let Some((reply, _, _)) = self.best_moves(chess) else {
+                break;
+            };
+            chess.make_move(reply);
+            match self.best_moves(chess) {
+                Some((mv, score, second)) if is_unique(score, second, false) => {
+                    chess.make_move(mv);
+                    line.extend([reply, mv]);
+                }
+                _ => {
+                    chess.unmake_move();
+                    break;
+                }
+            }
+        }
+        let mated = chess.legal_moves().is_empty() && chess.check_check(chess.player).is_some();
+        for _ in 0..line.len() {
+            chess.unmake_move();
+        }
+
+        (mate_in(score).is_none() || mated).then_some((line, score))
+    }
+
+    /// All puzzles of the game. Positions inside the solution of a found puzzle are not
+    /// searched again.
+    pub fn mine(&mut self, game: &Game) -> Result<Vec<Puzzle>, PgnError> {
+        let (mut chess, moves) = game.replay()?;
+        self.searcher.new_game();
+        let game_url = game
+            .tag("Site")
+            .filter(|site| site.starts_with("http"))
+            .map(str::to_string);
+
+        let mut puzzles = vec![];
+        let mut next_ply = self.min_ply.max(1);
+        for (ply, mv) in moves.iter().enumerate() {
+            let before = chess.clone();
+            chess.make_move(*mv);
+            if ply + 1 < next_ply {
+                continue;
+            }
+            let Some((line, score)) = self.solve(&mut chess) else {
+                continue;
+            };
+
+            next_ply = ply + 1 + line.len();
+            let themes = themes(&mut chess, &line, score);
+            let mut puzzle_moves = vec![*mv];
+            puzzle_moves.extend(line);
+            puzzles.push(Puzzle {
+                id: format!("{:016x}", before.hash),
+                fen: before.to_fen(),
+                moves: puzzle_moves,
+                themes,
+                game_url: game_url.clone(),
+            });
+        }
+        Ok(puzzles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        pgn::parse_pgn,
+        puzzle::{PuzzleMiner, CSV_HEADER},
+        search::SearchLimits,
+        Move,
+    };
+
+    #[test]
+    fn test_mate_puzzle() {
+        let games = parse_pgn(
+            "[Site \"https://example.org/game/1\"]\n\n\
+             1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0",
+        )
+        .unwrap();
+        let mut miner = PuzzleMiner::new(SearchLimits::depth(3));
+        let puzzles = miner.mine(&games[0]).unwrap();
+
+        let puzzle = puzzles.last().unwrap();
+        assert_eq!(puzzle.solution(), [Move::new((3, 7), (1, 5))]);
+        assert_eq!(puzzle.themes, ["mate", "mateIn1", "oneMove"]);
+        assert_eq!(
+            puzzle.to_csv(),
+            format!(
+                "{},r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3,\
+                 g8f6 h5f7,1500,500,0,0,mate mateIn1 oneMove,https://example.org/game/1,",
+                puzzle.id
+            )
+        );
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            puzzle.to_csv().split(',').count()
+        );
+    }
+
+    #[test]
+    fn test_fork_puzzle() {
+        let games = parse_pgn(
+            "[FEN \"4k3/8/8/2q5/4N3/8/8/4K3 b - - 0 1\"]\n\n1... Qb5 2. Nd6+ Kd7 3. Nxb5 1-0",
+        )
+        .unwrap();
+        let mut miner = PuzzleMiner::new(SearchLimits::depth(3));
+        let puzzles = miner.mine(&games[0]).unwrap();
+
+        assert_eq!(puzzles.len(), 1);
+        let puzzle = &puzzles[0];
+        assert_eq!(puzzle.moves[0], Move::new((3, 2), (3, 1)));
+        assert_eq!(puzzle.solution()[0], Move::new((4, 4), (2, 3)));
+        assert_eq!(puzzle.solution()[2], Move::new((2, 3), (3, 1)));
+        assert!(puzzle.themes.contains(&"fork"));
+        assert!(puzzle.themes.contains(&"short"));
+    }
+}
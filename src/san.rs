@@ -0,0 +1,192 @@
+use crate::{
+    fen::{field_name, figure_to_char},
+    Chess, Figure, FigureType, Move, Team,
+};
+
+fn figure_letter(figure: FigureType) -> char {
+    figure_to_char(Figure {
+        figure,
+        team: Team::White,
+        first_move: false,
+    })
+}
+
+/// Drops check and annotation suffixes as well as the "=" of promotions, so that
+/// "exd8=Q+!" and "exd8Q" compare equal.
+fn normalize(san: &str) -> String {
+    san.trim_end_matches(['+', '#', '!', '?'])
+        .replace('0', "O")
+        .replace('=', "")
+}
+
+impl Chess {
+    /// Standard algebraic notation of the legal move `mv` without the check suffix,
+    /// `legal` are all legal moves of the position.
+    fn san_without_check(&self, mv: Move, legal: &[Move]) -> String {
+        let moved = self
+            .field(mv.from())
+            .figure
+            .expect("A figure should be on the start field of the move.");
+
+        if moved.figure == FigureType::King && mv.start_col.abs_diff(mv.end_col) == 2 {
+            return if mv.end_col > mv.start_col {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            };
+        }
+
+        let mut san = String::new();
+        let capture = self.is_capture(mv);
+
+        if moved.figure == FigureType::Pawn {
+            if capture {
+                san.push((b'a' + mv.start_col as u8) as char);
+            }
+        } else {
+            san.push(figure_letter(moved.figure));
+
+            // other figures of the same type that can reach the target field
+            let rivals = legal
+                .iter()
+                .filter(|other| {
+                    other.to() == mv.to()
+                        && other.from() != mv.from()
+                        && self.field(other.from()).figure.map(|figure| figure.figure)
+                            == Some(moved.figure)
+                })
+                .collect::<Vec<_>>();
+
+            if !rivals.is_empty() {
+                let from = field_name(mv.from());
+                if rivals.iter().all(|other| other.start_col != mv.start_col) {
+                    san.push_str(&from[..1]);
+                } else if rivals.iter().all(|other| other.start_row != mv.start_row) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&field_name(mv.to()));
+
+        if mv.promotion != FigureType::Empty {
+            san.push('=');
+            san.push(figure_letter(mv.promotion));
+        }
+        san
+    }
+
+    /// Standard algebraic notation of the legal move `mv`, e.g. "Nbd7", "exd6" or "O-O+".
+    pub fn to_san(&mut self, mv: Move) -> String {
+        let legal = self.legal_moves();
+        let mut san = self.san_without_check(mv, &legal);
+
+        self.make_move(mv);
+        if self.check_check(self.player).is_some() {
+            san.push(if self.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        self.unmake_move();
+        san
+    }
+
+    /// A line of legal moves from the current position with move numbers, e.g.
+    /// "12... Nf6 13. e5".
+    pub fn san_line(&mut self, moves: &[Move]) -> String {
+        let mut line = vec![];
+        for (ply, mv) in moves.iter().enumerate() {
+            match self.player {
+                Team::White => line.push(format!("{}.", self.fullmove_number)),
+                Team::Black if ply == 0 => line.push(format!("{}...", self.fullmove_number)),
+                Team::Black => {}
+            }
+            line.push(self.to_san(*mv));
+            self.make_move(*mv);
+        }
+        for _ in moves {
+            self.unmake_move();
+        }
+        line.join(" ")
+    }
+
+    /// Finds the legal move written in standard algebraic notation. Check and annotation
+    /// suffixes are optional, castling may also be written with zeros.
+    pub fn parse_san(&mut self, san: &str) -> Option<Move> {
+        let san = normalize(san.trim());
+        let legal = self.legal_moves();
+        legal
+            .iter()
+            .find(|mv| normalize(&self.san_without_check(**mv, &legal)) == san)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chess, FigureType, Move};
+
+    #[test]
+    fn test_san_of_basic_moves() {
+        let mut chess = Chess::new(None);
+        assert_eq!(chess.to_san(Move::new((6, 4), (4, 4))), "e4");
+        assert_eq!(chess.to_san(Move::new((7, 6), (5, 5))), "Nf3");
+
+        let mut chess = Chess::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        assert_eq!(chess.to_san(Move::new((3, 4), (2, 3))), "exd6");
+        assert_eq!(chess.to_san(Move::new((7, 4), (7, 6))), "O-O");
+        assert_eq!(chess.to_san(Move::new((7, 4), (7, 2))), "O-O-O");
+        assert_eq!(chess.to_san(Move::new((7, 0), (0, 0))), "Rxa8+");
+    }
+
+    #[test]
+    fn test_san_disambiguation_and_promotion() {
+        let mut chess = Chess::from_fen("4k3/1P6/8/8/8/N7/8/N3K3 w - - 0 1").unwrap();
+        assert_eq!(chess.to_san(Move::new((5, 0), (6, 2))), "N3c2");
+        let promotion = Move::with_promotion((1, 1), (0, 1), FigureType::Queen);
+        assert_eq!(chess.to_san(promotion), "b8=Q+");
+
+        let mut chess = Chess::from_fen("4k3/8/8/8/8/8/8/1N3N1K w - - 0 1").unwrap();
+        assert_eq!(chess.to_san(Move::new((7, 1), (6, 3))), "Nbd2");
+    }
+
+    #[test]
+    fn test_san_mate() {
+        let mut chess = Chess::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(chess.to_san(Move::new((7, 0), (0, 0))), "Ra8#");
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let mut chess = Chess::new(None);
+        assert_eq!(chess.parse_san("e4"), Some(Move::new((6, 4), (4, 4))));
+        assert_eq!(chess.parse_san("Nf3!?"), Some(Move::new((7, 6), (5, 5))));
+        assert_eq!(chess.parse_san("e5"), None);
+        assert_eq!(chess.parse_san("Ke2"), None);
+
+        let mut chess = Chess::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(chess.parse_san("0-0"), Some(Move::new((7, 4), (7, 6))));
+        assert_eq!(
+            chess.parse_san("bxa8Q+"),
+            Some(Move::with_promotion((1, 1), (0, 0), FigureType::Queen))
+        );
+    }
+
+    #[test]
+    fn test_san_line() {
+        let mut chess = Chess::new(None);
+        let moves = [Move::new((6, 4), (4, 4)), Move::new((1, 4), (3, 4))];
+        assert_eq!(chess.san_line(&moves), "1. e4 e5");
+        assert!(chess.history.is_empty());
+
+        chess.make_move(moves[0]);
+        assert_eq!(chess.san_line(&moves[1..]), "1... e5");
+    }
+}
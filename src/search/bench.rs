@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use crate::Chess;
+
+use super::{SearchLimits, SearchResult, Searcher};
+
+pub const DEFAULT_BENCH_DEPTH: usize = 4;
+
+/// Positions searched by `bench`, a mix of openings, middlegames and endgames.
+pub const BENCH_FENS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r1bq1rk1/pp2bppp/2n1pn2/2pp4/2PP4/2N1PN2/PP2BPPP/R1BQK2R w KQ - 0 8",
+    "2r3k1/pp3ppp/4p3/3pP3/3P1P2/P7/1P4PP/2R3K1 w - - 0 25",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/8/4k3/8/2K5/3P4/8/8 w - - 0 1",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-9)) as u64
+    }
+}
+
+/// Searches every bench position to a fixed depth with a fresh hash table. The node
+/// count only changes if the search itself changes, which makes it a signature for
+/// comparing search changes. `report` is called after every position.
+pub fn bench(depth: usize, mut report: impl FnMut(&str, &SearchResult)) -> BenchResult {
+    let mut searcher = Searcher::default();
+    let mut total = BenchResult::default();
+    let start = Instant::now();
+
+    for fen in BENCH_FENS {
+        let mut chess = Chess::from_fen(fen).expect("Bench positions are valid");
+        searcher.new_game();
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(depth));
+        total.nodes += result.nodes;
+        report(fen, &result);
+    }
+
+    total.elapsed = start.elapsed();
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::{bench, BENCH_FENS};
+
+    #[test]
+    fn test_bench_is_deterministic() {
+        let mut searched = 0;
+        let first = bench(2, |_, result| {
+            assert!(result.best_move.is_some());
+            searched += 1;
+        });
+        let second = bench(2, |_, _| {});
+
+        assert_eq!(searched, BENCH_FENS.len());
+        assert_eq!(first.nodes, second.nodes);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{Chess, Move, Team};
+
+/// A move of the attacking side that forces mate, with every defence against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MateNode {
+    pub mv: Move,
+    /// Fewest moves, counting this one, the attacker needs to mate after it.
+    pub mate_in: usize,
+    /// Empty if the move mates.
+    pub replies: Vec<MateReply>,
+}
+
+/// A defence and all attacking moves that still force mate in time after it. More than
+/// one continuation is a dual.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MateReply {
+    pub mv: Move,
+    pub continuations: Vec<MateNode>,
+}
+
+/// Result of a "mate in N" stipulation for the side to move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MateSolution {
+    pub moves: usize,
+    /// Every first move forcing mate in at most `moves` moves. A sound problem has
+    /// exactly one, further key moves are cooks.
+    pub keys: Vec<MateNode>,
+    /// Positions visited while solving.
+    pub nodes: u64,
+}
+
+impl MateSolution {
+    /// Whether the stipulation holds at all.
+    pub fn is_solved(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Exactly one key move.
+    pub fn is_unique(&self) -> bool {
+        self.keys.len() == 1
+    }
+
+    /// Whether the attacker has a choice of moves after some defence, the final mating
+    /// move included.
+    pub fn has_duals(&self) -> bool {
+        fn duals(node: &MateNode) -> bool {
+            node.replies
+                .iter()
+                .any(|reply| reply.continuations.len() > 1 || reply.continuations.iter().any(duals))
+        }
+        self.keys.iter().any(duals)
+    }
+
+    /// The solution tree in standard algebraic notation, one line per move, starting
+    /// from the position the problem was set in.
+    pub fn format(&self, chess: &Chess) -> String {
+        let mut chess = chess.clone();
+        let mut out = String::new();
+        for key in &self.keys {
+            format_node(&mut chess, key, 0, &mut out);
+        }
+        out
+    }
+}
+
+fn move_number(chess: &Chess) -> String {
+    match chess.player {
+        Team::White => format!("{}.", chess.fullmove_number),
+        Team::Black => format!("{}...", chess.fullmove_number),
+    }
+}
+
+fn format_node(chess: &mut Chess, node: &MateNode, indent: usize, out: &mut String) {
+    let number = move_number(chess);
+    let san = chess.to_san(node.mv);
+    writeln!(out, "{:indent$}{number} {san}", "", indent = indent * 2).unwrap();
+
+    chess.make_move(node.mv);
+    for reply in &node.replies {
+        let number = move_number(chess);
+        let san = chess.to_san(reply.mv);
+        writeln!(out, "{:indent$}{number} {san}", "", indent = indent * 2 + 2).unwrap();
+
+        chess.make_move(reply.mv);
+        for continuation in &reply.continuations {
+            format_node(chess, continuation, indent + 2, out);
+        }
+        chess.unmake_move();
+    }
+    chess.unmake_move();
+}
+
+/// Proves or disproves "mate in N" by searching every move of the attacker and every
+/// defence, without any evaluation. Results are cached per position and remaining
+/// moves.
+#[derive(Debug, Default)]
+pub struct MateSolver {
+    /// Whether the side to move is mated within the given number of attacking moves.
+    cache: HashMap<(u64, usize), bool>,
+    nodes: u64,
+}
+
+impl MateSolver {
+    pub fn new() -> Self {
+        MateSolver::default()
+    }
+
+    /// Solves "the side to move mates in `moves`", with all key moves and the full
+    /// solution tree.
+    pub fn solve(&mut self, chess: &mut Chess, moves: usize) -> MateSolution {
+        self.nodes = 0;
+        let keys = if moves == 0 {
+            vec![]
+        } else {
+            self.attacking_moves(chess, moves)
+        };
+        MateSolution {
+            moves,
+            keys,
+            nodes: self.nodes,
+        }
+    }
+
+    /// Whether the defender, who is to move, gets mated while the attacker has `moves`
+    /// moves left, including the one after this defence.
+    fn is_lost(&mut self, chess: &mut Chess, moves: usize) -> bool {
+        if let Some(lost) = self.cache.get(&(chess.hash, moves)) {
+            return *lost;
+        }
+        self.nodes += 1;
+
+        let defences = chess.legal_moves();
+        let lost = if defences.is_empty() {
+            chess.check_check(chess.player).is_some()
+        } else if moves == 0 {
+            false
+        } else {
+            defences.into_iter().all(|defence| {
+                chess.make_move(defence);
+                let mates = self.can_mate(chess, moves);
+                chess.unmake_move();
+                mates
+            })
+        };
+        self.cache.insert((chess.hash, moves), lost);
+        lost
+    }
+
+    /// Whether the side to move mates within `moves` moves.
+    fn can_mate(&mut self, chess: &mut Chess, moves: usize) -> bool {
+        let attacker = chess.player;
+        chess.legal_moves().into_iter().any(|mv| {
+            chess.make_move(mv);
+            // the last move has to give check, which is much cheaper to test
+            let mates = (moves > 1 || chess.check_check(!attacker).is_some())
+                && self.is_lost(chess, moves - 1);
+            chess.unmake_move();
+            mates
+        })
+    }
+
+    fn attacking_moves(&mut self, chess: &mut Chess, moves: usize) -> Vec<MateNode> {
+        let attacker = chess.player;
+        let mut nodes = vec![];
+        for mv in chess.legal_moves() {
+            chess.make_move(mv);
+            let gives_check = chess.check_check(!attacker).is_some();
+            if (moves > 1 || gives_check) && self.is_lost(chess, moves - 1) {
+                let mate_in = (1..=moves)
+                    .find(|left| (*left > 1 || gives_check) && self.is_lost(chess, left - 1))
+                    .unwrap_or(moves);
+                let replies = self.defences(chess, moves - 1);
+                nodes.push(MateNode {
+                    mv,
+                    mate_in,
+                    replies,
+                });
+            }
+            chess.unmake_move();
+        }
+        nodes
+    }
+
+    fn defences(&mut self, chess: &mut Chess, moves: usize) -> Vec<MateReply> {
+        if moves == 0 {
+            return vec![];
+        }
+        let mut replies = vec![];
+        for defence in chess.legal_moves() {
+            chess.make_move(defence);
+            replies.push(MateReply {
+                mv: defence,
+                continuations: self.attacking_moves(chess, moves),
+            });
+            chess.unmake_move();
+        }
+        replies
+    }
+}
+
+/// Shorthand for `MateSolver::new().solve(chess, moves)`.
+pub fn solve_mate(chess: &mut Chess, moves: usize) -> MateSolution {
+    MateSolver::new().solve(chess, moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{search::solve_mate, Chess, Move};
+
+    #[test]
+    fn test_mate_in_one() {
+        let mut chess = Chess::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let solution = solve_mate(&mut chess, 1);
+        assert!(solution.is_unique());
+        assert_eq!(solution.keys[0].mv, Move::new((7, 0), (0, 0)));
+        assert!(solution.keys[0].replies.is_empty());
+        assert_eq!(solution.format(&chess), "1. Ra8#\n");
+    }
+
+    #[test]
+    fn test_mate_in_two() {
+        let mut chess = Chess::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        assert!(!solve_mate(&mut chess, 1).is_solved());
+
+        let solution = solve_mate(&mut chess, 2);
+        // 1. Kb6 Kb8 2. Rh8#
+        let key = solution
+            .keys
+            .iter()
+            .find(|key| key.mv == Move::new((2, 2), (2, 1)))
+            .unwrap();
+        assert_eq!(key.replies.len(), 1);
+        assert_eq!(
+            key.replies[0].continuations[0].mv,
+            Move::new((7, 7), (0, 7))
+        );
+        for key in &solution.keys {
+            assert_eq!(key.mate_in, 2);
+            // every defence is answered by a mate
+            for reply in &key.replies {
+                assert!(!reply.continuations.is_empty());
+                assert!(reply.continuations.iter().all(|mate| mate.mate_in == 1));
+            }
+        }
+        assert!(solution
+            .format(&chess)
+            .contains("1. Kb6\n  1... Kb8\n    2. Rh8#\n"));
+        // the position the solution starts in is left untouched
+        assert!(chess.history.is_empty());
+    }
+
+    #[test]
+    fn test_stalemate_is_no_mate() {
+        let mut chess = Chess::from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        let solution = solve_mate(&mut chess, 1);
+        let keys = solution.keys.iter().map(|key| key.mv).collect::<Vec<_>>();
+        assert!(keys.contains(&Move::new((7, 2), (0, 2))));
+        // Qc7 stalemates
+        assert!(!keys.contains(&Move::new((7, 2), (1, 2))));
+    }
+}
@@ -0,0 +1,837 @@
+mod bench;
+mod mate;
+mod ordering;
+mod see;
+mod skill;
+mod time;
+mod tt;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+pub use bench::*;
+pub use mate::*;
+pub use ordering::*;
+pub use skill::*;
+pub use time::*;
+pub use tt::*;
+
+use crate::{
+    eval::{figure_value, Evaluator, Handcrafted},
+    syzygy::{Probe, Tablebase, Wdl},
+    Chess, FigureType, Move,
+};
+
+pub const INFINITY: i32 = 32_000;
+pub const MATE: i32 = 31_000;
+/// Scores beyond this bound are mate scores.
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+pub const MAX_PLY: usize = 128;
+
+/// Score of a tablebase win, below every mate score.
+pub const TB_WIN: i32 = MATE_BOUND - MAX_PLY as i32;
+
+/// Captures that cannot raise the score to alpha even with this margin are skipped in
+/// the quiescence search.
+pub const DELTA_MARGIN: i32 = 200;
+
+/// Default size of the transposition table in megabytes.
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// Remaining time on the clock of the player to move.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub time: Duration,
+    pub inc: Duration,
+    pub moves_to_go: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub clock: Option<Clock>,
+    /// Search until stopped, the search itself treats this like no limit at all.
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+}
+
+/// One of the best moves at the root, with its score and principal variation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: i32,
+    /// Depth the line was searched to.
+    pub depth: usize,
+    pub pv: Vec<Move>,
+}
+
+impl PvLine {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// Score in centipawns from the view of the player to move.
+    pub score: i32,
+    /// Depth of the last completed iteration.
+    pub depth: usize,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+    pub time: Duration,
+    /// Permille of the transposition table written in this search.
+    pub hashfull: usize,
+    /// Positions found in the endgame tablebase.
+    pub tbhits: u64,
+    /// The best root moves of the last completed iteration, best first. Holds more than
+    /// one line if several were searched, see `Searcher::multipv`.
+    pub lines: Vec<PvLine>,
+}
+
+impl SearchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.time.as_secs_f64().max(1e-3)) as u64
+    }
+}
+
+/// Number of moves until mate if `score` is a mate score, negative if the player to move
+/// gets mated.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score >= MATE_BOUND {
+        Some((MATE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        Some(-(MATE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// Score of a probed root position, shorter distances to zeroing score higher.
+fn tb_score(probe: Probe) -> i32 {
+    let dtz = probe.dtz.unwrap_or(0).abs().min(MAX_PLY as i32);
+    match probe.wdl {
+        Wdl::Win => TB_WIN - dtz,
+        Wdl::Loss => -TB_WIN + dtz,
+        _ => 0,
+    }
+}
+
+/// Iterative deepening alpha-beta search. The transposition table is kept between
+/// searches. Positions are scored by `E`, the handcrafted evaluation by default.
+#[derive(Debug)]
+pub struct Searcher<E = Handcrafted> {
+    /// Shared with the helper threads of a parallel search.
+    pub tt: Arc<TranspositionTable>,
+    pub history: History,
+    /// Safety margin subtracted from the thinking time of every move.
+    pub move_overhead: Duration,
+    /// Probed at the root and for positions right after captures and pawn moves.
+    pub tablebase: Option<Arc<Tablebase>>,
+    /// Number of best root moves searched with exact scores, each gets a line in
+    /// `SearchResult::lines`.
+    pub multipv: usize,
+    /// Weakens the play below the maximum level.
+    pub skill: Skill,
+    killers: Vec<[Option<Move>; 2]>,
+    limits: SearchLimits,
+    /// Latest point in time the search may run until.
+    deadline: Option<Instant>,
+    start: Instant,
+    nodes: u64,
+    tbhits: u64,
+    stopped: bool,
+    stop: Arc<AtomicBool>,
+    /// Root moves skipped because they are the best moves of earlier MultiPV lines.
+    excluded: Vec<Move>,
+    /// Best move found by the last search of the root.
+    root_best: Option<Move>,
+    /// Zero for the main thread, helper threads count up from one.
+    id: usize,
+    evaluator: E,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl Searcher {
+    pub fn new(hash_mb: usize) -> Self {
+        Searcher::with_evaluator(hash_mb, Handcrafted::default())
+    }
+}
+
+impl<E: Evaluator + Clone> Searcher<E> {
+    /// A searcher for a helper thread of a lazy SMP search. It shares the
+    /// transposition table and stops once `stop` is set.
+    pub fn helper(&self, id: usize, stop: Arc<AtomicBool>) -> Searcher<E> {
+        Searcher {
+            tt: self.tt.clone(),
+            tablebase: self.tablebase.clone(),
+            stop,
+            id,
+            ..Searcher::with_evaluator(0, self.evaluator.clone())
+        }
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    pub fn with_evaluator(hash_mb: usize, evaluator: E) -> Self {
+        Searcher {
+            tt: Arc::new(TranspositionTable::new(hash_mb)),
+            history: History::default(),
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            tablebase: None,
+            multipv: 1,
+            skill: Skill::default(),
+            killers: vec![[None; 2]; MAX_PLY],
+            limits: SearchLimits::default(),
+            deadline: None,
+            start: Instant::now(),
+            nodes: 0,
+            tbhits: 0,
+            stopped: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            excluded: vec![],
+            root_best: None,
+            id: 0,
+            evaluator,
+        }
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    pub fn evaluator_mut(&mut self) -> &mut E {
+        &mut self.evaluator
+    }
+
+    /// Replaces the transposition table with an empty one of the given size.
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.tt = Arc::new(TranspositionTable::new(size_mb));
+    }
+
+    /// Setting the returned flag stops a running search as soon as possible. It is not
+    /// reset by the searcher.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn new_game(&mut self) {
+        self.tt.clear();
+        self.history.clear();
+    }
+
+    pub fn search(&mut self, chess: &mut Chess, limits: SearchLimits) -> SearchResult {
+        self.search_with_info(chess, limits, |_| {})
+    }
+
+    /// Like `search`, `info` is called after every completed iteration.
+    pub fn search_with_info(
+        &mut self,
+        chess: &mut Chess,
+        limits: SearchLimits,
+        mut info: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let limits = self.skill.limit(limits);
+        self.limits = limits;
+        self.start = Instant::now();
+        let mut time = match (limits.movetime, limits.clock) {
+            _ if limits.infinite => None,
+            (Some(movetime), _) => Some(TimeManager::fixed(movetime, self.move_overhead)),
+            (None, Some(clock)) => Some(TimeManager::new(clock, self.move_overhead)),
+            (None, None) => None,
+        };
+        self.deadline = time.map(|time| time.deadline());
+        // no need to think if there is only one reply
+        let forced = limits.clock.is_some() && chess.legal_moves().len() == 1;
+        self.nodes = 0;
+        self.tbhits = 0;
+        self.stopped = false;
+        if self.id == 0 {
+            self.tt.new_search();
+        }
+        self.history.age();
+        self.killers.fill([None; 2]);
+        self.evaluator.reset(chess);
+
+        let mut result = SearchResult::default();
+
+        // the tablebase knows the perfect move
+        if let Some((mv, probe)) = self
+            .tablebase
+            .clone()
+            .and_then(|tablebase| tablebase.best_move(chess))
+        {
+            result.best_move = Some(mv);
+            result.pv = vec![mv];
+            result.score = tb_score(probe);
+            result.depth = 1;
+            result.tbhits = 1;
+            result.time = self.start.elapsed();
+            info(&result);
+            return result;
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_PLY - 1).clamp(1, MAX_PLY - 1);
+        let multipv = self
+            .multipv
+            .max(self.skill.multipv())
+            .clamp(1, chess.legal_moves().len().max(1));
+
+        // helpers skip iterations to spread out over different depths
+        for depth in 1 + self.id % 2..=max_depth {
+            let mut lines = vec![];
+            for _ in 0..multipv {
+                self.root_best = None;
+                let score = self.negamax(chess, depth, 0, -INFINITY, INFINITY);
+                if self.stopped {
+                    break;
+                }
+                let Some(mv) = self.root_best else {
+                    break;
+                };
+                self.make_move(chess, mv);
+                let mut pv = vec![mv];
+                pv.extend(self.pv(chess, depth - 1));
+                self.unmake_move(chess, mv);
+                lines.push(PvLine { score, depth, pv });
+                self.excluded.push(mv);
+            }
+            self.excluded.clear();
+
+            // an interrupted iteration is only trusted if it has a move at all
+            if self.stopped && result.best_move.is_some() {
+                break;
+            }
+
+            if lines.is_empty() {
+                // stopped before the first root move was searched completely
+                result.pv = self.pv(chess, depth);
+                result.score = 0;
+            } else {
+                lines.sort_by_key(|line| -line.score);
+                result.pv = lines[0].pv.clone();
+                result.score = lines[0].score;
+            }
+            result.lines = lines;
+            result.best_move = result.pv.first().copied();
+            result.depth = depth;
+            result.nodes = self.nodes;
+            result.time = self.start.elapsed();
+            result.hashfull = self.tt.hashfull();
+            result.tbhits = self.tbhits;
+            info(&result);
+
+            let score = result.score;
+            if self.stopped
+                || forced
+                || mate_in(score).is_some_and(|mate| mate.unsigned_abs() as usize <= depth)
+            {
+                break;
+            }
+
+            if let (Some(time), Some(best_move)) = (&mut time, result.best_move) {
+                time.update(best_move, score);
+                if time.should_stop(time.elapsed()) {
+                    break;
+                }
+            }
+        }
+
+        if let Some(line) = self.skill.pick(&result.lines) {
+            result.pv = line.pv.clone();
+            result.best_move = result.pv.first().copied();
+            result.score = line.score;
+        }
+        if result.best_move.is_none() {
+            result.best_move = chess.legal_moves().first().copied();
+        }
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result
+    }
+
+    /// Makes a move of the search and tells the evaluator about it.
+    fn make_move(&mut self, chess: &mut Chess, mv: Move) {
+        chess.make_move(mv);
+        self.evaluator.on_make_move(chess, mv);
+    }
+
+    fn unmake_move(&mut self, chess: &mut Chess, mv: Move) {
+        self.evaluator.on_unmake_move(chess, mv);
+        chess.unmake_move();
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+            || self.stop.load(Ordering::Relaxed)
+        {
+            self.stopped = true;
+        }
+        if self.nodes & 255 == 0
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn negamax(
+        &mut self,
+        chess: &mut Chess,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+
+        if ply > 0 {
+            if self.should_stop() {
+                return 0;
+            }
+            if chess.halfmove_clock >= 100 || chess.repetitions() > 0 {
+                return 0;
+            }
+        }
+
+        let tt_entry = self.tt.probe(chess.hash, ply);
+        if let Some(entry) = tt_entry {
+            if ply > 0 && entry.depth as usize >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => {}
+                }
+            }
+        }
+
+        if ply > 0 && chess.halfmove_clock == 0 {
+            if let Some(score) = self.probe_tablebase(chess, ply) {
+                self.tt
+                    .store(chess.hash, None, score, depth, Bound::Exact, ply);
+                return score;
+            }
+        }
+
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(chess, ply, alpha, beta);
+        }
+
+        let mut moves = chess.legal_moves();
+        if ply == 0 {
+            moves.retain(|mv| !self.excluded.contains(mv));
+        }
+        if moves.is_empty() {
+            return if chess.check_check(chess.player).is_some() {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let tt_move = tt_entry.and_then(|entry| entry.best_move);
+        let mut picker = MovePicker::new(moves, tt_move, self.killers[ply]);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut searched_quiets = vec![];
+
+        while let Some(mv) = picker.next(chess, &self.history) {
+            let quiet = !chess.is_capture(mv) && mv.promotion == FigureType::Empty;
+
+            self.make_move(chess, mv);
+            let score = -self.negamax(chess, depth - 1, ply + 1, -beta, -alpha);
+            self.unmake_move(chess, mv);
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                if quiet {
+                    self.update_quiet_stats(chess, mv, &searched_quiets, depth, ply);
+                }
+                break;
+            }
+            if quiet {
+                searched_quiets.push(mv);
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        if ply == 0 {
+            self.root_best = best_move;
+        }
+        // the score of a root without its best moves is no score of the position
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt
+                .store(chess.hash, best_move, best_score, depth, bound, ply);
+        }
+
+        best_score
+    }
+
+    /// Exact score of a position in the tablebase. Wins that are drawn by the 50 move
+    /// rule count as draws.
+    fn probe_tablebase(&mut self, chess: &mut Chess, ply: usize) -> Option<i32> {
+        let tablebase = self.tablebase.as_ref()?;
+        if !tablebase.can_probe(chess) {
+            return None;
+        }
+        let wdl = tablebase.probe_wdl(chess)?;
+        self.tbhits += 1;
+        Some(match wdl {
+            Wdl::Win => TB_WIN - ply as i32,
+            Wdl::Loss => -TB_WIN + ply as i32,
+            _ => 0,
+        })
+    }
+
+    /// Rewards the quiet move that caused a beta cutoff and punishes the quiet moves
+    /// tried before it.
+    fn update_quiet_stats(
+        &mut self,
+        chess: &Chess,
+        mv: Move,
+        searched_quiets: &[Move],
+        depth: usize,
+        ply: usize,
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let bonus = (depth * depth) as i32;
+        self.history.update(chess.player, mv, bonus);
+        for quiet in searched_quiets {
+            self.history.update(chess.player, *quiet, -bonus);
+        }
+    }
+
+    /// Resolves captures and promotions until the position is quiet, so that the static
+    /// evaluation is not taken in the middle of an exchange.
+    fn quiescence(&mut self, chess: &mut Chess, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = chess.check_check(chess.player).is_some();
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(chess);
+        }
+
+        // the side to move may decline every capture, unless it is in check
+        let stand_pat = self.evaluator.evaluate(chess);
+        let mut best_score = -INFINITY;
+        let moves = if in_check {
+            chess.legal_moves()
+        } else {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            best_score = stand_pat;
+            chess.legal_tactical_moves()
+        };
+
+        if in_check && moves.is_empty() {
+            return -MATE + ply as i32;
+        }
+
+        let mut moves = moves
+            .into_iter()
+            .map(|mv| (mv, chess.see(mv)))
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|(_, see)| -see);
+
+        for (mv, see) in moves {
+            if !in_check {
+                // losing captures
+                if see < 0 {
+                    continue;
+                }
+                // delta pruning, a pawn moving diagonally onto an empty field captures
+                // en passant
+                let pawn_moved = chess
+                    .field(mv.from())
+                    .figure
+                    .is_some_and(|figure| figure.figure == FigureType::Pawn);
+                let mut gain = match chess.field(mv.to()).figure {
+                    Some(captured) => figure_value(captured.figure),
+                    None if pawn_moved && mv.from().1 != mv.to().1 => {
+                        figure_value(FigureType::Pawn)
+                    }
+                    None => 0,
+                };
+                if mv.promotion != FigureType::Empty {
+                    gain += figure_value(mv.promotion) - figure_value(FigureType::Pawn);
+                }
+                if stand_pat + gain + DELTA_MARGIN < alpha {
+                    continue;
+                }
+            }
+
+            self.make_move(chess, mv);
+            let score = -self.quiescence(chess, ply + 1, -beta, -alpha);
+            self.unmake_move(chess, mv);
+
+            if self.stopped {
+                return 0;
+            }
+
+            best_score = best_score.max(score);
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    /// Follows the best moves stored in the transposition table.
+    fn pv(&mut self, chess: &mut Chess, depth: usize) -> Vec<Move> {
+        let mut pv = vec![];
+        while pv.len() < depth {
+            let Some(mv) = self
+                .tt
+                .probe(chess.hash, 0)
+                .and_then(|entry| entry.best_move)
+            else {
+                break;
+            };
+            if !chess.legal_moves().contains(&mv) {
+                break;
+            }
+            chess.make_move(mv);
+            pv.push(mv);
+            if chess.repetitions() > 0 {
+                break;
+            }
+        }
+        for _ in 0..pv.len() {
+            chess.unmake_move();
+        }
+        pv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        eval::{figure_value, Evaluator, Material},
+        search::{mate_in, Clock, SearchLimits, Searcher, Skill},
+        Chess, FigureType, Move, Team,
+    };
+
+    /// Keeps the material balance from white's view up to date on every move and
+    /// checks it against a full count.
+    #[derive(Debug, Default)]
+    struct IncrementalMaterial {
+        white: i32,
+        evaluations: u64,
+    }
+
+    impl IncrementalMaterial {
+        fn delta(chess: &Chess) -> i32 {
+            let undo = chess.history.last().unwrap();
+            let mut delta = undo
+                .captured
+                .map_or(0, |(figure, _)| figure_value(figure.figure));
+            if undo.mv.promotion != FigureType::Empty {
+                delta += figure_value(undo.mv.promotion) - figure_value(FigureType::Pawn);
+            }
+            match undo.moved.team {
+                Team::White => delta,
+                Team::Black => -delta,
+            }
+        }
+    }
+
+    impl Evaluator for IncrementalMaterial {
+        fn evaluate(&mut self, chess: &Chess) -> i32 {
+            self.evaluations += 1;
+            let score = match chess.player {
+                Team::White => self.white,
+                Team::Black => -self.white,
+            };
+            assert_eq!(score, Material.evaluate(chess));
+            score
+        }
+
+        fn reset(&mut self, chess: &Chess) {
+            let score = Material.evaluate(chess);
+            self.white = match chess.player {
+                Team::White => score,
+                Team::Black => -score,
+            };
+        }
+
+        fn on_make_move(&mut self, chess: &Chess, _mv: Move) {
+            self.white += Self::delta(chess);
+        }
+
+        fn on_unmake_move(&mut self, chess: &Chess, _mv: Move) {
+            self.white -= Self::delta(chess);
+        }
+    }
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let mut chess = Chess::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(1);
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(3));
+        assert_eq!(result.best_move, Some(Move::new((7, 0), (0, 0))));
+        assert_eq!(mate_in(result.score), Some(1));
+    }
+
+    #[test]
+    fn test_captures_hanging_queen() {
+        let mut chess = Chess::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(1);
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(2));
+        assert_eq!(result.best_move, Some(Move::new((6, 3), (3, 3))));
+    }
+
+    #[test]
+    fn test_quiescence_sees_recapture() {
+        // Rxe5 wins a pawn at depth 1 without the recapture fxe5
+        let mut chess = Chess::from_fen("4k3/8/5p2/4p3/8/8/8/4RK2 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(1);
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(1));
+        assert_ne!(result.best_move, Some(Move::new((7, 4), (3, 4))));
+    }
+
+    #[test]
+    fn test_tt_reduces_nodes() {
+        let mut chess = Chess::new(None);
+        let mut searcher = Searcher::new(1);
+        let first = searcher.search(&mut chess, SearchLimits::depth(3));
+        let second = searcher.search(&mut chess, SearchLimits::depth(3));
+
+        assert!(second.nodes < first.nodes);
+        assert!(searcher.tt.stats().hits > 0);
+        assert!(searcher.tt.stats().stores > 0);
+    }
+
+    #[test]
+    fn test_single_reply_is_played_at_once() {
+        // the king in check can only take the queen
+        let mut chess = Chess::from_fen("6k1/8/8/8/8/8/6q1/7K w - - 0 1").unwrap();
+        assert_eq!(chess.legal_moves().len(), 1);
+
+        let mut searcher = Searcher::new(1);
+        let limits = SearchLimits {
+            clock: Some(Clock {
+                time: Duration::from_secs(60),
+                inc: Duration::ZERO,
+                moves_to_go: None,
+            }),
+            ..Default::default()
+        };
+        let result = searcher.search(&mut chess, limits);
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move, Some(Move::new((7, 7), (6, 6))));
+    }
+
+    #[test]
+    fn test_multipv_lines() {
+        let mut chess = Chess::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(1);
+        searcher.multipv = 3;
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(2));
+        assert_eq!(result.lines.len(), 3);
+        assert!(result.lines.iter().all(|line| line.depth == 2));
+        assert_eq!(result.lines[0].best_move(), Some(Move::new((6, 3), (3, 3))));
+        assert_eq!(result.best_move, Some(Move::new((6, 3), (3, 3))));
+        assert!(result
+            .lines
+            .windows(2)
+            .all(|lines| lines[0].score >= lines[1].score && lines[0].pv[0] != lines[1].pv[0]));
+    }
+
+    #[test]
+    fn test_weakened_search() {
+        let mut chess = Chess::new(None);
+        let mut searcher = Searcher::new(1);
+        searcher.skill = Skill::new(0);
+
+        let result = searcher.search(&mut chess, SearchLimits::default());
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.lines.len(), 4);
+        assert!(chess.legal_moves().contains(&result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn test_material_evaluator() {
+        let mut chess = Chess::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::with_evaluator(1, Material);
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(2));
+        assert_eq!(result.best_move, Some(Move::new((6, 3), (3, 3))));
+        assert_eq!(result.score, 500);
+    }
+
+    #[test]
+    fn test_incremental_evaluator() {
+        let mut chess = Chess::from_fen("r3k2r/1P3ppp/8/3q4/8/8/3R1PPP/R3K3 w Qkq - 0 1").unwrap();
+        let mut searcher = Searcher::with_evaluator(1, IncrementalMaterial::default());
+
+        let result = searcher.search(&mut chess, SearchLimits::depth(3));
+        assert!(result.best_move.is_some());
+        assert!(searcher.evaluator().evaluations > 0);
+        assert!(chess.history.is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{eval::figure_value, Chess, FigureType, Move, Team};
+
+/// History scores are kept within this bound.
+const MAX_HISTORY: i32 = 16_384;
+
+/// History heuristic: quiet moves that caused beta cutoffs before, indexed by the
+/// team and the start and end field of the move.
+#[derive(Debug, Clone)]
+pub struct History {
+    table: Vec<i32>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            table: vec![0; 2 * 64 * 64],
+        }
+    }
+}
+
+impl History {
+    #[inline]
+    fn index(team: Team, mv: Move) -> usize {
+        team as usize * 64 * 64
+            + (mv.start_row * 8 + mv.start_col) * 64
+            + mv.end_row * 8
+            + mv.end_col
+    }
+
+    pub fn get(&self, team: Team, mv: Move) -> i32 {
+        self.table[Self::index(team, mv)]
+    }
+
+    /// Adds `bonus` (negative for a malus), the closer an entry gets to `MAX_HISTORY`
+    /// the less it grows.
+    pub fn update(&mut self, team: Team, mv: Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+        let entry = &mut self.table[Self::index(team, mv)];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Halves all scores, so that older searches count less.
+    pub fn age(&mut self) {
+        self.table.iter_mut().for_each(|entry| *entry /= 2);
+    }
+
+    pub fn clear(&mut self) {
+        self.table.fill(0);
+    }
+}
+
+/// Most valuable victim, least valuable attacker.
+pub fn mvv_lva(chess: &Chess, mv: Move) -> i32 {
+    let victim = chess
+        .field(mv.to())
+        .figure
+        .map_or(figure_value(FigureType::Pawn), |figure| {
+            figure_value(figure.figure)
+        });
+    let attacker = chess
+        .field(mv.from())
+        .figure
+        .map_or(0, |figure| figure_value(figure.figure));
+    victim * 10 - attacker / 10 + figure_value(mv.promotion)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Hands out the legal moves of a position in the order they are likely to be good:
+/// hash move, winning captures, killer moves, quiet moves by history, losing captures.
+#[derive(Debug)]
+pub struct MovePicker {
+    stage: Stage,
+    moves: Vec<Move>,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    killer_idx: usize,
+    good_captures: Vec<(Move, i32)>,
+    bad_captures: Vec<(Move, i32)>,
+    quiets: Vec<(Move, i32)>,
+}
+
+/// Removes the entry with the highest score.
+fn pick_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let (idx, _) = moves
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (_, score))| *score)?;
+    Some(moves.swap_remove(idx).0)
+}
+
+impl MovePicker {
+    pub fn new(moves: Vec<Move>, tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Self {
+        MovePicker {
+            stage: Stage::TtMove,
+            moves,
+            tt_move,
+            killers,
+            killer_idx: 0,
+            good_captures: vec![],
+            bad_captures: vec![],
+            quiets: vec![],
+        }
+    }
+
+    fn is_special(&self, mv: Move) -> bool {
+        Some(mv) == self.tt_move || self.killers.contains(&Some(mv))
+    }
+
+    pub fn next(&mut self, chess: &Chess, history: &History) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if let Some(tt_move) = self.tt_move {
+                        if self.moves.contains(&tt_move) {
+                            return Some(tt_move);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    for mv in self.moves.iter().copied() {
+                        if Some(mv) == self.tt_move
+                            || (!chess.is_capture(mv) && mv.promotion == FigureType::Empty)
+                        {
+                            continue;
+                        }
+                        let score = mvv_lva(chess, mv);
+                        if chess.see(mv) >= 0 {
+                            self.good_captures.push((mv, score));
+                        } else {
+                            self.bad_captures.push((mv, score));
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match pick_best(&mut self.good_captures) {
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    while self.killer_idx < self.killers.len() {
+                        let killer = self.killers[self.killer_idx];
+                        self.killer_idx += 1;
+                        if let Some(killer) = killer {
+                            if Some(killer) != self.tt_move
+                                && self.moves.contains(&killer)
+                                && !chess.is_capture(killer)
+                                && killer.promotion == FigureType::Empty
+                            {
+                                return Some(killer);
+                            }
+                        }
+                    }
+                    // killers that were not played must not be skipped later on
+                    self.killers = self.killers.map(|killer| {
+                        killer.filter(|killer| {
+                            self.moves.contains(killer)
+                                && !chess.is_capture(*killer)
+                                && killer.promotion == FigureType::Empty
+                        })
+                    });
+                    self.stage = Stage::GenerateQuiets;
+                }
+                Stage::GenerateQuiets => {
+                    self.quiets = self
+                        .moves
+                        .iter()
+                        .copied()
+                        .filter(|mv| {
+                            !self.is_special(*mv)
+                                && !chess.is_capture(*mv)
+                                && mv.promotion == FigureType::Empty
+                        })
+                        .map(|mv| (mv, history.get(chess.player, mv)))
+                        .collect();
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match pick_best(&mut self.quiets) {
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => match pick_best(&mut self.bad_captures) {
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        search::ordering::{History, MovePicker},
+        Chess, Move,
+    };
+
+    #[test]
+    fn test_picks_every_move_once() {
+        let mut chess =
+            Chess::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let moves = chess.legal_moves();
+        let tt_move = Some(moves[7]);
+        let killers = [Some(Move::new((6, 0), (5, 0))), None];
+
+        let mut picker = MovePicker::new(moves.clone(), tt_move, killers);
+        let mut picked = vec![];
+        while let Some(mv) = picker.next(&chess, &History::default()) {
+            picked.push(mv);
+        }
+
+        assert_eq!(picked[0], moves[7]);
+        assert_eq!(picked.len(), moves.len());
+        assert!(moves
+            .iter()
+            .all(|mv| picked.iter().filter(|picked| *picked == mv).count() == 1));
+    }
+
+    #[test]
+    fn test_order_of_stages() {
+        // Qxd5 wins the queen, Bxa6 bxa6 loses the bishop for the knight
+        let mut chess = Chess::from_fen("4k3/1p6/n7/3q4/8/8/3Q4/4KB2 w - - 0 1").unwrap();
+        let moves = chess.legal_moves();
+        let killer = Move::new((7, 4), (6, 5));
+
+        let mut picker = MovePicker::new(moves, None, [Some(killer), None]);
+        let history = History::default();
+        let mut picked = vec![];
+        while let Some(mv) = picker.next(&chess, &history) {
+            picked.push(mv);
+        }
+
+        assert_eq!(picked[0], Move::new((6, 3), (3, 3)));
+        assert_eq!(picked[1], killer);
+        assert_eq!(*picked.last().unwrap(), Move::new((7, 5), (2, 0)));
+    }
+}
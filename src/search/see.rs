@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::{eval::figure_value, Chess, Field, Figure, FigureType, Move, Team};
+
+/// The king is worth more than everything else, capturing it ends the exchange.
+const KING_VALUE: i32 = 20_000;
+
+fn see_value(figure: FigureType) -> i32 {
+    match figure {
+        FigureType::King => KING_VALUE,
+        figure => figure_value(figure),
+    }
+}
+
+/// The least valuable figure of `team` attacking `target`.
+fn least_valuable_attacker(
+    fields: &[[Field; 8]; 8],
+    target: (usize, usize),
+    team: Team,
+) -> Option<((usize, usize), Figure)> {
+    let no_en_passants = HashMap::new();
+    fields
+        .iter()
+        .flatten()
+        .filter_map(|field| Some((field.idxs, field.figure?)))
+        .filter(|(_, figure)| figure.team == team)
+        .filter(|(pos, figure)| {
+            figure
+                .valid_moves(*pos, fields, &no_en_passants)
+                .contains(&target)
+        })
+        .min_by_key(|(_, figure)| see_value(figure.figure))
+}
+
+impl Chess {
+    /// Static exchange evaluation: the material balance in centipawns after all
+    /// captures on the target field of `mv`, with both sides always recapturing with
+    /// their least valuable attacker and stopping when continuing would lose material.
+    /// Pins and checks are ignored.
+    pub fn see(&self, mv: Move) -> i32 {
+        let (from, to) = (mv.from(), mv.to());
+        let Some(mut attacker) = self.field(from).figure else {
+            return 0;
+        };
+        let mut fields = self.fields;
+
+        let mut gain = [0; 32];
+        if let Some(captured) = fields[to.0][to.1].figure {
+            gain[0] = see_value(captured.figure);
+        } else if attacker.figure == FigureType::Pawn && from.1 != to.1 {
+            gain[0] = figure_value(FigureType::Pawn);
+            fields[from.0][to.1].figure = None;
+        }
+        if mv.promotion != FigureType::Empty {
+            gain[0] += figure_value(mv.promotion) - figure_value(FigureType::Pawn);
+            attacker.figure = mv.promotion;
+        }
+
+        fields[from.0][from.1].figure = None;
+        fields[to.0][to.1].figure = Some(attacker);
+
+        let mut on_target = see_value(attacker.figure);
+        let mut team = !attacker.team;
+        let mut depth = 0;
+
+        while depth + 1 < gain.len() {
+            let Some((pos, figure)) = least_valuable_attacker(&fields, to, team) else {
+                break;
+            };
+            depth += 1;
+            gain[depth] = on_target - gain[depth - 1];
+
+            on_target = see_value(figure.figure);
+            fields[pos.0][pos.1].figure = None;
+            fields[to.0][to.1].figure = Some(figure);
+            team = !team;
+        }
+
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chess, Move};
+
+    #[test]
+    fn test_see_undefended() {
+        let chess = Chess::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        assert_eq!(chess.see(Move::new((6, 3), (3, 3))), 900);
+    }
+
+    #[test]
+    fn test_see_defended_pawn() {
+        // Rxe5 wins the undefended pawn
+        let chess = Chess::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        assert_eq!(chess.see(Move::new((7, 4), (3, 4))), 100);
+
+        // after Rxe5 fxe5 the rook is lost for a pawn
+        let chess = Chess::from_fen("4k3/8/5p2/4p3/8/8/8/4RK2 w - - 0 1").unwrap();
+        assert_eq!(chess.see(Move::new((7, 4), (3, 4))), -400);
+    }
+
+    #[test]
+    fn test_see_xray() {
+        // Nxe5 is answered by Nxe5, Bxe5, Qxe5 and Rxe5 from the white side
+        let chess =
+            Chess::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        assert_eq!(chess.see(Move::new((5, 3), (3, 4))), -220);
+    }
+}
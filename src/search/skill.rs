@@ -0,0 +1,157 @@
+use crate::{eval::figure_value, rng::Rng, FigureType};
+
+use super::{PvLine, SearchLimits};
+
+/// Full strength, the search is not weakened at all.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+
+/// Number of root moves a weakened engine chooses from.
+const CANDIDATES: usize = 4;
+
+/// Weakens the engine for human opponents. Below the maximum level the search is cut
+/// short by depth and node limits, and the move is picked among the best few root
+/// moves with a randomness that grows as the level drops. Moves losing much more than
+/// the best one are never picked, so the engine makes plausible mistakes instead of
+/// throwing away pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill {
+    level: u8,
+    rng: Rng,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill::new(MAX_SKILL_LEVEL)
+    }
+}
+
+impl Skill {
+    pub fn new(level: u8) -> Self {
+        Skill {
+            level: level.min(MAX_SKILL_LEVEL),
+            rng: Rng::default(),
+        }
+    }
+
+    /// The level playing at roughly the given Elo, between `MIN_ELO` and `MAX_ELO`.
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = (elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO);
+        Skill::new(level as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.level < MAX_SKILL_LEVEL
+    }
+
+    /// Fixes the random choices, mostly for tests.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    /// Depth and node limits of the level on top of `limits`.
+    pub fn limit(&self, limits: SearchLimits) -> SearchLimits {
+        if !self.is_enabled() {
+            return limits;
+        }
+        let depth = 1 + self.level as usize / 2;
+        let nodes = 256 << (self.level / 2);
+        SearchLimits {
+            depth: Some(limits.depth.map_or(depth, |limit| limit.min(depth))),
+            nodes: Some(limits.nodes.map_or(nodes, |limit| limit.min(nodes))),
+            ..limits
+        }
+    }
+
+    /// Root moves that need an exact score.
+    pub fn multipv(&self) -> usize {
+        if self.is_enabled() {
+            CANDIDATES
+        } else {
+            1
+        }
+    }
+
+    /// Largest score loss against the best move that is still accepted.
+    fn max_loss(&self) -> i32 {
+        figure_value(FigureType::Pawn) / 2 + 15 * (MAX_SKILL_LEVEL - self.level) as i32
+    }
+
+    /// Picks one of the best lines, `lines` are sorted best first. Worse moves get a
+    /// bonus that grows with the weakness, the rest is chance, as Stockfish does it.
+    /// `None` at full strength.
+    pub fn pick<'a>(&mut self, lines: &'a [PvLine]) -> Option<&'a PvLine> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let top = lines.first()?.score;
+        let candidates = lines
+            .iter()
+            .take(CANDIDATES)
+            .filter(|line| top - line.score <= self.max_loss() && !line.pv.is_empty())
+            .collect::<Vec<_>>();
+
+        let worst = candidates.last()?.score;
+        let delta = (top - worst).min(figure_value(FigureType::Pawn));
+        let weakness = 120 - 2 * self.level as i32;
+
+        candidates.into_iter().max_by_key(|line| {
+            let chance = self.rng.below(weakness as u64) as i32;
+            line.score + (weakness * (top - line.score) + delta * chance) / 128
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        search::{PvLine, SearchLimits, Skill, MAX_SKILL_LEVEL},
+        Move,
+    };
+
+    fn line(score: i32, col: usize) -> PvLine {
+        PvLine {
+            score,
+            depth: 1,
+            pv: vec![Move::new((6, col), (5, col))],
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = Skill::new(0).limit(SearchLimits::default());
+        assert_eq!(limits.depth, Some(1));
+        assert_eq!(limits.nodes, Some(256));
+
+        let limits = Skill::new(10).limit(SearchLimits::depth(2));
+        assert_eq!(limits.depth, Some(2));
+
+        let limits = Skill::default().limit(SearchLimits::default());
+        assert_eq!(limits, SearchLimits::default());
+        assert_eq!(Skill::from_elo(0).level(), 0);
+        assert_eq!(Skill::from_elo(5000).level(), MAX_SKILL_LEVEL);
+    }
+
+    #[test]
+    fn test_pick_varies_but_avoids_blunders() {
+        let lines = [line(50, 0), line(40, 1), line(30, 2), line(-400, 3)];
+        let mut skill = Skill::new(0);
+        skill.set_seed(3);
+
+        let mut picked = [0; 4];
+        for _ in 0..200 {
+            let line = skill.pick(&lines).unwrap();
+            picked[line.best_move().unwrap().start_col] += 1;
+        }
+        assert!(picked[..3].iter().all(|count| *count > 0));
+        assert_eq!(picked[3], 0);
+
+        assert_eq!(Skill::default().pick(&lines), None);
+    }
+}
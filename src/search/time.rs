@@ -0,0 +1,186 @@
+use std::time::{Duration, Instant};
+
+use crate::Move;
+
+use super::Clock;
+
+/// Default safety margin for the communication delay between engine and GUI.
+pub const DEFAULT_MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// Expected number of moves until the end of a game without a move limit.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Decides how long to think on a move. The search stops after an iteration once the
+/// optimum time is used, scaled up if the best move is unstable or the score dropped,
+/// and never runs longer than the maximum time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeManager {
+    start: Instant,
+    optimum: Duration,
+    maximum: Duration,
+    previous: Option<(Move, i32)>,
+    best_move_changes: f64,
+    score_drop: i32,
+}
+
+impl TimeManager {
+    fn with_times(optimum: Duration, maximum: Duration) -> Self {
+        TimeManager {
+            start: Instant::now(),
+            optimum,
+            maximum,
+            previous: None,
+            best_move_changes: 0.,
+            score_drop: 0,
+        }
+    }
+
+    /// Splits the remaining time and increment across the expected moves to go.
+    pub fn new(clock: Clock, move_overhead: Duration) -> Self {
+        let moves_to_go = clock
+            .moves_to_go
+            .map_or(DEFAULT_MOVES_TO_GO, |moves| moves.clamp(1, 50) as u32);
+
+        // every remaining move pays the overhead
+        let available = (clock.time + clock.inc * (moves_to_go - 1))
+            .saturating_sub(move_overhead * moves_to_go);
+        let safe_time = clock.time.saturating_sub(move_overhead);
+
+        let optimum = (available / moves_to_go).min(safe_time);
+        let maximum = if moves_to_go == 1 {
+            safe_time
+        } else {
+            (optimum * 5).min(safe_time * 4 / 5)
+        };
+
+        TimeManager::with_times(optimum, maximum.max(optimum))
+    }
+
+    /// Thinks for exactly `movetime` minus the overhead.
+    pub fn fixed(movetime: Duration, move_overhead: Duration) -> Self {
+        let time = movetime
+            .saturating_sub(move_overhead)
+            .max(Duration::from_millis(1));
+        TimeManager::with_times(time, time)
+    }
+
+    pub fn optimum(&self) -> Duration {
+        self.optimum
+    }
+
+    pub fn maximum(&self) -> Duration {
+        self.maximum
+    }
+
+    /// The search is aborted at this point, even in the middle of an iteration.
+    pub fn deadline(&self) -> Instant {
+        self.start + self.maximum
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        self.best_move_changes /= 2.;
+        if let Some((previous_move, previous_score)) = self.previous {
+            if previous_move != best_move {
+                self.best_move_changes += 1.;
+            }
+            self.score_drop = previous_score - score;
+        }
+        self.previous = Some((best_move, score));
+    }
+
+    /// Time the search may use after the last iteration, at most the maximum time.
+    pub fn soft_limit(&self) -> Duration {
+        let instability = 1. + self.best_move_changes.min(1.5);
+        let score_drop = 1. + self.score_drop.clamp(0, 100) as f64 / 100.;
+        self.optimum
+            .mul_f64(instability * score_drop)
+            .min(self.maximum)
+    }
+
+    /// Whether another iteration should be started after `elapsed`.
+    pub fn should_stop(&self, elapsed: Duration) -> bool {
+        elapsed >= self.soft_limit()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        search::{time::TimeManager, Clock},
+        Move,
+    };
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_splits_time() {
+        let clock = Clock {
+            time: ms(60_000),
+            inc: ms(0),
+            moves_to_go: None,
+        };
+        let tm = TimeManager::new(clock, ms(0));
+        assert_eq!(tm.optimum(), ms(2000));
+        assert_eq!(tm.maximum(), ms(10_000));
+
+        let tm = TimeManager::new(
+            Clock {
+                inc: ms(1000),
+                ..clock
+            },
+            ms(0),
+        );
+        assert!(tm.optimum() > ms(2900) && tm.optimum() < ms(3000));
+    }
+
+    #[test]
+    fn test_never_exceeds_clock() {
+        let tm = TimeManager::new(
+            Clock {
+                time: ms(100),
+                inc: ms(5000),
+                moves_to_go: Some(1),
+            },
+            ms(30),
+        );
+        assert!(tm.maximum() <= ms(70));
+        assert!(tm.optimum() <= tm.maximum());
+
+        let tm = TimeManager::fixed(ms(1000), ms(30));
+        assert_eq!(tm.maximum(), ms(970));
+    }
+
+    #[test]
+    fn test_extends_on_instability_and_score_drop() {
+        let clock = Clock {
+            time: ms(60_000),
+            inc: ms(0),
+            moves_to_go: None,
+        };
+        let mut tm = TimeManager::new(clock, ms(0));
+        let (a, b) = (Move::new((6, 4), (4, 4)), Move::new((6, 3), (4, 3)));
+
+        tm.update(a, 20);
+        tm.update(a, 20);
+        assert_eq!(tm.soft_limit(), tm.optimum());
+        assert!(tm.should_stop(ms(2000)));
+
+        tm.update(b, 20);
+        assert!(tm.soft_limit() > tm.optimum());
+        assert!(!tm.should_stop(ms(2000)));
+
+        let mut tm = TimeManager::new(clock, ms(0));
+        tm.update(a, 20);
+        tm.update(a, -80);
+        assert_eq!(tm.soft_limit(), tm.optimum() * 2);
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    mem::size_of,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::{FigureType, Move};
+
+use super::MATE_BOUND;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The score is at least this high (fail high).
+    Lower,
+    /// The score is at most this high (fail low).
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub key: u64,
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub age: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TtStats {
+    pub probes: u64,
+    pub hits: u64,
+    pub stores: u64,
+    /// Stores that replaced an entry of a different position.
+    pub overwrites: u64,
+}
+
+impl TtStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.;
+        }
+        self.hits as f64 / self.probes as f64
+    }
+}
+
+#[derive(Debug, Default)]
+struct AtomicStats {
+    probes: AtomicU64,
+    hits: AtomicU64,
+    stores: AtomicU64,
+    overwrites: AtomicU64,
+}
+
+/// One entry, written without locks. The key is stored xored with the data, so that a
+/// torn write of another thread turns into a key mismatch instead of a corrupt entry.
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+const PROMOTIONS: [FigureType; 5] = [
+    FigureType::Empty,
+    FigureType::Queen,
+    FigureType::Rook,
+    FigureType::Bishop,
+    FigureType::Knight,
+];
+
+/// Packs a move into 16 bits: a presence bit, the promotion and both squares.
+fn pack_move(mv: Option<Move>) -> u64 {
+    let Some(mv) = mv else {
+        return 0;
+    };
+    let promotion = PROMOTIONS
+        .iter()
+        .position(|figure| *figure == mv.promotion)
+        .unwrap_or(0) as u64;
+    let from = (mv.start_row * 8 + mv.start_col) as u64;
+    let to = (mv.end_row * 8 + mv.end_col) as u64;
+    1 << 15 | promotion << 12 | from << 6 | to
+}
+
+fn unpack_move(bits: u64) -> Option<Move> {
+    if bits & 1 << 15 == 0 {
+        return None;
+    }
+    let square = |bits: u64| ((bits >> 3 & 7) as usize, (bits & 7) as usize);
+    Some(Move::with_promotion(
+        square(bits >> 6),
+        square(bits),
+        PROMOTIONS[(bits >> 12 & 7) as usize],
+    ))
+}
+
+// data layout: move 0..16, score 16..32, depth 32..40, bound 40..42, age 42..50
+fn pack(entry: &TtEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    pack_move(entry.best_move)
+        | (entry.score as i16 as u16 as u64) << 16
+        | (entry.depth as u64) << 32
+        | bound << 40
+        | (entry.age as u64) << 42
+}
+
+/// `None` for slots that were never written.
+fn unpack(key: u64, data: u64) -> Option<TtEntry> {
+    let bound = match data >> 40 & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    Some(TtEntry {
+        key,
+        best_move: unpack_move(data & 0xffff),
+        score: (data >> 16) as u16 as i16 as i32,
+        depth: (data >> 32) as u8,
+        bound,
+        age: (data >> 42) as u8,
+    })
+}
+
+/// Fixed size hash table with a power of two number of entries, indexed by the
+/// zobrist key of the position. It is shared between the threads of a search, all
+/// accesses go through atomics.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    age: AtomicU8,
+    stats: AtomicStats,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let mut tt = TranspositionTable {
+            slots: vec![],
+            age: AtomicU8::new(0),
+            stats: AtomicStats::default(),
+        };
+        tt.resize(size_mb);
+        tt
+    }
+
+    /// Reallocates the table with the largest power of two number of entries fitting
+    /// into `size_mb` megabytes. Clears all entries.
+    pub fn resize(&mut self, size_mb: usize) {
+        let max_entries = (size_mb * 1024 * 1024 / size_of::<Slot>()).max(1);
+        let len = 1 << max_entries.ilog2();
+        self.slots = (0..len).map(|_| Slot::default()).collect();
+        self.clear();
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+        for counter in [
+            &self.stats.probes,
+            &self.stats.hits,
+            &self.stats.stores,
+            &self.stats.overwrites,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Entries of earlier searches are preferred to be replaced.
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    /// Reads the entry in the slot of `key`, whatever position it belongs to.
+    fn load(&self, key: u64) -> Option<TtEntry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        let stored_key = slot.key.load(Ordering::Relaxed) ^ data;
+        unpack(stored_key, data)
+    }
+
+    /// The score of the returned entry is already adjusted to `ply`.
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TtEntry> {
+        self.stats.probes.fetch_add(1, Ordering::Relaxed);
+        let mut entry = self.load(key).filter(|entry| entry.key == key)?;
+        self.stats.hits.fetch_add(1, Ordering::Relaxed);
+        entry.score = score_from_tt(entry.score, ply);
+        Some(entry)
+    }
+
+    pub fn store(
+        &self,
+        key: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: usize,
+        bound: Bound,
+        ply: usize,
+    ) {
+        let age = self.age.load(Ordering::Relaxed);
+
+        // Replacement scheme: always replace entries of older searches, otherwise
+        // keep deeper results of other positions.
+        let mut best_move = best_move;
+        if let Some(old) = self.load(key) {
+            if old.key == key {
+                best_move = best_move.or(old.best_move);
+                if bound != Bound::Exact && old.age == age && depth + 2 < old.depth as usize {
+                    return;
+                }
+            } else if old.age == age && depth < old.depth as usize {
+                return;
+            } else {
+                self.stats.overwrites.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        self.stats.stores.fetch_add(1, Ordering::Relaxed);
+        let data = pack(&TtEntry {
+            key,
+            best_move,
+            score: score_to_tt(score, ply),
+            depth: depth.min(u8::MAX as usize) as u8,
+            bound,
+            age,
+        });
+        let slot = self.slot(key);
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Permille of used entries written in the current search, as UCI reports it.
+    pub fn hashfull(&self) -> usize {
+        let sample = self.slots.len().min(1000);
+        let age = self.age.load(Ordering::Relaxed);
+        let used = self.slots[..sample]
+            .iter()
+            .filter_map(|slot| unpack(0, slot.data.load(Ordering::Relaxed)))
+            .filter(|entry| entry.age == age)
+            .count();
+        used * 1000 / sample
+    }
+
+    pub fn stats(&self) -> TtStats {
+        TtStats {
+            probes: self.stats.probes.load(Ordering::Relaxed),
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            stores: self.stats.stores.load(Ordering::Relaxed),
+            overwrites: self.stats.overwrites.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Mate scores are stored relative to the position instead of the root.
+pub fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+pub fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        search::{
+            tt::{Bound, TranspositionTable},
+            MATE,
+        },
+        FigureType, Move,
+    };
+
+    #[test]
+    fn test_size_is_power_of_two() {
+        let tt = TranspositionTable::new(1);
+        assert!(tt.len().is_power_of_two());
+        assert!(tt.len() * std::mem::size_of::<super::Slot>() <= 1024 * 1024);
+    }
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        let mv = Move::new((6, 4), (4, 4));
+        tt.store(42, Some(mv), 17, 5, Bound::Exact, 0);
+
+        let entry = tt.probe(42, 0).unwrap();
+        assert_eq!(entry.best_move, Some(mv));
+        assert_eq!(entry.score, 17);
+        assert_eq!(entry.depth, 5);
+        assert!(tt.probe(43, 0).is_none());
+        assert_eq!(tt.stats().hits, 1);
+        assert_eq!(tt.stats().probes, 2);
+    }
+
+    #[test]
+    fn test_mate_score_adjustment() {
+        let tt = TranspositionTable::new(1);
+        // mate in 3 plies seen from ply 2 is a mate in 1 ply from the stored position
+        tt.store(7, None, MATE - 3, 4, Bound::Exact, 2);
+        assert_eq!(tt.probe(7, 2).unwrap().score, MATE - 3);
+        assert_eq!(tt.probe(7, 4).unwrap().score, MATE - 5);
+    }
+
+    #[test]
+    fn test_replacement_prefers_depth() {
+        let tt = TranspositionTable::new(1);
+        let other = 1 + tt.len() as u64;
+
+        tt.store(1, None, 0, 8, Bound::Exact, 0);
+        tt.store(other, None, 0, 2, Bound::Exact, 0);
+        assert!(tt.probe(1, 0).is_some());
+
+        tt.new_search();
+        tt.store(other, None, 0, 2, Bound::Exact, 0);
+        assert!(tt.probe(other, 0).is_some());
+    }
+
+    #[test]
+    fn test_packing_roundtrip() {
+        let tt = TranspositionTable::new(1);
+        let promotion = Move::with_promotion((1, 7), (0, 6), FigureType::Knight);
+        tt.store(9, Some(promotion), -MATE + 10, 200, Bound::Upper, 0);
+
+        let entry = tt.probe(9, 0).unwrap();
+        assert_eq!(entry.best_move, Some(promotion));
+        assert_eq!(entry.score, -MATE + 10);
+        assert_eq!(entry.depth, 200);
+        assert_eq!(entry.bound, Bound::Upper);
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let tt = TranspositionTable::new(1);
+        std::thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let tt = &tt;
+                scope.spawn(move || {
+                    for key in 0..1000 {
+                        tt.store(key * 4 + thread, None, thread as i32, 1, Bound::Exact, 0);
+                    }
+                });
+            }
+        });
+        for key in 0..4000 {
+            let entry = tt.probe(key, 0).unwrap();
+            assert_eq!(entry.score, (key % 4) as i32);
+        }
+    }
+}
@@ -2,7 +2,7 @@ use macroquad::{prelude::YELLOW, shapes::draw_circle};
 
 use crate::{SIZE, X_DIST, Y_DIST};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Selection {
     pub selected_field: Option<(usize, usize)>,
     pub moves: Vec<(usize, usize)>,
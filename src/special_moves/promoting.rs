@@ -73,6 +73,7 @@ impl Chess {
         self.promote_pawn_at(to_promote, figure);
 
         self.player = !self.player;
+        self.refresh_hash();
         self.state = State::Select;
     }
 }
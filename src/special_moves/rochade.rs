@@ -1,4 +1,4 @@
-use crate::{Chess, FigureType, Team, COLS, ROWS};
+use crate::{Chess, FigureType, Move, Team, COLS, ROWS};
 
 impl Chess {
     pub fn tried_rochade(
@@ -73,3 +73,39 @@ impl Chess {
         new_rook_field.figure = Some(rook);
     }
 }
+
+impl Chess {
+    /// Rochades of the current player in the encoding used by `make_move`.
+    /// The king may neither be in check nor pass an attacked field.
+    pub fn rochade_moves(&mut self) -> Vec<Move> {
+        let rights = self.castling_rights();
+        let (row, rights) = match self.player {
+            Team::White => (ROWS - 1, [rights[0], rights[1]]),
+            Team::Black => (0, [rights[2], rights[3]]),
+        };
+
+        if rights == [false, false] || self.check_check(self.player).is_some() {
+            return vec![];
+        }
+
+        // (right, rook col, passed col, king destination col)
+        [(rights[0], COLS - 1, 5, 6), (rights[1], 0, 3, 2)]
+            .into_iter()
+            .filter(|(right, rook_col, passed_col, _)| {
+                *right
+                    && self
+                        .is_rochade_valid(((row, 4), (row, *rook_col)))
+                        .is_some()
+                    && !self.is_passed_field_attacked((row, 4), (row, *passed_col))
+            })
+            .map(|(_, _, _, king_col)| Move::new((row, 4), (row, king_col)))
+            .collect()
+    }
+
+    fn is_passed_field_attacked(&mut self, king: (usize, usize), passed: (usize, usize)) -> bool {
+        self.field_mut(passed).figure = self.field_mut(king).figure.take();
+        let attacked = self.check_check(self.player).is_some();
+        self.field_mut(king).figure = self.field_mut(passed).figure.take();
+        attacked
+    }
+}
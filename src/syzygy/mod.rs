@@ -0,0 +1,495 @@
+mod table;
+
+use std::{collections::HashMap, ffi::OsStr, fmt::Display, fs, io, path::PathBuf};
+
+use once_cell::sync::OnceCell;
+
+pub use table::MAX_PIECES;
+use table::{Lookup, Material, Table, TableKind, TbPosition};
+
+use crate::{Chess, FigureType, Move};
+
+/// Distance to zeroing of a position whose best move is a capture or pawn move, by WDL.
+const WDL_TO_DTZ: [i32; 5] = [-1, -101, 0, 101, 1];
+
+/// Outcome of a position with perfect play, from the view of the player to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    /// A loss that is drawn by the 50 move rule.
+    BlessedLoss,
+    Draw,
+    /// A win that is drawn by the 50 move rule.
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+/// Result of a tablebase probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Probe {
+    pub wdl: Wdl,
+    /// Plies until the next capture or pawn move with perfect play, negative if losing.
+    /// `None` if the DTZ table is missing.
+    pub dtz: Option<i32>,
+}
+
+impl Display for Probe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self.wdl {
+            Wdl::Loss => "loss",
+            Wdl::BlessedLoss => "blessed loss",
+            Wdl::Draw => return write!(f, "draw"),
+            Wdl::CursedWin => "cursed win",
+            Wdl::Win => "win",
+        };
+        match self.dtz {
+            Some(dtz) => write!(f, "{outcome} in {}", dtz.abs()),
+            None => write!(f, "{outcome}"),
+        }
+    }
+}
+
+/// WDL and DTZ files of one material configuration, read on first use.
+#[derive(Debug, Default)]
+struct TableFiles {
+    paths: [Option<PathBuf>; 2],
+    tables: [OnceCell<Option<Table>>; 2],
+}
+
+/// Syzygy endgame tablebases found in a set of directories.
+#[derive(Debug, Default)]
+pub struct Tablebase {
+    /// Indexed by table names like "KRPvKR".
+    files: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Scans the directories in `paths`, separated like the `PATH` variable, for table
+    /// files. The tables themselves are read when they are probed for the first time.
+    pub fn open(paths: impl AsRef<OsStr>) -> io::Result<Tablebase> {
+        let mut tablebase = Tablebase::default();
+        for dir in std::env::split_paths(&paths) {
+            if dir.as_os_str().is_empty() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                let extension = path.extension().and_then(OsStr::to_str);
+                let Some(kind) = [TableKind::Wdl, TableKind::Dtz]
+                    .into_iter()
+                    .find(|kind| Some(kind.extension()) == extension)
+                else {
+                    continue;
+                };
+                let Some(material) = path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .and_then(Material::from_name)
+                else {
+                    continue;
+                };
+                if material.pieces() > MAX_PIECES {
+                    continue;
+                }
+
+                let files = tablebase.files.entry(material.name()).or_default();
+                files.paths[kind as usize] = Some(path);
+                if kind == TableKind::Wdl {
+                    tablebase.max_pieces = tablebase.max_pieces.max(material.pieces());
+                }
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// Number of material configurations with a WDL table.
+    pub fn len(&self) -> usize {
+        self.files
+            .values()
+            .filter(|files| files.paths[TableKind::Wdl as usize].is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Most pieces of any WDL table, kings included.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether `chess` may be in the tablebase. Tables do not cover rochade rights.
+    pub fn can_probe(&self, chess: &Chess) -> bool {
+        let pieces = chess
+            .fields
+            .iter()
+            .flatten()
+            .filter(|field| field.figure.is_some())
+            .count();
+        pieces <= self.max_pieces && !chess.castling_rights().contains(&true)
+    }
+
+    /// Outcome and distance to zeroing of `chess`, `None` if it is not in the tablebase.
+    pub fn probe(&self, chess: &Chess) -> Option<Probe> {
+        if !self.can_probe(chess) {
+            return None;
+        }
+        let mut chess = chess.clone();
+        let wdl = self.probe_wdl(&mut chess)?;
+        Some(Probe {
+            wdl,
+            dtz: self.probe_dtz(&mut chess),
+        })
+    }
+
+    /// Outcome of `chess`, en passant captures included. Expects `can_probe` to be true.
+    pub fn probe_wdl(&self, chess: &mut Chess) -> Option<Wdl> {
+        self.wdl(chess).map(|(wdl, _)| Wdl::from_value(wdl))
+    }
+
+    /// Plies until the next zeroing move, see `Probe::dtz`. A mated player gets -1.
+    /// Expects `can_probe` to be true.
+    pub fn probe_dtz(&self, chess: &mut Chess) -> Option<i32> {
+        self.dtz(chess)
+    }
+
+    /// The move keeping the best outcome of `chess` under the 50 move rule, winning as
+    /// fast as possible and losing as slow as possible.
+    pub fn best_move(&self, chess: &mut Chess) -> Option<(Move, Probe)> {
+        if !self.can_probe(chess) {
+            return None;
+        }
+        let halfmove_clock = chess.halfmove_clock as i32;
+        let repeated = chess.repetitions() > 0;
+
+        let mut best: Option<(i32, i32, Move)> = None;
+        for mv in chess.legal_moves() {
+            chess.make_move(mv);
+            let dtz = if chess.halfmove_clock == 0 {
+                self.wdl(chess)
+                    .map(|(wdl, _)| WDL_TO_DTZ[(2 - wdl) as usize])
+            } else {
+                self.dtz(chess).map(|dtz| -dtz - dtz.signum())
+            };
+            let dtz = match dtz {
+                Some(2) if is_mate(chess) => Some(1),
+                dtz => dtz,
+            };
+            chess.unmake_move();
+            let dtz = dtz?;
+
+            // guaranteed wins first, then wins that may run into the 50 move rule
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 && !repeated {
+                    1000
+                } else {
+                    1000 - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {
+                    -1000
+                } else {
+                    -1000 + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+            if best.is_none_or(|(best_rank, best_dtz, _)| (rank, -dtz) > (best_rank, -best_dtz)) {
+                best = Some((rank, dtz, mv));
+            }
+        }
+
+        let (_, _, mv) = best?;
+        Some((mv, self.probe(chess)?))
+    }
+
+    /// Reads the table of `material` or its mirrored material, also returns whether it
+    /// was the mirrored one.
+    fn table(&self, material: &Material, kind: TableKind) -> Option<(&Table, bool)> {
+        let name = material.name();
+        let (name, flip) = if self.files.contains_key(&name) {
+            (name, false)
+        } else {
+            (material.mirrored().name(), true)
+        };
+        let files = self.files.get(&name)?;
+        let table = files.tables[kind as usize].get_or_init(|| {
+            let path = files.paths[kind as usize].as_ref()?;
+            let material = Material::from_name(&name)?;
+            Table::read(path, kind, &material).ok()
+        });
+        table.as_ref().map(|table| (table, flip))
+    }
+
+    fn probe_table(&self, chess: &Chess, kind: TableKind, wdl: i32) -> Option<Lookup> {
+        let pos = TbPosition::new(chess);
+        let material = pos.material();
+        if material.pieces() == 2 {
+            return Some(Lookup::Value(0));
+        }
+        let (table, flip) = self.table(&material, kind)?;
+        table.probe(&pos, flip, wdl)
+    }
+
+    fn wdl_table(&self, chess: &Chess) -> Option<i32> {
+        match self.probe_table(chess, TableKind::Wdl, 0)? {
+            Lookup::Value(wdl) => Some(wdl),
+            Lookup::OtherSide => None,
+        }
+    }
+
+    /// Alpha-beta search over captures, the tables assume that no capture improves the
+    /// score.
+    fn probe_ab(&self, chess: &mut Chess, mut alpha: i32, beta: i32) -> Option<i32> {
+        for mv in captures(chess) {
+            chess.make_move(mv);
+            let score = self.probe_ab(chess, -beta, -alpha).map(|score| -score);
+            chess.unmake_move();
+            let score = score?;
+            if score > alpha {
+                if score >= beta {
+                    return Some(score);
+                }
+                alpha = score;
+            }
+        }
+        Some(alpha.max(self.wdl_table(chess)?))
+    }
+
+    /// WDL value from -2 to 2, and whether a capture is the only best move.
+    fn wdl(&self, chess: &mut Chess) -> Option<(i32, bool)> {
+        let mut best_capture = -3;
+        let mut best_en_passant = -3;
+
+        for mv in captures(chess) {
+            let en_passant = is_en_passant(chess, mv);
+            chess.make_move(mv);
+            let score = self.probe_ab(chess, -2, -best_capture).map(|score| -score);
+            chess.unmake_move();
+            let score = score?;
+            if score > best_capture {
+                if score == 2 {
+                    return Some((2, true));
+                }
+                if !en_passant {
+                    best_capture = score;
+                } else if score > best_en_passant {
+                    best_en_passant = score;
+                }
+            }
+        }
+
+        let value = self.wdl_table(chess)?;
+
+        if best_en_passant > best_capture {
+            if best_en_passant > value {
+                return Some((best_en_passant, true));
+            }
+            best_capture = best_en_passant;
+        }
+        if best_capture >= value {
+            return Some((best_capture, best_capture > 0));
+        }
+
+        // the table does not know that an en passant capture prevents a stalemate
+        if best_en_passant > -3 && value == 0 {
+            let only_en_passant = chess
+                .legal_moves()
+                .into_iter()
+                .all(|mv| is_en_passant(chess, mv));
+            if only_en_passant && chess.check_check(chess.player).is_none() {
+                return Some((best_en_passant, true));
+            }
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, chess: &mut Chess) -> Option<i32> {
+        let (wdl, capture_only) = self.wdl(chess)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if capture_only {
+            return Some(WDL_TO_DTZ[(wdl + 2) as usize]);
+        }
+
+        let moves = chess.legal_moves();
+        let zeroing = |chess: &Chess, mv: Move| {
+            chess.is_capture(mv)
+                || matches!(chess.field(mv.from()).figure, Some(figure) if figure.figure == FigureType::Pawn)
+        };
+
+        // a winning pawn move resets the counter right away
+        if wdl > 0 {
+            for mv in moves.iter().copied() {
+                if !zeroing(chess, mv) || chess.is_capture(mv) {
+                    continue;
+                }
+                chess.make_move(mv);
+                let value = self.wdl(chess).map(|(value, _)| -value);
+                chess.unmake_move();
+                if value? == wdl {
+                    return Some(WDL_TO_DTZ[(wdl + 2) as usize]);
+                }
+            }
+        }
+
+        if let Lookup::Value(dtz) = self.probe_table(chess, TableKind::Dtz, wdl)? {
+            return Some(WDL_TO_DTZ[(wdl + 2) as usize] + if wdl > 0 { dtz } else { -dtz });
+        }
+
+        // the table only stores the other side to move, look one ply ahead
+        let mut best = if wdl > 0 {
+            i32::MAX
+        } else {
+            WDL_TO_DTZ[(wdl + 2) as usize]
+        };
+        for mv in moves {
+            if zeroing(chess, mv) {
+                continue;
+            }
+            chess.make_move(mv);
+            let value = self.dtz(chess).map(|value| -value);
+            let mate = value == Some(1) && is_mate(chess);
+            chess.unmake_move();
+            let value = value?;
+
+            if mate {
+                best = 1;
+            } else if wdl > 0 {
+                if value > 0 && value + 1 < best {
+                    best = value + 1;
+                }
+            } else if value - 1 < best {
+                best = value - 1;
+            }
+        }
+        Some(best)
+    }
+}
+
+fn captures(chess: &mut Chess) -> Vec<Move> {
+    let mut moves = chess.legal_moves();
+    moves.retain(|mv| chess.is_capture(*mv));
+    moves
+}
+
+fn is_en_passant(chess: &Chess, mv: Move) -> bool {
+    chess.is_capture(mv) && chess.field(mv.to()).figure.is_none()
+}
+
+fn is_mate(chess: &mut Chess) -> bool {
+    chess.check_check(chess.player).is_some() && chess.legal_moves().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        syzygy::{Probe, Tablebase, Wdl},
+        Chess, Move,
+    };
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy");
+
+    fn tablebase() -> Tablebase {
+        let tablebase = Tablebase::open(FIXTURES).unwrap();
+        assert!(!tablebase.is_empty(), "no tables in {FIXTURES}");
+        tablebase
+    }
+
+    #[test]
+    fn test_display() {
+        let probe = Probe {
+            wdl: Wdl::Win,
+            dtz: Some(13),
+        };
+        assert_eq!(probe.to_string(), "win in 13");
+        let probe = Probe {
+            wdl: Wdl::Loss,
+            dtz: Some(-4),
+        };
+        assert_eq!(probe.to_string(), "loss in 4");
+    }
+
+    #[test]
+    fn test_open_ignores_other_files() {
+        let tablebase = Tablebase::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("src")).unwrap();
+        assert!(tablebase.is_empty());
+        let chess = Chess::from_fen("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe(&chess), None);
+    }
+
+    #[test]
+    fn test_probe_wdl() {
+        let tablebase = tablebase();
+        for (fen, wdl) in [
+            ("k7/8/8/8/8/8/8/KQ6 w - - 0 1", Wdl::Win),
+            // the queen is lost
+            ("7K/8/8/8/8/8/1Qk5/8 b - - 0 1", Wdl::Draw),
+            ("8/8/8/4k3/8/8/8/KR6 b - - 0 1", Wdl::Loss),
+            ("8/4P3/8/8/8/8/8/K5k1 w - - 0 1", Wdl::Win),
+            // stalemate
+            ("8/8/8/8/8/8/2k5/K1n5 w - - 0 1", Wdl::Draw),
+            ("8/8/8/8/8/4k3/8/KBN5 w - - 0 1", Wdl::Win),
+            // mirrored material, probed in the KQvK and KPvK tables
+            ("kq6/8/8/8/8/8/8/7K b - - 0 1", Wdl::Win),
+            ("8/8/8/8/8/1k6/1p6/1K6 w - - 0 1", Wdl::Draw),
+        ] {
+            let chess = Chess::from_fen(fen).unwrap();
+            assert_eq!(
+                tablebase.probe(&chess).map(|probe| probe.wdl),
+                Some(wdl),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_probe_dtz_and_best_move() {
+        let tablebase = tablebase();
+        // mate in one
+        let mut chess = Chess::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        let probe = tablebase.probe(&chess).unwrap();
+        assert_eq!(probe.wdl, Wdl::Win);
+        assert_eq!(probe.dtz, Some(1));
+        let (mv, _) = tablebase.best_move(&mut chess).unwrap();
+        assert_eq!(mv, Move::new((7, 7), (0, 7)));
+
+        // the mated player
+        let chess = Chess::from_fen("R1k5/8/2K5/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(tablebase.probe(&chess).unwrap().dtz, Some(-1));
+
+        // the DTZ table only stores white to move, black has to look one ply ahead and
+        // must not walk into Ra8#
+        let mut chess = Chess::from_fen("6k1/8/6K1/8/8/8/8/R7 b - - 0 1").unwrap();
+        let (mv, probe) = tablebase.best_move(&mut chess).unwrap();
+        assert_eq!(probe.wdl, Wdl::Loss);
+        assert_eq!(probe.dtz, Some(-4));
+        assert_eq!(mv, Move::new((0, 6), (0, 5)));
+
+        // mirrored material
+        for (fen, dtz) in [
+            ("8/8/8/8/8/1k6/1p6/1K6 b - - 0 1", 5),
+            ("1q6/8/8/8/8/8/8/K1k5 w - - 0 1", -2),
+        ] {
+            let chess = Chess::from_fen(fen).unwrap();
+            assert_eq!(tablebase.probe(&chess).unwrap().dtz, Some(dtz), "{fen}");
+        }
+    }
+}
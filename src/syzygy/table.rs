@@ -0,0 +1,1110 @@
+use std::{fs, io, path::Path};
+
+use crate::{Chess, FigureType, Team};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// Tables hold at most this many pieces, kings included.
+pub const MAX_PIECES: usize = 7;
+
+/// Piece letters in the order used by table names, e.g. "KQRvKR".
+const PIECE_LETTERS: [(char, usize); 6] = [
+    ('K', KING),
+    ('Q', QUEEN),
+    ('R', ROOK),
+    ('B', BISHOP),
+    ('N', KNIGHT),
+    ('P', PAWN),
+];
+
+// piece codes of the file format, the color is stored in bit 3
+const PAWN: usize = 1;
+const KNIGHT: usize = 2;
+const BISHOP: usize = 3;
+const ROOK: usize = 4;
+const QUEEN: usize = 5;
+const KING: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// Pieces of a position in the square numbering of the tables, a1 = 0 and h8 = 63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TbPosition {
+    /// Bitboards indexed by color (white = 0) and piece code.
+    boards: [[u64; 7]; 2],
+    pub white_to_move: bool,
+}
+
+impl TbPosition {
+    pub fn new(chess: &Chess) -> TbPosition {
+        let mut boards = [[0; 7]; 2];
+        for field in chess.fields.iter().flatten() {
+            let Some(figure) = field.figure else {
+                continue;
+            };
+            let piece = match figure.figure {
+                FigureType::Pawn => PAWN,
+                FigureType::Knight => KNIGHT,
+                FigureType::Bishop => BISHOP,
+                FigureType::Rook => ROOK,
+                FigureType::Queen => QUEEN,
+                FigureType::King => KING,
+                FigureType::Empty => continue,
+            };
+            let (row, col) = field.idxs;
+            boards[figure.team as usize][piece] |= 1 << ((7 - row) * 8 + col);
+        }
+        TbPosition {
+            boards,
+            white_to_move: chess.player == Team::White,
+        }
+    }
+
+    pub fn material(&self) -> Material {
+        let mut counts = [[0; 7]; 2];
+        for (color, boards) in self.boards.iter().enumerate() {
+            for (piece, board) in boards.iter().enumerate() {
+                counts[color][piece] = board.count_ones() as u8;
+            }
+        }
+        Material { counts }
+    }
+}
+
+/// Number of pieces per color and piece code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Material {
+    counts: [[u8; 7]; 2],
+}
+
+impl Material {
+    /// Parses table names like "KRPvKR", white is the side in front of the "v".
+    pub fn from_name(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 7]; 2];
+        for (color, side) in [white, black].iter().enumerate() {
+            for letter in side.chars() {
+                let (_, piece) = PIECE_LETTERS.iter().find(|(c, _)| *c == letter)?;
+                counts[color][*piece] += 1;
+            }
+        }
+        let material = Material { counts };
+        (counts[0][KING] == 1 && counts[1][KING] == 1 && material.name() == name)
+            .then_some(material)
+    }
+
+    pub fn name(&self) -> String {
+        let side = |color: usize| {
+            PIECE_LETTERS
+                .iter()
+                .flat_map(|(letter, piece)| {
+                    std::iter::repeat_n(*letter, self.counts[color][*piece] as usize)
+                })
+                .collect::<String>()
+        };
+        format!("{}v{}", side(0), side(1))
+    }
+
+    /// The same material with the colors swapped.
+    pub fn mirrored(&self) -> Material {
+        Material {
+            counts: [self.counts[1], self.counts[0]],
+        }
+    }
+
+    pub fn pieces(&self) -> usize {
+        self.counts
+            .iter()
+            .flatten()
+            .map(|count| *count as usize)
+            .sum()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.counts[0][PAWN] + self.counts[1][PAWN] > 0
+    }
+}
+
+const TRIANGLE: [usize; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6, //
+    0, 7, 3, 4, 4, 3, 7, 0, //
+    1, 3, 8, 5, 5, 8, 3, 1, //
+    2, 4, 5, 9, 9, 5, 4, 2, //
+    2, 4, 5, 9, 9, 5, 4, 2, //
+    1, 3, 8, 5, 5, 8, 3, 1, //
+    0, 7, 3, 4, 4, 3, 7, 0, //
+    6, 0, 1, 2, 2, 1, 0, 6, //
+];
+
+const FILE_TO_FILE: [usize; 8] = [0, 1, 2, 3, 3, 2, 1, 0];
+
+/// Positive above the a1-h8 diagonal, negative below it.
+fn off_diag(sq: usize) -> i32 {
+    (sq >> 3) as i32 - (sq & 7) as i32
+}
+
+fn flip_diag(sq: usize) -> usize {
+    ((sq >> 3) | (sq << 3)) & 63
+}
+
+/// Index of both kings if the first one is in the a1-d1-d4 triangle. Kings on the
+/// diagonal keep the other king on or below the diagonal, those positions come last.
+const KK_IDX: [[i16; 64]; 10] = {
+    const fn touching(a: usize, b: usize) -> bool {
+        let files = (a & 7) as i32 - (b & 7) as i32;
+        let ranks = (a >> 3) as i32 - (b >> 3) as i32;
+        files >= -1 && files <= 1 && ranks >= -1 && ranks <= 1
+    }
+
+    // squares of the triangle in the order of their `TRIANGLE` value
+    const KINGS: [usize; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+    let mut table = [[-1; 64]; 10];
+    let mut idx = 0;
+    let mut i = 0;
+    while i < 6 {
+        let mut sq = 0;
+        while sq < 64 {
+            if !touching(KINGS[i], sq) {
+                table[i][sq] = idx;
+                idx += 1;
+            }
+            sq += 1;
+        }
+        i += 1;
+    }
+
+    let mut on_diagonal = 0;
+    while on_diagonal < 2 {
+        let mut i = 6;
+        while i < 10 {
+            let mut sq = 0;
+            while sq < 64 {
+                let below = (sq & 7) > (sq >> 3);
+                let diagonal = (sq & 7) == (sq >> 3);
+                let wanted = if on_diagonal == 0 { below } else { diagonal };
+                if wanted && !touching(KINGS[i], sq) {
+                    table[i][sq] = idx;
+                    idx += 1;
+                }
+                sq += 1;
+            }
+            i += 1;
+        }
+        on_diagonal += 1;
+    }
+    table
+};
+
+/// Numbers the squares below the diagonal 0..28, mirrored above it, and the diagonal
+/// itself 28..36.
+const LOWER: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut idx = 0;
+    let mut sq = 0;
+    while sq < 64 {
+        let (rank, file) = (sq >> 3, sq & 7);
+        if file > rank {
+            table[sq] = idx;
+            table[file * 8 + rank] = idx;
+            idx += 1;
+        } else if file == rank {
+            table[sq] = 28 + rank;
+        }
+        sq += 1;
+    }
+    table
+};
+
+/// Position along the a1-h8 diagonal (0..8) or the h1-a8 diagonal (8..16).
+const DIAG: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut rank = 0;
+    while rank < 8 {
+        table[rank * 8 + rank] = rank;
+        table[rank * 8 + 7 - rank] = 8 + rank;
+        rank += 1;
+    }
+    table
+};
+
+/// Index of a leading pawn within the queenside half of the board, by file first.
+const FLAP: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 8;
+    while sq < 56 {
+        let file = sq & 7;
+        let file = if file < 4 { file } else { 7 - file };
+        table[sq] = file * 6 + (sq >> 3) - 1;
+        sq += 1;
+    }
+    table
+};
+
+/// Orders pawn squares from the center files on the seventh rank outwards.
+const PAWN_TWIST: [usize; 64] = {
+    let mut table = [0; 64];
+    let mut sq = 8;
+    while sq < 56 {
+        let file = sq & 7;
+        let pair = if file < 4 { file } else { 7 - file };
+        let from_seventh = 6 - (sq >> 3);
+        table[sq] = (3 - pair) * 12 + 2 * from_seventh + (file < 4) as usize;
+        sq += 1;
+    }
+    table
+};
+
+/// `BINOMIAL[k][n]` is n choose k.
+const BINOMIAL: [[u64; 64]; MAX_PIECES] = {
+    let mut table = [[0; 64]; MAX_PIECES];
+    let mut k = 0;
+    while k < MAX_PIECES {
+        let mut n = k;
+        while n < 64 {
+            let mut f = 1;
+            let mut l = 1;
+            let mut i = 0;
+            while i < k {
+                f *= (n - i) as u64;
+                l *= (i + 1) as u64;
+                i += 1;
+            }
+            table[k][n] = f / l;
+            n += 1;
+        }
+        k += 1;
+    }
+    table
+};
+
+/// Index offsets of the leading pawns by their count and the `FLAP` of the first one,
+/// plus the number of indices per file.
+const PAWN_IDX: ([[u64; 24]; 6], [[u64; 4]; 6]) = {
+    let mut idx = [[0; 24]; 6];
+    let mut factor = [[0; 4]; 6];
+    let mut i = 0;
+    while i < 6 {
+        let mut s = 0;
+        let mut j = 0;
+        while j < 24 {
+            idx[i][j] = s;
+            s += BINOMIAL[i][PAWN_TWIST[(1 + j % 6) * 8 + j / 6]];
+            if (j + 1) % 6 == 0 {
+                factor[i][j / 6] = s;
+                s = 0;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    (idx, factor)
+};
+
+fn subfactor(k: usize, n: usize) -> u64 {
+    BINOMIAL[k][n]
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Huffman coded symbols of one sub table, decoded with the re-pair grammar stored in
+/// the symbol patterns.
+#[derive(Debug)]
+struct Huffman {
+    block_size: u32,
+    idx_bits: u32,
+    min_len: usize,
+    /// Offsets of the first symbol per code length.
+    offsets: usize,
+    base: Vec<u64>,
+    sym_len: Vec<u8>,
+    sym_pat: usize,
+    index_table: usize,
+    size_table: usize,
+    blocks: usize,
+}
+
+#[derive(Debug)]
+enum Pairs {
+    Const(u8),
+    Huffman(Huffman),
+}
+
+impl Pairs {
+    /// Reads the header of a sub table and returns it together with the sizes of its
+    /// index table, size table and data blocks, plus the flags byte.
+    fn read(
+        data: &[u8],
+        pos: &mut usize,
+        tb_size: u64,
+        kind: TableKind,
+    ) -> io::Result<(Pairs, [usize; 3], u8)> {
+        let header = data
+            .get(*pos..*pos + 12)
+            .ok_or_else(|| invalid("truncated table"))?;
+        let flags = header[0];
+        if flags & 0x80 != 0 {
+            *pos += 2;
+            let value = if kind == TableKind::Wdl { header[1] } else { 0 };
+            return Ok((Pairs::Const(value), [0; 3], flags));
+        }
+
+        let block_size = header[1] as u32;
+        let idx_bits = header[2] as u32;
+        let real_num_blocks = read_u32(data, *pos + 4).unwrap() as usize;
+        let num_blocks = real_num_blocks + header[3] as usize;
+        let (max_len, min_len) = (header[8] as usize, header[9] as usize);
+        if min_len == 0 || max_len < min_len || max_len > 64 || idx_bits == 0 || idx_bits > 63 {
+            return Err(invalid("invalid huffman code"));
+        }
+        let h = max_len - min_len + 1;
+        let offsets = *pos + 10;
+        let num_syms =
+            read_u16(data, offsets + 2 * h).ok_or_else(|| invalid("truncated table"))? as usize;
+        let sym_pat = offsets + 2 * h + 2;
+        *pos = sym_pat + 3 * num_syms + (num_syms & 1);
+        if *pos > data.len() {
+            return Err(invalid("truncated table"));
+        }
+
+        let mut sym_len = vec![0; num_syms];
+        let mut done = vec![false; num_syms];
+        for sym in 0..num_syms {
+            if !done[sym] {
+                calc_sym_len(data, sym_pat, sym, &mut sym_len, &mut done)?;
+            }
+        }
+
+        let offset = |i: usize| read_u16(data, offsets + 2 * i).unwrap() as u64;
+        let mut base = vec![0u64; h];
+        for i in (0..h - 1).rev() {
+            base[i] = base[i + 1]
+                .wrapping_add(offset(i))
+                .wrapping_sub(offset(i + 1))
+                / 2;
+        }
+        for (i, base) in base.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - (min_len + i) as u32).unwrap_or(0);
+        }
+
+        let num_indices = (tb_size + (1 << idx_bits) - 1) >> idx_bits;
+        let sizes = [
+            6 * num_indices as usize,
+            2 * num_blocks,
+            real_num_blocks << block_size,
+        ];
+        let huffman = Huffman {
+            block_size,
+            idx_bits,
+            min_len,
+            offsets,
+            base,
+            sym_len,
+            sym_pat,
+            index_table: 0,
+            size_table: 0,
+            blocks: 0,
+        };
+        Ok((Pairs::Huffman(huffman), sizes, flags))
+    }
+
+    /// The symbol stored at `idx`, the value is in its first byte and a half.
+    fn decompress(&self, data: &[u8], idx: u64) -> Option<(u8, u8)> {
+        let huffman = match self {
+            Pairs::Const(value) => return Some((*value, 0)),
+            Pairs::Huffman(huffman) => huffman,
+        };
+
+        let main_idx = (idx >> huffman.idx_bits) as usize;
+        let mut lit_idx =
+            (idx & ((1 << huffman.idx_bits) - 1)) as i64 - (1i64 << (huffman.idx_bits - 1));
+        let entry = huffman.index_table + 6 * main_idx;
+        let mut block = read_u32(data, entry)? as usize;
+        lit_idx += read_u16(data, entry + 4)? as i64;
+
+        let block_len =
+            |block: usize| read_u16(data, huffman.size_table + 2 * block).map(|len| len as i64);
+        if lit_idx < 0 {
+            while lit_idx < 0 {
+                block = block.checked_sub(1)?;
+                lit_idx += block_len(block)? + 1;
+            }
+        } else {
+            loop {
+                let len = block_len(block)?;
+                if lit_idx <= len {
+                    break;
+                }
+                lit_idx -= len + 1;
+                block += 1;
+            }
+        }
+
+        let mut ptr = huffman.blocks + (block << huffman.block_size);
+        let mut code = u64::from_be_bytes(data.get(ptr..ptr + 8)?.try_into().ok()?);
+        ptr += 8;
+        let mut bit_cnt = 0;
+
+        let mut sym = loop {
+            let mut l = huffman.min_len;
+            while code < *huffman.base.get(l - huffman.min_len)? {
+                l += 1;
+            }
+            let first = read_u16(data, huffman.offsets + 2 * (l - huffman.min_len))? as usize;
+            let sym = first + ((code - huffman.base[l - huffman.min_len]) >> (64 - l)) as usize;
+            let len = *huffman.sym_len.get(sym)? as i64;
+            if lit_idx < len + 1 {
+                break sym;
+            }
+            lit_idx -= len + 1;
+            code = code.checked_shl(l as u32).unwrap_or(0);
+            bit_cnt += l;
+            if bit_cnt >= 32 {
+                bit_cnt -= 32;
+                let bits = u32::from_be_bytes(data.get(ptr..ptr + 4)?.try_into().ok()?);
+                ptr += 4;
+                code |= (bits as u64) << bit_cnt;
+            }
+        };
+
+        while *huffman.sym_len.get(sym)? != 0 {
+            let w = data.get(huffman.sym_pat + 3 * sym..huffman.sym_pat + 3 * sym + 3)?;
+            let left = ((w[1] as usize & 0x0f) << 8) | w[0] as usize;
+            let left_len = *huffman.sym_len.get(left)? as i64;
+            if lit_idx < left_len + 1 {
+                sym = left;
+            } else {
+                lit_idx -= left_len + 1;
+                sym = ((w[2] as usize) << 4) | (w[1] as usize >> 4);
+            }
+        }
+        let w = data.get(huffman.sym_pat + 3 * sym..huffman.sym_pat + 3 * sym + 2)?;
+        Some((w[0], w[1]))
+    }
+
+    fn set_offset(&mut self, section: usize, offset: usize) {
+        if let Pairs::Huffman(huffman) = self {
+            match section {
+                0 => huffman.index_table = offset,
+                1 => huffman.size_table = offset,
+                _ => huffman.blocks = offset,
+            }
+        }
+    }
+}
+
+/// Number of plain symbols a symbol expands to, minus one.
+fn calc_sym_len(
+    data: &[u8],
+    sym_pat: usize,
+    sym: usize,
+    sym_len: &mut [u8],
+    done: &mut [bool],
+) -> io::Result<()> {
+    let w = &data[sym_pat + 3 * sym..sym_pat + 3 * sym + 3];
+    let right = ((w[2] as usize) << 4) | (w[1] as usize >> 4);
+    if right == 0x0fff {
+        sym_len[sym] = 0;
+    } else {
+        let left = ((w[1] as usize & 0x0f) << 8) | w[0] as usize;
+        if left >= sym_len.len() || right >= sym_len.len() {
+            return Err(invalid("invalid symbol"));
+        }
+        for child in [left, right] {
+            if !done[child] {
+                calc_sym_len(data, sym_pat, child, sym_len, done)?;
+            }
+        }
+        sym_len[sym] = sym_len[left].wrapping_add(sym_len[right]).wrapping_add(1);
+    }
+    done[sym] = true;
+    Ok(())
+}
+
+/// How the pieces of a sub table are turned into an index.
+#[derive(Debug)]
+struct EncInfo {
+    pieces: [u8; MAX_PIECES],
+    norm: [usize; MAX_PIECES],
+    factor: [u64; MAX_PIECES],
+    pairs: Pairs,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct DtzMap {
+    flags: u8,
+    /// Start of the value map per WDL outcome.
+    maps: [usize; 4],
+}
+
+/// Result of a table lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Value(i32),
+    /// DTZ tables only store one side to move.
+    OtherSide,
+}
+
+/// A single WDL or DTZ file, read into memory.
+#[derive(Debug)]
+pub struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    num: usize,
+    symmetric: bool,
+    has_pawns: bool,
+    /// Two kings are the only unique pieces.
+    kk_enc: bool,
+    /// Counts of the leading pawns and of the other color's pawns.
+    pawns: [usize; 2],
+    /// Sub tables, indexed by `side * files + file`.
+    infos: Vec<EncInfo>,
+    dtz_maps: Vec<DtzMap>,
+}
+
+impl Table {
+    pub fn read(path: &Path, kind: TableKind, material: &Material) -> io::Result<Table> {
+        let data = fs::read(path)?;
+        if data.get(..4) != Some(&kind.magic()[..]) || data.len() < 6 {
+            return Err(invalid("not a syzygy table"));
+        }
+
+        let counts = material.counts;
+        let has_pawns = material.has_pawns();
+        let mut pawns = [counts[0][PAWN] as usize, counts[1][PAWN] as usize];
+        if pawns[1] > 0 && (pawns[0] == 0 || pawns[0] > pawns[1]) {
+            pawns.swap(0, 1);
+        }
+        let unique = counts.iter().flatten().filter(|count| **count == 1).count();
+
+        let mut table = Table {
+            data: vec![],
+            kind,
+            num: material.pieces(),
+            symmetric: material.mirrored() == *material,
+            has_pawns,
+            kk_enc: !has_pawns && unique == 2,
+            pawns,
+            infos: vec![],
+            dtz_maps: vec![],
+        };
+        if table.num > MAX_PIECES || table.num < 3 {
+            return Err(invalid("unsupported number of pieces"));
+        }
+
+        let split = kind == TableKind::Wdl && data[4] & 0x01 != 0;
+        let sides = if split { 2 } else { 1 };
+        let files = if has_pawns { 4 } else { 1 };
+        let more_pawns = (has_pawns && pawns[1] > 0) as usize;
+
+        let mut pos = 5;
+        let mut sub_tables = vec![];
+        for file in 0..files {
+            let header = data
+                .get(pos..pos + table.num + 1 + more_pawns)
+                .ok_or_else(|| invalid("truncated table"))?;
+            for side in 0..sides {
+                sub_tables.push((side, file, table.enc_info(header, side as u32 * 4, file)));
+            }
+            pos += table.num + 1 + more_pawns;
+        }
+        pos += pos & 1;
+
+        let mut infos = vec![];
+        for (side, file, (pieces, norm, factor, tb_size)) in sub_tables {
+            let (pairs, sizes, flags) = Pairs::read(&data, &mut pos, tb_size, kind)?;
+            if kind == TableKind::Dtz {
+                table.dtz_maps.push(DtzMap {
+                    flags,
+                    maps: [0; 4],
+                });
+            }
+            let info = EncInfo {
+                pieces,
+                norm,
+                factor,
+                pairs,
+            };
+            infos.push((side, file, info, sizes));
+        }
+
+        if kind == TableKind::Dtz {
+            for map in &mut table.dtz_maps {
+                if map.flags & 2 == 0 {
+                    continue;
+                }
+                if map.flags & 16 == 0 {
+                    for start in &mut map.maps {
+                        *start = pos + 1;
+                        pos +=
+                            1 + *data.get(pos).ok_or_else(|| invalid("truncated table"))? as usize;
+                    }
+                } else {
+                    pos += pos & 1;
+                    for start in &mut map.maps {
+                        *start = pos + 2;
+                        let len = read_u16(&data, pos).ok_or_else(|| invalid("truncated table"))?;
+                        pos += 2 + 2 * len as usize;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for section in 0..3 {
+            for (_, _, info, sizes) in &mut infos {
+                if section == 2 {
+                    // data blocks are aligned to 64 bytes
+                    pos = (pos + 0x3f) & !0x3f;
+                }
+                info.pairs.set_offset(section, pos);
+                pos += sizes[section];
+            }
+        }
+        if pos > data.len() {
+            return Err(invalid("truncated table"));
+        }
+
+        infos.sort_by_key(|(side, file, _, _)| side * files + file);
+        table.infos = infos.into_iter().map(|(_, _, info, _)| info).collect();
+        table.data = data;
+        Ok(table)
+    }
+
+    /// Reads the piece order and computes the index factors of a sub table, returns them
+    /// together with the number of indices.
+    #[allow(clippy::type_complexity)]
+    fn enc_info(
+        &self,
+        header: &[u8],
+        shift: u32,
+        file: usize,
+    ) -> (
+        [u8; MAX_PIECES],
+        [usize; MAX_PIECES],
+        [u64; MAX_PIECES],
+        u64,
+    ) {
+        let more_pawns = (self.has_pawns && self.pawns[1] > 0) as usize;
+        let mut pieces = [0; MAX_PIECES];
+        let mut norm = [0; MAX_PIECES];
+        let mut factor = [0; MAX_PIECES];
+
+        for (i, piece) in pieces.iter_mut().enumerate().take(self.num) {
+            *piece = (header[i + 1 + more_pawns] >> shift) & 0x0f;
+        }
+        let order = ((header[0] >> shift) & 0x0f) as usize;
+        let order2 = if more_pawns == 1 {
+            ((header[1] >> shift) & 0x0f) as usize
+        } else {
+            0x0f
+        };
+
+        let mut k = if self.has_pawns {
+            self.pawns[0]
+        } else if self.kk_enc {
+            2
+        } else {
+            3
+        };
+        norm[0] = k;
+        if more_pawns == 1 {
+            norm[k] = self.pawns[1];
+            k += norm[k];
+        }
+        let mut i = k;
+        while i < self.num {
+            let mut j = i;
+            while j < self.num && pieces[j] == pieces[i] {
+                norm[i] += 1;
+                j += 1;
+            }
+            i += norm[i];
+        }
+
+        let mut n = 64 - k;
+        let mut f = 1;
+        let mut i = 0;
+        while k < self.num || i == order || i == order2 {
+            if i == order {
+                factor[0] = f;
+                f *= if self.has_pawns {
+                    PAWN_IDX.1[norm[0] - 1][file]
+                } else if self.kk_enc {
+                    462
+                } else {
+                    31332
+                };
+            } else if i == order2 {
+                factor[norm[0]] = f;
+                f *= subfactor(norm[norm[0]], 48 - norm[0]);
+            } else {
+                factor[k] = f;
+                f *= subfactor(norm[k], n);
+                n -= norm[k];
+                k += norm[k];
+            }
+            i += 1;
+        }
+        (pieces, norm, factor, f)
+    }
+
+    /// Looks up `pos` with the white pieces of the table played by black if `flip` is
+    /// set. `wdl` selects the value map of DTZ tables.
+    pub fn probe(&self, pos: &TbPosition, flip: bool, wdl: i32) -> Option<Lookup> {
+        let (flip, black_side) = if self.symmetric {
+            (!pos.white_to_move, false)
+        } else {
+            (flip, pos.white_to_move == flip)
+        };
+        let dtz_side_matches = |map: &DtzMap| self.symmetric || (map.flags & 1 != 0) == black_side;
+
+        let mut p = [0; MAX_PIECES];
+        let squares = &mut p[..self.num];
+        let (info, idx, dtz_map) = if !self.has_pawns {
+            let dtz_map = self.dtz_maps.first().copied();
+            if dtz_map.is_some_and(|map| !dtz_side_matches(&map)) {
+                return Some(Lookup::OtherSide);
+            }
+            let side = (self.kind == TableKind::Wdl && black_side) as usize;
+            let info = self.infos.get(side)?;
+            let mut i = 0;
+            while i < self.num {
+                i = fill_squares(pos, &info.pieces, flip, 0, squares, i)?;
+            }
+            (info, self.encode_piece(squares, info), dtz_map)
+        } else {
+            let mirror = if flip { 0x38 } else { 0 };
+            let mut i = fill_squares(pos, &self.infos[0].pieces, flip, mirror, squares, 0)?;
+            let file = self.leading_pawn(squares);
+            let dtz_map = self.dtz_maps.get(file).copied();
+            if dtz_map.is_some_and(|map| !dtz_side_matches(&map)) {
+                return Some(Lookup::OtherSide);
+            }
+            let side = (self.kind == TableKind::Wdl && black_side) as usize;
+            let info = self.infos.get(side * 4 + file)?;
+            while i < self.num {
+                i = fill_squares(pos, &info.pieces, flip, mirror, squares, i)?;
+            }
+            (info, self.encode_pawn(squares, info), dtz_map)
+        };
+
+        let (low, high) = info.pairs.decompress(&self.data, idx)?;
+        let Some(map) = dtz_map else {
+            return Some(Lookup::Value(low as i32 - 2));
+        };
+
+        const WDL_TO_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        const PLY_FLAGS: [u8; 5] = [8, 0, 0, 0, 4];
+
+        let mut value = low as usize + ((high as usize & 0x0f) << 8);
+        let outcome = (wdl + 2) as usize;
+        if map.flags & 2 != 0 {
+            let start = map.maps[WDL_TO_MAP[outcome]];
+            value = if map.flags & 16 == 0 {
+                *self.data.get(start + value)? as usize
+            } else {
+                read_u16(&self.data, start + 2 * value)? as usize
+            };
+        }
+        // values in moves instead of plies
+        if map.flags & PLY_FLAGS[outcome] == 0 || wdl & 1 != 0 {
+            value *= 2;
+        }
+        Some(Lookup::Value(value as i32))
+    }
+
+    /// Moves the leading pawn with the lowest `FLAP` to the front and returns its file.
+    fn leading_pawn(&self, p: &mut [usize]) -> usize {
+        for i in 1..self.pawns[0] {
+            if FLAP[p[0]] > FLAP[p[i]] {
+                p.swap(0, i);
+            }
+        }
+        FILE_TO_FILE[p[0] & 7]
+    }
+
+    fn encode_piece(&self, p: &mut [usize], info: &EncInfo) -> u64 {
+        if p[0] & 0x04 != 0 {
+            p.iter_mut().for_each(|sq| *sq ^= 0x07);
+        }
+        if p[0] & 0x20 != 0 {
+            p.iter_mut().for_each(|sq| *sq ^= 0x38);
+        }
+        let leading = if self.kk_enc { 2 } else { 3 };
+        for i in 0..p.len() {
+            let diagonal = off_diag(p[i]);
+            if diagonal != 0 {
+                if diagonal > 0 && i < leading {
+                    p.iter_mut().for_each(|sq| *sq = flip_diag(*sq));
+                }
+                break;
+            }
+        }
+
+        let idx = if self.kk_enc {
+            KK_IDX[TRIANGLE[p[0]]][p[1]].max(0) as usize
+        } else {
+            let s1 = (p[1] > p[0]) as usize;
+            let s2 = (p[2] > p[0]) as usize + (p[2] > p[1]) as usize;
+            if off_diag(p[0]) != 0 {
+                TRIANGLE[p[0]] * 63 * 62 + (p[1] - s1) * 62 + (p[2] - s2)
+            } else if off_diag(p[1]) != 0 {
+                6 * 63 * 62 + DIAG[p[0]] * 28 * 62 + LOWER[p[1]] * 62 + p[2] - s2
+            } else if off_diag(p[2]) != 0 {
+                6 * 63 * 62
+                    + 4 * 28 * 62
+                    + DIAG[p[0]] * 7 * 28
+                    + (DIAG[p[1]] - s1) * 28
+                    + LOWER[p[2]]
+            } else {
+                6 * 63 * 62
+                    + 4 * 28 * 62
+                    + 4 * 7 * 28
+                    + DIAG[p[0]] * 7 * 6
+                    + (DIAG[p[1]] - s1) * 6
+                    + (DIAG[p[2]] - s2)
+            }
+        };
+        idx as u64 * info.factor[0] + encode_groups(p, info, leading, 0)
+    }
+
+    fn encode_pawn(&self, p: &mut [usize], info: &EncInfo) -> u64 {
+        if p[0] & 0x04 != 0 {
+            p.iter_mut().for_each(|sq| *sq ^= 0x07);
+        }
+
+        let k = self.pawns[0];
+        p[1..k].sort_unstable_by_key(|sq| std::cmp::Reverse(PAWN_TWIST[*sq]));
+        let mut idx = PAWN_IDX.0[k - 1][FLAP[p[0]]];
+        for i in 1..k {
+            idx += BINOMIAL[k - i][PAWN_TWIST[p[i]]];
+        }
+        idx *= info.factor[0];
+
+        let mut k = k;
+        if self.pawns[1] > 0 {
+            idx += encode_group(p, k, k + self.pawns[1], 8) * info.factor[k];
+            k += self.pawns[1];
+        }
+        idx + encode_groups(p, info, k, 0)
+    }
+}
+
+/// Adds the squares of all pieces like `pieces[i]` to `p`, returns the next index.
+fn fill_squares(
+    pos: &TbPosition,
+    pieces: &[u8],
+    flip: bool,
+    mirror: usize,
+    p: &mut [usize],
+    mut i: usize,
+) -> Option<usize> {
+    let piece = *pieces.get(i)? as usize;
+    let color = (piece >> 3) ^ flip as usize;
+    let mut board = *pos.boards.get(color)?.get(piece & 7)?;
+    if board == 0 {
+        return None;
+    }
+    while board != 0 {
+        *p.get_mut(i)? = board.trailing_zeros() as usize ^ mirror;
+        board &= board - 1;
+        i += 1;
+    }
+    Some(i)
+}
+
+/// Index of the pieces from `k` on, each group of equal pieces is a combination of the
+/// squares left free by the pieces before it.
+fn encode_groups(p: &mut [usize], info: &EncInfo, mut k: usize, offset: usize) -> u64 {
+    let mut idx = 0;
+    while k < p.len() && info.norm[k] > 0 {
+        let end = k + info.norm[k];
+        idx += encode_group(p, k, end, offset) * info.factor[k];
+        k = end;
+    }
+    idx
+}
+
+fn encode_group(p: &mut [usize], k: usize, end: usize, offset: usize) -> u64 {
+    p[k..end].sort_unstable();
+    (k..end)
+        .map(|i| {
+            let skips = p[..k].iter().filter(|sq| p[i] > **sq).count();
+            BINOMIAL[i - k + 1][p[i] - skips - offset]
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::{
+        EncInfo, Material, Pairs, Table, TableKind, DIAG, FLAP, KK_IDX, LOWER, PAWN_IDX, PAWN_TWIST,
+    };
+
+    /// A table without data, enough to compute indices.
+    fn empty_table(name: &str) -> Table {
+        let material = Material::from_name(name).unwrap();
+        Table {
+            data: vec![],
+            kind: TableKind::Wdl,
+            num: material.pieces(),
+            symmetric: false,
+            has_pawns: material.has_pawns(),
+            kk_enc: false,
+            pawns: [1, 0],
+            infos: vec![],
+            dtz_maps: vec![],
+        }
+    }
+
+    fn enc_info(table: &Table, header: &[u8], file: usize) -> (EncInfo, u64) {
+        let (pieces, norm, factor, size) = table.enc_info(header, 0, file);
+        let pairs = Pairs::Const(0);
+        let info = EncInfo {
+            pieces,
+            norm,
+            factor,
+            pairs,
+        };
+        (info, size)
+    }
+
+    /// Every placement of three pieces gets an index, which it only shares with its
+    /// mirror images.
+    fn assert_bijective(table: &Table, header: &[u8], symmetries: usize) {
+        let infos = (0..4)
+            .map(|file| enc_info(table, header, file))
+            .collect::<Vec<_>>();
+        let mirror = |sq: usize, symmetry: usize| {
+            let (mut rank, mut file) = (sq >> 3, sq & 7);
+            if symmetry & 1 != 0 {
+                file = 7 - file;
+            }
+            if symmetry & 2 != 0 {
+                rank = 7 - rank;
+            }
+            if symmetry & 4 != 0 {
+                std::mem::swap(&mut rank, &mut file);
+            }
+            rank * 8 + file
+        };
+
+        let mut seen = HashMap::new();
+        for a in 0..64 {
+            for b in 0..64 {
+                for c in 0..64 {
+                    let squares = [a, b, c];
+                    if a == b || a == c || b == c || table.has_pawns && !(8..56).contains(&a) {
+                        continue;
+                    }
+                    let canonical = (0..symmetries)
+                        .map(|symmetry| squares.map(|sq| mirror(sq, symmetry)))
+                        .min();
+
+                    let mut p = squares;
+                    let (file, idx) = if table.has_pawns {
+                        let file = table.leading_pawn(&mut p);
+                        (file, table.encode_pawn(&mut p, &infos[file].0))
+                    } else {
+                        (0, table.encode_piece(&mut p, &infos[0].0))
+                    };
+                    assert!(idx < infos[file].1);
+                    assert_eq!(*seen.entry((file, idx)).or_insert(canonical), canonical);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_piece_encoding() {
+        // white king, white queen, black king
+        assert_bijective(&empty_table("KQvK"), &[0x00, 6, 5, 14], 8);
+    }
+
+    #[test]
+    fn test_pawn_encoding() {
+        // white pawn, white king, black king
+        assert_bijective(&empty_table("KPvK"), &[0x01, 1, 6, 14], 2);
+    }
+
+    #[test]
+    fn test_material_names() {
+        let material = Material::from_name("KRPvKR").unwrap();
+        assert_eq!(material.pieces(), 5);
+        assert!(material.has_pawns());
+        assert_eq!(material.mirrored().name(), "KRvKRP");
+        assert!(Material::from_name("KPRvKR").is_none());
+        assert!(Material::from_name("KQvQ").is_none());
+    }
+
+    #[test]
+    fn test_king_indices() {
+        let indices = KK_IDX.iter().flatten().filter(|idx| **idx >= 0);
+        assert_eq!(indices.clone().count(), 462);
+        assert_eq!(indices.max(), Some(&461));
+        // king on b1, the other one on a1 or d1
+        assert_eq!(KK_IDX[0][0], -1);
+        assert_eq!(KK_IDX[0][3], 0);
+        // king on a1, the other one on h7 or c3
+        assert_eq!(KK_IDX[6][55], 365);
+        assert_eq!(KK_IDX[6][18], 441);
+    }
+
+    #[test]
+    fn test_square_tables() {
+        assert_eq!(LOWER[1], 0);
+        assert_eq!(LOWER[8], 0);
+        assert_eq!(LOWER[55], 27);
+        assert_eq!(LOWER[63], 35);
+        assert_eq!(DIAG[7], 8);
+        assert_eq!(DIAG[56], 15);
+        assert_eq!(FLAP[8], 0);
+        assert_eq!(FLAP[27], 20);
+        assert_eq!(PAWN_TWIST[8], 47);
+        assert_eq!(PAWN_TWIST[52], 0);
+
+        // pawn twists number the 48 pawn squares
+        let twists = (8..56).map(|sq| PAWN_TWIST[sq]).collect::<HashSet<_>>();
+        assert_eq!(twists.len(), 48);
+        assert!(twists.iter().all(|twist| *twist < 48));
+
+        // a single leading pawn has six squares per file
+        assert_eq!(PAWN_IDX.1[0], [6; 4]);
+        // a second one of the same color has a lower twist
+        assert_eq!(PAWN_IDX.1[1], [252, 180, 108, 36]);
+    }
+}
@@ -0,0 +1,617 @@
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    io,
+};
+
+use crate::{
+    match_runner::{play_game, EngineConfig, GameSettings, Opening, Termination, UciProcess},
+    pgn::{Game, GameResult},
+    Team,
+};
+
+/// Points for a bye in a Swiss tournament. Round-robin byes only mean sitting out.
+const SWISS_BYE_POINTS: f64 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    RoundRobin,
+    /// Everyone meets everyone twice, once with each color.
+    DoubleRoundRobin,
+    Swiss {
+        rounds: usize,
+    },
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::RoundRobin => write!(f, "round-robin"),
+            Format::DoubleRoundRobin => write!(f, "double round-robin"),
+            Format::Swiss { rounds } => write!(f, "Swiss, {rounds} rounds"),
+        }
+    }
+}
+
+/// A game of a round, players are indices into the player list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pairing {
+    pub white: usize,
+    pub black: usize,
+    /// `Unknown` until the game is played.
+    pub result: GameResult,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Round {
+    pub pairings: Vec<Pairing>,
+    /// The player without an opponent if the number of players is odd.
+    pub bye: Option<usize>,
+}
+
+impl Round {
+    pub fn is_finished(&self) -> bool {
+        self.pairings
+            .iter()
+            .all(|pairing| pairing.result != GameResult::Unknown)
+    }
+}
+
+/// A line of the standings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub points: f64,
+    pub games: usize,
+    pub wins: usize,
+    /// Points of the beaten opponents plus half the points of the drawn ones.
+    pub sonneborn_berger: f64,
+    /// Sum of the points of all opponents.
+    pub buchholz: f64,
+}
+
+/// Pairs `players`, ordered from the top, so that nobody meets an opponent from
+/// `played` again. As in the Monrad system the highest player takes the highest
+/// possible opponent.
+fn pair_swiss(players: &[usize], played: &HashSet<(usize, usize)>) -> Option<Vec<(usize, usize)>> {
+    let Some((&first, rest)) = players.split_first() else {
+        return Some(vec![]);
+    };
+    for (idx, &opponent) in rest.iter().enumerate() {
+        if played.contains(&(first, opponent)) {
+            continue;
+        }
+        let mut others = rest.to_vec();
+        others.remove(idx);
+        if let Some(mut pairs) = pair_swiss(&others, played) {
+            pairs.insert(0, (first, opponent));
+            return Some(pairs);
+        }
+    }
+    None
+}
+
+/// A tournament between engines or humans. Rounds are paired one at a time, the next
+/// round once all results of the current one are in.
+#[derive(Debug, Clone)]
+pub struct Tournament {
+    pub name: String,
+    pub players: Vec<String>,
+    pub format: Format,
+    rounds: Vec<Round>,
+}
+
+impl Tournament {
+    pub fn new(name: impl Into<String>, players: Vec<String>, format: Format) -> Self {
+        Tournament {
+            name: name.into(),
+            players,
+            format,
+            rounds: vec![],
+        }
+    }
+
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+
+    /// Number of rounds once the tournament is complete, none with fewer than two
+    /// players.
+    pub fn round_count(&self) -> usize {
+        if self.players.len() < 2 {
+            return 0;
+        }
+        let single = self.players.len() - 1 + self.players.len() % 2;
+        match self.format {
+            Format::RoundRobin => single,
+            Format::DoubleRoundRobin => 2 * single,
+            Format::Swiss { rounds } => rounds,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.rounds.len() == self.round_count() && self.rounds.iter().all(Round::is_finished)
+    }
+
+    /// Enters the result of the game on `board` of `round`, both counted from 0.
+    pub fn set_result(&mut self, round: usize, board: usize, result: GameResult) {
+        self.rounds[round].pairings[board].result = result;
+    }
+
+    /// Pairs the next round. `None` while the current round is unfinished or once all
+    /// rounds are paired.
+    pub fn pair_next_round(&mut self) -> Option<&Round> {
+        if self.rounds.len() >= self.round_count() || !self.rounds.iter().all(Round::is_finished) {
+            return None;
+        }
+        let round = match self.format {
+            Format::RoundRobin | Format::DoubleRoundRobin => self.berger_round(self.rounds.len()),
+            Format::Swiss { .. } => self.swiss_round(),
+        };
+        self.rounds.push(round);
+        self.rounds.last()
+    }
+
+    /// Round `number` of the Berger tables: one player stays in place while the others
+    /// rotate around them. The second cycle of a double round-robin swaps colors.
+    fn berger_round(&self, number: usize) -> Round {
+        let count = self.players.len() + self.players.len() % 2;
+        let Some(cycle) = count.checked_sub(1) else {
+            return Round::default();
+        };
+        let (number, swap) = (number % cycle, number >= cycle);
+        let ring = |idx: usize| idx % cycle;
+
+        let mut pairs = vec![if number.is_multiple_of(2) {
+            (ring(number), cycle)
+        } else {
+            (cycle, ring(number))
+        }];
+        for offset in 1..count / 2 {
+            pairs.push((ring(number + offset), ring(number + cycle - offset)));
+        }
+
+        let mut round = Round::default();
+        for (white, black) in pairs {
+            // the extra player of an odd field stands for the bye
+            if black == self.players.len() {
+                round.bye = Some(white);
+            } else if white == self.players.len() {
+                round.bye = Some(black);
+            } else {
+                let (white, black) = if swap { (black, white) } else { (white, black) };
+                round.pairings.push(Pairing {
+                    white,
+                    black,
+                    result: GameResult::Unknown,
+                });
+            }
+        }
+        round
+    }
+
+    /// Players with equal points meet first, nobody meets the same opponent twice as
+    /// long as that is possible. The lowest player without a bye sits out.
+    fn swiss_round(&self) -> Round {
+        let mut ranking = self
+            .standings()
+            .into_iter()
+            .map(|standing| standing.player)
+            .collect::<Vec<_>>();
+        let mut round = Round::default();
+        if ranking.len() % 2 == 1 {
+            let had_bye =
+                |player: &usize| self.rounds.iter().any(|round| round.bye == Some(*player));
+            let idx = ranking
+                .iter()
+                .rposition(|player| !had_bye(player))
+                .unwrap_or(ranking.len() - 1);
+            round.bye = Some(ranking.remove(idx));
+        }
+
+        let played = self
+            .rounds
+            .iter()
+            .flat_map(|round| &round.pairings)
+            .flat_map(|pairing| {
+                [
+                    (pairing.white, pairing.black),
+                    (pairing.black, pairing.white),
+                ]
+            })
+            .collect::<HashSet<_>>();
+        let pairs = pair_swiss(&ranking, &played)
+            .or_else(|| pair_swiss(&ranking, &HashSet::new()))
+            .expect("an even number of players can always be paired");
+
+        for (upper, lower) in pairs {
+            let balance = |player: usize| {
+                self.rounds
+                    .iter()
+                    .flat_map(|round| &round.pairings)
+                    .map(|pairing| match player {
+                        _ if pairing.white == player => 1,
+                        _ if pairing.black == player => -1,
+                        _ => 0,
+                    })
+                    .sum::<i32>()
+            };
+            let upper_white = match balance(upper).cmp(&balance(lower)) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => self.rounds.len().is_multiple_of(2),
+            };
+            let (white, black) = if upper_white {
+                (upper, lower)
+            } else {
+                (lower, upper)
+            };
+            round.pairings.push(Pairing {
+                white,
+                black,
+                result: GameResult::Unknown,
+            });
+        }
+        round
+    }
+
+    /// Finished games of `player` as (opponent, points) pairs.
+    fn games_of(&self, player: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.rounds
+            .iter()
+            .flat_map(|round| &round.pairings)
+            .filter_map(move |pairing| {
+                let (opponent, team) = if pairing.white == player {
+                    (pairing.black, Team::White)
+                } else if pairing.black == player {
+                    (pairing.white, Team::Black)
+                } else {
+                    return None;
+                };
+                Some((opponent, pairing.result.score(team)?))
+            })
+    }
+
+    fn points(&self, player: usize) -> f64 {
+        let byes = match self.format {
+            Format::Swiss { .. } => {
+                self.rounds
+                    .iter()
+                    .filter(|round| round.bye == Some(player))
+                    .count() as f64
+                    * SWISS_BYE_POINTS
+            }
+            _ => 0.,
+        };
+        self.games_of(player).map(|(_, points)| points).sum::<f64>() + byes
+    }
+
+    /// Players ordered by points, then by tie-breaks: Sonneborn-Berger first in
+    /// round-robins, Buchholz first in Swiss tournaments. Ties left keep the seeding.
+    pub fn standings(&self) -> Vec<Standing> {
+        let points = (0..self.players.len())
+            .map(|player| self.points(player))
+            .collect::<Vec<_>>();
+        let mut standings = (0..self.players.len())
+            .map(|player| {
+                let games = self.games_of(player).collect::<Vec<_>>();
+                Standing {
+                    player,
+                    points: points[player],
+                    games: games.len(),
+                    wins: games.iter().filter(|(_, score)| *score == 1.).count(),
+                    sonneborn_berger: games
+                        .iter()
+                        .map(|(opponent, score)| score * points[*opponent])
+                        .sum(),
+                    buchholz: games.iter().map(|(opponent, _)| points[*opponent]).sum(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let swiss = matches!(self.format, Format::Swiss { .. });
+        standings.sort_by(|a, b| {
+            let tie_breaks = |standing: &Standing| {
+                if swiss {
+                    [standing.buchholz, standing.sonneborn_berger]
+                } else {
+                    [standing.sonneborn_berger, standing.buchholz]
+                }
+            };
+            b.points
+                .total_cmp(&a.points)
+                .then_with(|| {
+                    let (a, b) = (tie_breaks(a), tie_breaks(b));
+                    b[0].total_cmp(&a[0]).then(b[1].total_cmp(&a[1]))
+                })
+                .then(a.player.cmp(&b.player))
+        });
+        standings
+    }
+
+    /// The standings as a text table with one column per opponent, in the order of the
+    /// standings. A cell holds "1", "=" or "0" for every game against that opponent.
+    pub fn crosstable(&self) -> String {
+        let standings = self.standings();
+        let name_width = self
+            .players
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Player".len());
+        let cell_width = match self.format {
+            Format::DoubleRoundRobin => 3,
+            _ => 2,
+        };
+
+        let mut table = format!(
+            "{}\n{:>3}  {:<name_width$}  {:>5}  {:>6}  {:>6} ",
+            self.name, "#", "Player", "Pts", "SB", "Buch"
+        );
+        for rank in 1..=standings.len() {
+            table += &format!(" {rank:>cell_width$}");
+        }
+        for (rank, standing) in standings.iter().enumerate() {
+            table += &format!(
+                "\n{:>3}  {:<name_width$}  {:>5.1}  {:>6.2}  {:>6.1} ",
+                rank + 1,
+                self.players[standing.player],
+                standing.points,
+                standing.sonneborn_berger,
+                standing.buchholz
+            );
+            for opponent in &standings {
+                let cell = if opponent.player == standing.player {
+                    "*".to_string()
+                } else {
+                    self.games_of(standing.player)
+                        .filter(|(other, _)| *other == opponent.player)
+                        .map(|(_, points)| match points {
+                            1. => '1',
+                            0. => '0',
+                            _ => '=',
+                        })
+                        .collect()
+                };
+                table += &format!(" {cell:>cell_width$}");
+            }
+        }
+        table.push('\n');
+        table
+    }
+
+    /// Plays the tournament between UCI engines, `engines` in the order of the players.
+    /// All games of a round start from the same opening. Unfinished rounds are completed
+    /// first, engines that disconnect are restarted. `on_game` sees every finished game
+    /// with its round.
+    pub fn play_engines(
+        &mut self,
+        engines: &[EngineConfig],
+        openings: &[Opening],
+        settings: &GameSettings,
+        mut on_game: impl FnMut(usize, &Game),
+    ) -> io::Result<()> {
+        if engines.len() != self.players.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "one engine per player is needed",
+            ));
+        }
+        let mut processes = engines
+            .iter()
+            .map(UciProcess::start)
+            .collect::<io::Result<Vec<_>>>()?;
+        let settings = GameSettings {
+            event: self.name.clone(),
+            ..settings.clone()
+        };
+
+        loop {
+            let number = match self.rounds.iter().position(|round| !round.is_finished()) {
+                Some(number) => number,
+                None if self.pair_next_round().is_some() => self.rounds.len() - 1,
+                None => return Ok(()),
+            };
+            let opening = match openings.len() {
+                0 => Opening::default(),
+                len => openings[number % len].clone(),
+            };
+
+            for board in 0..self.rounds[number].pairings.len() {
+                let pairing = self.rounds[number].pairings[board];
+                if pairing.result != GameResult::Unknown {
+                    continue;
+                }
+                let (white, black) = match pairing.white.cmp(&pairing.black) {
+                    Ordering::Less => {
+                        let (left, right) = processes.split_at_mut(pairing.black);
+                        (&mut left[pairing.white], &mut right[0])
+                    }
+                    _ => {
+                        let (left, right) = processes.split_at_mut(pairing.white);
+                        (&mut right[0], &mut left[pairing.black])
+                    }
+                };
+                let players = [
+                    (white, &engines[pairing.white].time_control),
+                    (black, &engines[pairing.black].time_control),
+                ];
+                let (mut game, termination) = play_game(players, &opening, &settings);
+                game.set_tag("Round", format!("{}.{}", number + 1, board + 1));
+                game.set_tag("White", self.players[pairing.white].as_str());
+                game.set_tag("Black", self.players[pairing.black].as_str());
+
+                if termination == Termination::Disconnect {
+                    let loser = match game.result {
+                        GameResult::WhiteWins => pairing.black,
+                        _ => pairing.white,
+                    };
+                    processes[loser] = UciProcess::start(&engines[loser])?;
+                }
+                self.set_result(number, board, game.result);
+                on_game(number, &game);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        pgn::GameResult,
+        tournament::{Format, Round, Tournament},
+    };
+
+    fn players(count: usize) -> Vec<String> {
+        (0..count).map(|idx| format!("Player {idx}")).collect()
+    }
+
+    /// Plays all rounds, the player with the lower index wins every game.
+    fn play_out(tournament: &mut Tournament) {
+        while let Some(round) = tournament.pair_next_round() {
+            let results = round
+                .pairings
+                .iter()
+                .map(|pairing| {
+                    if pairing.white < pairing.black {
+                        GameResult::WhiteWins
+                    } else {
+                        GameResult::BlackWins
+                    }
+                })
+                .collect::<Vec<_>>();
+            let number = tournament.rounds().len() - 1;
+            for (board, result) in results.into_iter().enumerate() {
+                tournament.set_result(number, board, result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        for count in [4, 5] {
+            let mut tournament = Tournament::new("Test", players(count), Format::RoundRobin);
+            play_out(&mut tournament);
+            assert!(tournament.is_finished());
+
+            let mut games = HashSet::new();
+            let mut whites = vec![0; count];
+            for round in tournament.rounds() {
+                assert_eq!(round.bye.is_some(), count % 2 == 1);
+                for pairing in &round.pairings {
+                    assert!(games.insert((
+                        pairing.white.min(pairing.black),
+                        pairing.white.max(pairing.black)
+                    )));
+                    whites[pairing.white] += 1;
+                }
+            }
+            assert_eq!(games.len(), count * (count - 1) / 2);
+            let (min, max) = (whites.iter().min().unwrap(), whites.iter().max().unwrap());
+            assert!(max - min <= 1, "{whites:?}");
+
+            let standings = tournament.standings();
+            for (rank, standing) in standings.iter().enumerate() {
+                assert_eq!(standing.player, rank);
+                assert_eq!(standing.points, (count - 1 - rank) as f64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_too_few_players() {
+        for format in [
+            Format::RoundRobin,
+            Format::DoubleRoundRobin,
+            Format::Swiss { rounds: 3 },
+        ] {
+            for players in [vec![], vec!["Ann".to_string()]] {
+                let mut tournament = Tournament::new("Empty", players, format);
+                assert_eq!(tournament.round_count(), 0);
+                assert!(tournament.pair_next_round().is_none());
+                assert!(tournament.is_finished());
+            }
+        }
+        let tournament = Tournament::new("Empty", vec![], Format::RoundRobin);
+        assert_eq!(tournament.berger_round(0), Round::default());
+        assert!(tournament.standings().is_empty());
+    }
+
+    #[test]
+    fn test_double_round_robin_and_crosstable() {
+        let mut tournament = Tournament::new(
+            "Club",
+            vec!["Ann".to_string(), "Bob".to_string(), "Cy".to_string()],
+            Format::DoubleRoundRobin,
+        );
+        assert_eq!(tournament.round_count(), 6);
+        while let Some(round) = tournament.pair_next_round() {
+            let boards = round.pairings.len();
+            let number = tournament.rounds().len() - 1;
+            for board in 0..boards {
+                tournament.set_result(number, board, GameResult::Draw);
+            }
+        }
+        let mut colors = HashSet::new();
+        for pairing in tournament.rounds().iter().flat_map(|round| &round.pairings) {
+            assert!(colors.insert((pairing.white, pairing.black)));
+        }
+        assert_eq!(colors.len(), 6);
+
+        let standings = tournament.standings();
+        assert!(standings.iter().all(|standing| standing.points == 2.));
+        assert_eq!(standings[0].sonneborn_berger, 4.);
+        assert_eq!(
+            tournament.crosstable(),
+            "Club\n  \
+               #  Player    Pts      SB    Buch    1   2   3\n  \
+               1  Ann       2.0    4.00     8.0    *  ==  ==\n  \
+               2  Bob       2.0    4.00     8.0   ==   *  ==\n  \
+               3  Cy        2.0    4.00     8.0   ==  ==   *\n"
+        );
+    }
+
+    #[test]
+    fn test_swiss() {
+        let mut tournament = Tournament::new("Swiss", players(7), Format::Swiss { rounds: 3 });
+        play_out(&mut tournament);
+        assert!(tournament.is_finished());
+        assert_eq!(tournament.rounds().len(), 3);
+
+        let mut games = HashSet::new();
+        let mut byes = HashSet::new();
+        for round in tournament.rounds() {
+            assert_eq!(round.pairings.len(), 3);
+            assert!(byes.insert(round.bye.unwrap()));
+            for pairing in &round.pairings {
+                assert!(games.insert((
+                    pairing.white.min(pairing.black),
+                    pairing.white.max(pairing.black)
+                )));
+            }
+        }
+
+        // the winners of round one meet in round two
+        let first = &tournament.rounds()[0];
+        let second = &tournament.rounds()[1];
+        let winners = first
+            .pairings
+            .iter()
+            .map(|pairing| pairing.white.min(pairing.black))
+            .collect::<HashSet<_>>();
+        assert!(second.pairings.iter().any(|pairing| {
+            winners.contains(&pairing.white) && winners.contains(&pairing.black)
+        }));
+
+        let standings = tournament.standings();
+        assert_eq!(standings[0].player, 0);
+        assert_eq!(standings[0].points, 3.);
+        assert!(standings
+            .windows(2)
+            .all(|pair| pair[0].points >= pair[1].points));
+    }
+}
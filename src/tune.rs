@@ -0,0 +1,459 @@
+use std::{
+    f64::consts::LN_10,
+    fmt::{Display, Formatter},
+    thread,
+};
+
+use crate::{
+    eval::{eval_index, game_phase, table_row, EvalParams, EVAL_FIGURES, MAX_PHASE},
+    fen::FenError,
+    pgn::Game,
+    Chess, FigureType, Team,
+};
+
+/// Parameters of one figure type: middlegame and endgame value, then both tables.
+const PARAMS_PER_FIGURE: usize = 2 + 2 * 64;
+pub const PARAM_COUNT: usize = EVAL_FIGURES.len() * PARAMS_PER_FIGURE;
+
+/// Opening moves of a game are left out of the training data, they come from books.
+pub const DEFAULT_SKIP_PLIES: usize = 16;
+
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+
+/// A line (counted from 1) of an EPD file without a valid position, then `fen` holds the
+/// reason, or without a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpdError {
+    pub line: usize,
+    pub fen: Option<FenError>,
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.fen {
+            Some(err) => write!(f, "invalid position in line {}: {err}", self.line),
+            None => write!(f, "no result in line {}", self.line),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// Flattens `params` into the layout the tuner optimises.
+pub fn params_to_vec(params: &EvalParams) -> Vec<f64> {
+    let mut out = Vec::with_capacity(PARAM_COUNT);
+    for (values, tables) in params.values.iter().zip(&params.tables) {
+        out.extend(values.iter().map(|value| *value as f64));
+        for table in tables {
+            out.extend(table.iter().flatten().map(|value| *value as f64));
+        }
+    }
+    out
+}
+
+/// Rounds the flat parameters back to centipawns.
+pub fn params_from_vec(flat: &[f64]) -> EvalParams {
+    let mut params = EvalParams::default();
+    let mut flat = flat.iter().map(|value| value.round() as i32);
+    for (values, tables) in params.values.iter_mut().zip(&mut params.tables) {
+        for value in values.iter_mut() {
+            *value = flat.next().unwrap();
+        }
+        for field in tables.iter_mut().flatten().flatten() {
+            *field = flat.next().unwrap();
+        }
+    }
+    params
+}
+
+fn figure_offset(figure: FigureType) -> Option<usize> {
+    eval_index(figure).map(|idx| idx * PARAMS_PER_FIGURE)
+}
+
+/// A position labelled with the result of its game. The evaluation is linear in the
+/// parameters, so the position is stored as the coefficient of every parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuningPosition {
+    coefficients: Vec<(u16, f32)>,
+    /// Score of white: 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub result: f64,
+}
+
+impl TuningPosition {
+    pub fn new(chess: &Chess, result: f64) -> Self {
+        let phase = game_phase(chess);
+        let middlegame = phase as f32 / MAX_PHASE as f32;
+        let endgame = 1. - middlegame;
+
+        let mut coefficients = Vec::new();
+        for field in chess.fields.iter().flatten() {
+            let Some(figure) = field.figure else {
+                continue;
+            };
+            let Some(offset) = figure_offset(figure.figure) else {
+                continue;
+            };
+            let (row, col) = field.idxs;
+            let square = table_row(figure.team, row) * 8 + col;
+            let sign = match figure.team {
+                Team::White => 1.,
+                Team::Black => -1.,
+            };
+            coefficients.extend([
+                (offset, sign * middlegame),
+                (offset + 1, sign * endgame),
+                (offset + 2 + square, sign * middlegame),
+                (offset + 2 + 64 + square, sign * endgame),
+            ]);
+        }
+
+        // pieces of the same type share their value
+        coefficients.sort_by_key(|(idx, _)| *idx);
+        let mut merged: Vec<(u16, f32)> = Vec::with_capacity(coefficients.len());
+        for (idx, coefficient) in coefficients {
+            match merged.last_mut() {
+                Some((last, sum)) if *last as usize == idx => *sum += coefficient,
+                _ => merged.push((idx as u16, coefficient)),
+            }
+        }
+        merged.retain(|(_, coefficient)| *coefficient != 0.);
+
+        TuningPosition {
+            coefficients: merged,
+            result,
+        }
+    }
+
+    /// Evaluation from white's view with the flat parameters.
+    pub fn evaluate(&self, params: &[f64]) -> f64 {
+        self.coefficients
+            .iter()
+            .map(|(idx, coefficient)| params[*idx as usize] * *coefficient as f64)
+            .sum()
+    }
+}
+
+/// Reads the result of an EPD line, either as a `c9 "1-0"` style opcode or a score in
+/// brackets like `[0.5]`.
+fn parse_result(line: &str) -> Option<f64> {
+    if line.contains("1/2-1/2") {
+        return Some(0.5);
+    }
+    if line.contains("1-0") {
+        return Some(1.);
+    }
+    if line.contains("0-1") {
+        return Some(0.);
+    }
+    let start = line.rfind('[')?;
+    let end = start + line[start..].find(']')?;
+    line[start + 1..end]
+        .trim()
+        .parse()
+        .ok()
+        .filter(|score| (0.0..=1.0).contains(score))
+}
+
+/// Reads result-annotated positions, one per line. The position is given by the first
+/// four FEN fields, empty lines and lines starting with `#` are skipped.
+pub fn parse_epd(text: &str) -> Result<Vec<TuningPosition>, EpdError> {
+    let mut positions = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fen = line
+            .split_whitespace()
+            .take(4)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let chess = Chess::from_fen(&fen).map_err(|err| EpdError {
+            line: idx + 1,
+            fen: Some(err),
+        })?;
+        let result = parse_result(line).ok_or(EpdError {
+            line: idx + 1,
+            fen: None,
+        })?;
+        positions.push(TuningPosition::new(&chess, result));
+    }
+    Ok(positions)
+}
+
+/// Quiet positions of finished games: not in check, and neither the move leading to the
+/// position nor the move played in it captures or promotes. The first `skip_plies` plies
+/// of every game are left out. Games with an unknown result or an illegal move are
+/// skipped.
+pub fn quiet_positions(games: &[Game], skip_plies: usize) -> Vec<TuningPosition> {
+    let mut positions = Vec::new();
+    for game in games {
+        let Some(result) = game.result.score(Team::White) else {
+            continue;
+        };
+        let Ok((mut chess, moves)) = game.replay() else {
+            continue;
+        };
+
+        let mut last_was_quiet = true;
+        for (ply, mv) in moves.into_iter().enumerate() {
+            let quiet = !chess.is_capture(mv) && mv.promotion == FigureType::Empty;
+            if ply >= skip_plies
+                && quiet
+                && last_was_quiet
+                && chess.check_check(chess.player).is_none()
+            {
+                positions.push(TuningPosition::new(&chess, result));
+            }
+            last_was_quiet = quiet;
+            chess.make_move(mv);
+        }
+    }
+    positions
+}
+
+/// Texel's tuning method: minimises the squared error between the game results and the
+/// evaluation mapped to a winning probability. Optimised with Adam on the full data set,
+/// the work is split across threads.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    params: Vec<f64>,
+    /// Scales the evaluation before the logistic function, see `optimize_k`.
+    pub k: f64,
+    pub threads: usize,
+    /// Largest change of a parameter in one epoch, in centipawns.
+    pub learning_rate: f64,
+    momentum: Vec<f64>,
+    velocity: Vec<f64>,
+    epochs: i32,
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<TuningPosition>, params: &EvalParams) -> Self {
+        Tuner {
+            positions,
+            params: params_to_vec(params),
+            k: 1.,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            learning_rate: 1.,
+            momentum: vec![0.; PARAM_COUNT],
+            velocity: vec![0.; PARAM_COUNT],
+            epochs: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn epochs(&self) -> i32 {
+        self.epochs
+    }
+
+    /// The current parameters rounded to centipawns.
+    pub fn params(&self) -> EvalParams {
+        params_from_vec(&self.params)
+    }
+
+    fn sigmoid(&self, eval: f64) -> f64 {
+        1. / (1. + 10f64.powf(-self.k * eval / 400.))
+    }
+
+    /// Runs `map` over equal parts of the positions on all threads and sums up the
+    /// results with `add`.
+    fn map_reduce<T: Send>(
+        &self,
+        map: impl Fn(&[TuningPosition]) -> T + Sync,
+        add: impl Fn(T, T) -> T,
+    ) -> Option<T> {
+        let chunk = self.positions.len().div_ceil(self.threads.max(1)).max(1);
+        thread::scope(|scope| {
+            let handles = self
+                .positions
+                .chunks(chunk)
+                .map(|positions| scope.spawn(|| map(positions)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .reduce(add)
+        })
+    }
+
+    /// Mean squared error of the predicted results.
+    pub fn error(&self) -> f64 {
+        let sum = self.map_reduce(
+            |positions| {
+                positions
+                    .iter()
+                    .map(|position| {
+                        let predicted = self.sigmoid(position.evaluate(&self.params));
+                        (position.result - predicted).powi(2)
+                    })
+                    .sum::<f64>()
+            },
+            |a, b| a + b,
+        );
+        sum.unwrap_or_default() / self.positions.len().max(1) as f64
+    }
+
+    /// Finds the scaling constant that fits the current parameters best, by a golden
+    /// section search. Should run once before tuning.
+    pub fn optimize_k(&mut self) -> f64 {
+        let ratio = (5f64.sqrt() - 1.) / 2.;
+        let (mut low, mut high) = (0.01, 4.);
+        while high - low > 1e-4 {
+            let left = high - ratio * (high - low);
+            let right = low + ratio * (high - low);
+            self.k = left;
+            let left_error = self.error();
+            self.k = right;
+            if left_error < self.error() {
+                high = right;
+            } else {
+                low = left;
+            }
+        }
+        self.k = (low + high) / 2.;
+        self.k
+    }
+
+    fn gradient(&self) -> Vec<f64> {
+        let scale = self.k * LN_10 / 400.;
+        let gradient = self.map_reduce(
+            |positions| {
+                let mut gradient = vec![0.; PARAM_COUNT];
+                for position in positions {
+                    let predicted = self.sigmoid(position.evaluate(&self.params));
+                    let factor =
+                        -2. * (position.result - predicted) * predicted * (1. - predicted) * scale;
+                    for (idx, coefficient) in &position.coefficients {
+                        gradient[*idx as usize] += factor * *coefficient as f64;
+                    }
+                }
+                gradient
+            },
+            |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                a
+            },
+        );
+        let count = self.positions.len().max(1) as f64;
+        gradient
+            .unwrap_or_else(|| vec![0.; PARAM_COUNT])
+            .into_iter()
+            .map(|sum| sum / count)
+            .collect()
+    }
+
+    /// One step over all positions. Returns the error after the step.
+    pub fn epoch(&mut self) -> f64 {
+        let gradient = self.gradient();
+        self.epochs += 1;
+        let momentum_correction = 1. - BETA1.powi(self.epochs);
+        let velocity_correction = 1. - BETA2.powi(self.epochs);
+
+        for (idx, gradient) in gradient.into_iter().enumerate() {
+            self.momentum[idx] = BETA1 * self.momentum[idx] + (1. - BETA1) * gradient;
+            self.velocity[idx] = BETA2 * self.velocity[idx] + (1. - BETA2) * gradient.powi(2);
+            let momentum = self.momentum[idx] / momentum_correction;
+            let velocity = self.velocity[idx] / velocity_correction;
+            self.params[idx] -= self.learning_rate * momentum / (velocity.sqrt() + EPSILON);
+        }
+        self.error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eval::{evaluate, EvalParams},
+        pgn::parse_pgn,
+        tune::{params_from_vec, params_to_vec, parse_epd, quiet_positions, Tuner, TuningPosition},
+        Chess, Team,
+    };
+
+    #[test]
+    fn test_linear_evaluation_matches() {
+        let params = params_to_vec(&EvalParams::default());
+        assert_eq!(params_from_vec(&params), EvalParams::default());
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R b KQ - 0 8",
+            "8/5k2/8/3P4/8/2K5/8/6r1 w - - 0 50",
+        ] {
+            let chess = Chess::from_fen(fen).unwrap();
+            let white = match chess.player {
+                Team::White => evaluate(&chess),
+                Team::Black => -evaluate(&chess),
+            };
+            let linear = TuningPosition::new(&chess, 0.5).evaluate(&params);
+            assert!(
+                (linear - white as f64).abs() < 1.,
+                "{fen}: {linear} vs {white}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_epd() {
+        let positions = parse_epd(
+            "# comment\n\
+             4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";\n\
+             4k3/8/8/8/8/8/8/3QK3 b - - 0 1 [0.5]\n\
+             4k3/8/8/8/8/8/8/3qK3 w - - c9 \"0-1\";\n",
+        )
+        .unwrap();
+        let results = positions
+            .iter()
+            .map(|position| position.result)
+            .collect::<Vec<_>>();
+        assert_eq!(results, [1., 0.5, 0.]);
+
+        assert!(parse_epd("4k3/8/8/8/8/8/8/3QK3 w - -\n").is_err());
+        assert!(parse_epd("4k3/8/8 w - - c9 \"1-0\";\n").is_err());
+    }
+
+    #[test]
+    fn test_quiet_positions() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Bxc6 dxc6 5. O-O f6 1-0").unwrap();
+        let positions = quiet_positions(&games, 0);
+        // the positions before Bxc6, dxc6 and the one after dxc6 are not quiet
+        assert_eq!(positions.len(), 7);
+        assert!(positions.iter().all(|position| position.result == 1.));
+        assert!(quiet_positions(&games, 8).len() == 1);
+    }
+
+    #[test]
+    fn test_tuning_lowers_error() {
+        let positions = parse_epd(
+            "4k3/8/8/8/8/8/8/3QK3 w - - c9 \"1-0\";\n\
+             4k3/8/8/8/8/8/8/3QK3 b - - c9 \"1-0\";\n\
+             4k3/8/8/8/8/8/8/2N1K3 w - - c9 \"1/2-1/2\";\n\
+             2n1k3/8/8/8/8/8/8/4K3 b - - c9 \"1/2-1/2\";\n\
+             3qk3/8/8/8/8/8/8/4K3 w - - c9 \"0-1\";\n",
+        )
+        .unwrap();
+        let mut tuner = Tuner::new(positions, &EvalParams::default());
+        tuner.threads = 2;
+        tuner.learning_rate = 5.;
+        tuner.optimize_k();
+
+        let start = tuner.error();
+        let mut error = start;
+        for _ in 0..20 {
+            error = tuner.epoch();
+        }
+        assert!(error < start, "{error} >= {start}");
+        // a knight alone cannot win
+        assert!(tuner.params().values[1][1] < 320);
+    }
+}
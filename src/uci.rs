@@ -0,0 +1,482 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    book::{BookSelection, DEFAULT_BOOK_DEPTH},
+    engine::Engine,
+    fen::START_FEN,
+    search::{
+        bench, mate_in, Clock, SearchLimits, SearchResult, Skill, DEFAULT_BENCH_DEPTH,
+        DEFAULT_HASH_MB, DEFAULT_MOVE_OVERHEAD, MAX_ELO, MAX_SKILL_LEVEL, MIN_ELO,
+    },
+    Chess, Move, Team,
+};
+
+pub const ENGINE_NAME: &str = concat!("chess-rs ", env!("CARGO_PKG_VERSION"));
+pub const MAX_HASH_MB: usize = 4096;
+pub const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+pub const MAX_THREADS: usize = 256;
+pub const MAX_BOOK_DEPTH: usize = 255;
+pub const MAX_MULTIPV: usize = 256;
+
+/// Receives every line the engine sends to the GUI.
+pub type Output = Arc<dyn Fn(String) + Send + Sync>;
+
+/// "cp 35" or "mate -3", as UCI expects scores.
+pub fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(mate) => format!("mate {mate}"),
+        None => format!("cp {score}"),
+    }
+}
+
+fn format_line(result: &SearchResult, multipv: Option<usize>, score: i32, pv: &[Move]) -> String {
+    let multipv = multipv
+        .map(|idx| format!(" multipv {idx}"))
+        .unwrap_or_default();
+    let pv = pv
+        .iter()
+        .map(|mv| mv.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "info depth {}{multipv} score {} nodes {} nps {} hashfull {} tbhits {} time {} pv {}",
+        result.depth,
+        format_score(score),
+        result.nodes,
+        result.nps(),
+        result.hashfull,
+        result.tbhits,
+        result.time.as_millis(),
+        pv
+    )
+}
+
+pub fn format_info(result: &SearchResult) -> String {
+    format_line(result, None, result.score, &result.pv)
+}
+
+/// One info line per principal variation, numbered with "multipv" if more than one
+/// line was asked for.
+pub fn format_multipv_info(result: &SearchResult, multipv: usize) -> Vec<String> {
+    if multipv <= 1 || result.lines.is_empty() {
+        return vec![format_info(result)];
+    }
+    result
+        .lines
+        .iter()
+        .take(multipv)
+        .enumerate()
+        .map(|(idx, line)| format_line(result, Some(idx + 1), line.score, &line.pv))
+        .collect()
+}
+
+/// Parses the arguments of "go" for a search of `player`.
+pub fn parse_go(args: &[&str], player: Team) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut clock = Clock::default();
+    let mut has_clock = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().and_then(|value| value.parse::<u64>().ok());
+        match (*arg, player) {
+            ("depth", _) => limits.depth = value().map(|depth| depth as usize),
+            ("nodes", _) => limits.nodes = value(),
+            ("movetime", _) => limits.movetime = value().map(Duration::from_millis),
+            ("infinite", _) => limits.infinite = true,
+            ("wtime", Team::White) | ("btime", Team::Black) => {
+                has_clock = true;
+                clock.time = Duration::from_millis(value().unwrap_or_default());
+            }
+            ("winc", Team::White) | ("binc", Team::Black) => {
+                clock.inc = Duration::from_millis(value().unwrap_or_default());
+            }
+            ("movestogo", _) => clock.moves_to_go = value().map(|moves| moves as usize),
+            // the values of the other player's clock
+            ("wtime" | "btime" | "winc" | "binc", _) => {
+                value();
+            }
+            _ => {}
+        }
+    }
+
+    if has_clock {
+        limits.clock = Some(clock);
+    }
+    limits
+}
+
+/// Universal Chess Interface frontend for the `Engine`.
+pub struct Uci {
+    engine: Engine,
+    out: Output,
+    multipv: usize,
+    skill_level: u8,
+    /// Whether "UCI_Elo" is used instead of "Skill Level".
+    limit_strength: bool,
+    elo: u32,
+}
+
+impl Uci {
+    pub fn new(out: impl Fn(String) + Send + Sync + 'static) -> Self {
+        Uci {
+            engine: Engine::new(),
+            out: Arc::new(out),
+            multipv: 1,
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MIN_ELO,
+        }
+    }
+
+    fn send(&self, line: impl Into<String>) {
+        (self.out)(line.into())
+    }
+
+    /// Handles a single command, returns false after "quit".
+    pub fn handle(&mut self, line: &str) -> bool {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "uci" => {
+                self.send(format!("id name {ENGINE_NAME}"));
+                self.send("id author the chess-rs developers");
+                self.send(format!(
+                    "option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}"
+                ));
+                self.send(format!(
+                    "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+                ));
+                self.send(format!(
+                    "option name MultiPV type spin default 1 min 1 max {MAX_MULTIPV}"
+                ));
+                self.send("option name Clear Hash type button");
+                self.send("option name SyzygyPath type string default <empty>");
+                self.send(format!(
+                    "option name Move Overhead type spin default {} min 0 max {MAX_MOVE_OVERHEAD_MS}",
+                    DEFAULT_MOVE_OVERHEAD.as_millis()
+                ));
+                self.send(format!(
+                    "option name Skill Level type spin default {MAX_SKILL_LEVEL} min 0 max {MAX_SKILL_LEVEL}"
+                ));
+                self.send("option name UCI_LimitStrength type check default false");
+                self.send(format!(
+                    "option name UCI_Elo type spin default {MIN_ELO} min {MIN_ELO} max {MAX_ELO}"
+                ));
+                self.send("option name OwnBook type check default false");
+                self.send("option name BookFile type string default <empty>");
+                self.send(format!(
+                    "option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 0 max {MAX_BOOK_DEPTH}"
+                ));
+                self.send(
+                    "option name BookSelection type combo default Weighted var Weighted var Best",
+                );
+                self.send("option name EvalFile type string default <empty>");
+                self.send("uciok");
+            }
+            "isready" => self.send("readyok"),
+            "ucinewgame" => self.engine.new_game(),
+            "position" => self.position(args),
+            "go" => self.go(args),
+            "stop" => self.engine.stop(),
+            "setoption" => self.set_option(args),
+            "bench" => {
+                let depth = args
+                    .first()
+                    .and_then(|depth| depth.parse().ok())
+                    .unwrap_or(DEFAULT_BENCH_DEPTH);
+                let total = bench(depth, |_, _| {});
+                self.send(format!(
+                    "info string bench depth {depth} nodes {} nps {}",
+                    total.nodes,
+                    total.nps()
+                ));
+            }
+            "quit" => {
+                self.engine.stop();
+                return false;
+            }
+            _ => self.send(format!("info string unknown command {command}")),
+        }
+        true
+    }
+
+    fn position(&mut self, args: &[&str]) {
+        self.engine.stop();
+
+        let moves_idx = args.iter().position(|arg| *arg == "moves");
+        let (setup, moves) = match moves_idx {
+            Some(idx) => (&args[..idx], &args[idx + 1..]),
+            None => (args, &[][..]),
+        };
+
+        let chess = match setup.split_first() {
+            Some((&"startpos", _)) => Chess::from_fen(START_FEN),
+            Some((&"fen", fen)) => Chess::from_fen(&fen.join(" ")),
+            _ => {
+                self.send("info string expected startpos or fen");
+                return;
+            }
+        };
+        let mut chess = match chess {
+            Ok(chess) => chess,
+            Err(err) => {
+                self.send(format!("info string invalid fen: {err}"));
+                return;
+            }
+        };
+
+        for uci_move in moves {
+            match chess.parse_uci_move(uci_move) {
+                Some(mv) => chess.make_move(mv),
+                None => {
+                    self.send(format!("info string illegal move {uci_move}"));
+                    break;
+                }
+            }
+        }
+        self.engine.chess = chess;
+    }
+
+    fn go(&mut self, args: &[&str]) {
+        let limits = parse_go(args, self.engine.chess.player);
+
+        let info_out = self.out.clone();
+        let done_out = self.out.clone();
+        let multipv = self.multipv;
+        self.engine.go(
+            limits,
+            move |result| {
+                for line in format_multipv_info(result, multipv) {
+                    info_out(line);
+                }
+            },
+            move |result| match result.best_move {
+                Some(mv) => done_out(format!("bestmove {mv}")),
+                None => done_out("bestmove 0000".to_string()),
+            },
+        );
+    }
+
+    fn update_skill(&mut self) {
+        let skill = if self.limit_strength {
+            Skill::from_elo(self.elo)
+        } else {
+            Skill::new(self.skill_level)
+        };
+        self.engine.set_skill(skill);
+    }
+
+    fn set_option(&mut self, args: &[&str]) {
+        let value_idx = args
+            .iter()
+            .position(|arg| *arg == "value")
+            .unwrap_or(args.len());
+        let name = args
+            .get(1..value_idx)
+            .unwrap_or_default()
+            .join(" ")
+            .to_lowercase();
+        let value = args.get(value_idx + 1..).unwrap_or_default().join(" ");
+
+        match name.as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(size_mb) => self.engine.set_hash_size(size_mb.min(MAX_HASH_MB)),
+                Err(_) => self.send(format!("info string invalid hash size {value}")),
+            },
+            "threads" => match value.parse::<usize>() {
+                Ok(threads) => self.engine.set_threads(threads.min(MAX_THREADS)),
+                Err(_) => self.send(format!("info string invalid thread count {value}")),
+            },
+            "multipv" => match value.parse::<usize>() {
+                Ok(multipv) => {
+                    self.multipv = multipv.clamp(1, MAX_MULTIPV);
+                    self.engine.set_multipv(self.multipv);
+                }
+                Err(_) => self.send(format!("info string invalid multipv {value}")),
+            },
+            "clear hash" => self.engine.clear_hash(),
+            "syzygypath" => match self.engine.set_syzygy_path(&value) {
+                Ok(tables) => self.send(format!("info string found {tables} tablebases")),
+                Err(err) => self.send(format!("info string cannot read tablebases: {err}")),
+            },
+            "move overhead" => match value.parse::<u64>() {
+                Ok(ms) => self
+                    .engine
+                    .set_move_overhead(Duration::from_millis(ms.min(MAX_MOVE_OVERHEAD_MS))),
+                Err(_) => self.send(format!("info string invalid move overhead {value}")),
+            },
+            "skill level" => match value.parse::<u8>() {
+                Ok(level) => {
+                    self.skill_level = level.min(MAX_SKILL_LEVEL);
+                    self.update_skill();
+                }
+                Err(_) => self.send(format!("info string invalid skill level {value}")),
+            },
+            "uci_limitstrength" => match value.to_lowercase().parse::<bool>() {
+                Ok(limit_strength) => {
+                    self.limit_strength = limit_strength;
+                    self.update_skill();
+                }
+                Err(_) => self.send(format!("info string invalid check value {value}")),
+            },
+            "uci_elo" => match value.parse::<u32>() {
+                Ok(elo) => {
+                    self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+                    self.update_skill();
+                }
+                Err(_) => self.send(format!("info string invalid elo {value}")),
+            },
+            "ownbook" => match value.to_lowercase().parse::<bool>() {
+                Ok(own_book) => self.engine.set_own_book(own_book),
+                Err(_) => self.send(format!("info string invalid check value {value}")),
+            },
+            "bookfile" => match self.engine.set_book_file(&value) {
+                Ok(entries) => self.send(format!("info string found {entries} book entries")),
+                Err(err) => self.send(format!("info string cannot read book: {err}")),
+            },
+            "evalfile" => {
+                if let Err(err) = self.engine.set_eval_file(&value) {
+                    self.send(format!(
+                        "info string cannot read evaluation parameters: {err}"
+                    ));
+                }
+            }
+            "bookdepth" => match value.parse::<usize>() {
+                Ok(depth) => self.engine.set_book_depth(depth.min(MAX_BOOK_DEPTH)),
+                Err(_) => self.send(format!("info string invalid book depth {value}")),
+            },
+            "bookselection" => match value.to_lowercase().as_str() {
+                "weighted" => self
+                    .engine
+                    .set_book_selection(BookSelection::WeightedRandom),
+                "best" => self.engine.set_book_selection(BookSelection::BestWeight),
+                _ => self.send(format!("info string invalid book selection {value}")),
+            },
+            _ => self.send(format!("info string unknown option {name}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::{
+        search::{Clock, MATE},
+        uci::{format_score, parse_go, Uci},
+        Team,
+    };
+
+    fn uci() -> (Uci, Arc<Mutex<Vec<String>>>) {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let out = lines.clone();
+        (Uci::new(move |line| out.lock().unwrap().push(line)), lines)
+    }
+
+    fn wait_for_bestmove(lines: &Mutex<Vec<String>>) -> String {
+        loop {
+            if let Some(line) = lines
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|line| line.starts_with("bestmove"))
+            {
+                return line.clone();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_handshake() {
+        let (mut uci, lines) = uci();
+        assert!(uci.handle("uci"));
+        assert!(uci.handle("isready"));
+        let lines = lines.lock().unwrap();
+        assert!(lines[0].starts_with("id name"));
+        assert_eq!(lines[lines.len() - 2], "uciok");
+        assert_eq!(lines[lines.len() - 1], "readyok");
+    }
+
+    #[test]
+    fn test_position_and_go() {
+        let (mut uci, lines) = uci();
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1 moves g8h8");
+        uci.handle("go depth 3");
+        assert_eq!(wait_for_bestmove(&lines), "bestmove a1a8");
+        assert!(lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.starts_with("info depth 1") && line.contains("score mate 1")));
+        assert!(!uci.handle("quit"));
+    }
+
+    #[test]
+    fn test_multipv() {
+        let (mut uci, lines) = uci();
+        uci.handle("setoption name MultiPV value 3");
+        uci.handle("position startpos");
+        uci.handle("go depth 2");
+        wait_for_bestmove(&lines);
+
+        let lines = lines.lock().unwrap();
+        for idx in 1..=3 {
+            assert!(lines
+                .iter()
+                .any(|line| line.starts_with(&format!("info depth 2 multipv {idx} score"))));
+        }
+        assert!(!lines.iter().any(|line| line.contains("multipv 4")));
+    }
+
+    #[test]
+    fn test_stop_infinite() {
+        let (mut uci, lines) = uci();
+        uci.handle("position startpos moves e2e4 e7e5");
+        uci.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.starts_with("bestmove")));
+
+        uci.handle("stop");
+        assert!(wait_for_bestmove(&lines).len() > "bestmove ".len());
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let limits = parse_go(
+            &["wtime", "1000", "btime", "2000", "winc", "10", "binc", "20"],
+            Team::Black,
+        );
+        assert_eq!(
+            limits.clock,
+            Some(Clock {
+                time: Duration::from_millis(2000),
+                inc: Duration::from_millis(20),
+                moves_to_go: None,
+            })
+        );
+
+        let limits = parse_go(&["depth", "5", "nodes", "1000"], Team::White);
+        assert_eq!(limits.depth, Some(5));
+        assert_eq!(limits.nodes, Some(1000));
+        assert_eq!(limits.clock, None);
+    }
+
+    #[test]
+    fn test_format_score() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(MATE - 3), "mate 2");
+        assert_eq!(format_score(-MATE + 2), "mate -1");
+    }
+}
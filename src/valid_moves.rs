@@ -202,7 +202,8 @@ pub fn pawn_moves(
     }
 
     let (row, first_move_row) = match team {
-        Team::White => (org_row - 1, org_row - 2),
+        // the double move row is out of bounds for white pawns on the seventh rank
+        Team::White => (org_row - 1, org_row.wrapping_sub(2)),
         Team::Black => (org_row + 1, org_row + 2),
     };
 
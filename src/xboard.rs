@@ -0,0 +1,369 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    engine::Engine,
+    search::{mate_in, Clock, SearchLimits, SearchResult},
+    uci::{Output, ENGINE_NAME, MAX_THREADS},
+    Chess, Move, Team,
+};
+
+/// XBoard reports mates as 100000 + N for a mate in N moves.
+pub fn format_score(score: i32) -> i32 {
+    match mate_in(score) {
+        Some(mate) if mate > 0 => 100_000 + mate,
+        Some(mate) => -100_000 + mate,
+        None => score,
+    }
+}
+
+/// Thinking output: ply, score, time in centiseconds, nodes and the principal variation.
+pub fn format_thinking(result: &SearchResult) -> String {
+    let pv = result
+        .pv
+        .iter()
+        .map(|mv| mv.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{} {} {} {} {}",
+        result.depth,
+        format_score(result.score),
+        result.time.as_millis() / 10,
+        result.nodes,
+        pv
+    )
+}
+
+/// Parses "40", "5" or "0:30" of the level command into a duration.
+fn parse_base_time(base: &str) -> Option<Duration> {
+    let (minutes, seconds) = match base.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds.parse().ok()?),
+        None => (base.parse::<u64>().ok()?, 0),
+    };
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+/// Chess Engine Communication Protocol (XBoard/WinBoard) frontend for the `Engine`.
+pub struct XBoard {
+    engine: Engine,
+    out: Output,
+    /// The team the engine plays, `None` in force mode.
+    engine_team: Option<Team>,
+    post: bool,
+    depth: Option<usize>,
+    move_time: Option<Duration>,
+    moves_per_session: usize,
+    inc: Duration,
+    time_left: Duration,
+    /// The move of a finished search that is not yet applied to the board.
+    pending: Arc<Mutex<Option<Move>>>,
+}
+
+impl XBoard {
+    pub fn new(out: impl Fn(String) + Send + Sync + 'static) -> Self {
+        XBoard {
+            engine: Engine::new(),
+            out: Arc::new(out),
+            engine_team: Some(Team::Black),
+            post: false,
+            depth: None,
+            move_time: None,
+            moves_per_session: 0,
+            inc: Duration::ZERO,
+            time_left: Duration::from_secs(300),
+            pending: Default::default(),
+        }
+    }
+
+    fn send(&self, line: impl Into<String>) {
+        (self.out)(line.into())
+    }
+
+    /// Applies the move of a finished search to the board.
+    fn sync(&mut self) {
+        if let Some(mv) = self.pending.lock().unwrap().take() {
+            self.engine.chess.make_move(mv);
+        }
+    }
+
+    /// Stops thinking and brings the board up to date.
+    fn stop(&mut self) {
+        self.engine.stop();
+        self.sync();
+    }
+
+    /// Handles a single command, returns false after "quit".
+    pub fn handle(&mut self, line: &str) -> bool {
+        self.sync();
+
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let Some((command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "otim" => {}
+            "protover" => {
+                self.send(format!(
+                    "feature myname=\"{ENGINE_NAME}\" setboard=1 usermove=1 ping=1 smp=1 egt=\"syzygy\" \
+                     sigint=0 sigterm=0 colors=0 analyze=0 done=1"
+                ));
+            }
+            "ping" => self.send(format!("pong {}", args.first().unwrap_or(&""))),
+            "new" => {
+                self.stop();
+                self.engine.new_game();
+                self.engine_team = Some(Team::Black);
+                self.depth = None;
+            }
+            "force" => {
+                self.stop();
+                self.engine_team = None;
+            }
+            "go" => {
+                self.stop();
+                self.engine_team = Some(self.engine.chess.player);
+                self.think();
+            }
+            "?" => self.engine.stop(),
+            "usermove" => match args.first() {
+                Some(usermove) => self.user_move(usermove),
+                None => self.send("Error (missing move): usermove"),
+            },
+            "level" => match args {
+                [moves, base, inc, ..] => {
+                    self.moves_per_session = moves.parse().unwrap_or(0);
+                    self.time_left = parse_base_time(base).unwrap_or(self.time_left);
+                    self.inc = Duration::from_secs_f64(inc.parse().unwrap_or(0.));
+                    self.move_time = None;
+                }
+                _ => self.send(format!("Error (invalid arguments): {line}")),
+            },
+            "st" => {
+                self.move_time = args
+                    .first()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .map(Duration::from_secs_f64);
+            }
+            "sd" => self.depth = args.first().and_then(|depth| depth.parse().ok()),
+            "egtpath" => {
+                if args.first() == Some(&"syzygy") {
+                    self.stop();
+                    if let Err(err) = self.engine.set_syzygy_path(&args[1..].join(" ")) {
+                        self.send(format!("telluser cannot read tablebases: {err}"));
+                    }
+                }
+            }
+            "cores" => {
+                if let Some(cores) = args.first().and_then(|cores| cores.parse::<usize>().ok()) {
+                    self.stop();
+                    self.engine.set_threads(cores.min(MAX_THREADS));
+                }
+            }
+            "time" => {
+                if let Some(centis) = args.first().and_then(|time| time.parse::<u64>().ok()) {
+                    self.time_left = Duration::from_millis(centis * 10);
+                }
+            }
+            "undo" => {
+                self.stop();
+                self.engine.chess.unmake_move();
+            }
+            "remove" => {
+                self.stop();
+                self.engine.chess.unmake_move();
+                self.engine.chess.unmake_move();
+            }
+            "result" => {
+                self.stop();
+                self.engine_team = None;
+            }
+            "setboard" => {
+                self.stop();
+                match Chess::from_fen(&args.join(" ")) {
+                    Ok(chess) => self.engine.chess = chess,
+                    Err(err) => self.send(format!("tellusererror Illegal position: {err}")),
+                }
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "quit" => {
+                self.engine.stop();
+                return false;
+            }
+            _ => self.send(format!("Error (unknown command): {command}")),
+        }
+        true
+    }
+
+    fn user_move(&mut self, usermove: &str) {
+        self.stop();
+        let Some(mv) = self.engine.chess.parse_uci_move(usermove) else {
+            self.send(format!("Illegal move: {usermove}"));
+            return;
+        };
+        self.engine.chess.make_move(mv);
+
+        if self.engine_team == Some(self.engine.chess.player) {
+            self.think();
+        }
+    }
+
+    fn limits(&self) -> SearchLimits {
+        let moves_to_go = (self.moves_per_session > 0).then(|| {
+            let played = (self.engine.chess.fullmove_number - 1) % self.moves_per_session;
+            self.moves_per_session - played
+        });
+
+        SearchLimits {
+            depth: self.depth,
+            movetime: self.move_time,
+            clock: Some(Clock {
+                time: self.time_left,
+                inc: self.inc,
+                moves_to_go,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Starts searching, the move is sent and applied once the search is done.
+    fn think(&mut self) {
+        if self.engine.chess.legal_moves().is_empty() {
+            self.send_result();
+            return;
+        }
+
+        let info_out = self.out.clone();
+        let done_out = self.out.clone();
+        let post = self.post;
+        let pending = self.pending.clone();
+
+        self.engine.go(
+            self.limits(),
+            move |result| {
+                if post {
+                    info_out(format_thinking(result));
+                }
+            },
+            move |result| {
+                if let Some(mv) = result.best_move {
+                    *pending.lock().unwrap() = Some(mv);
+                    done_out(format!("move {mv}"));
+                }
+            },
+        );
+    }
+
+    fn send_result(&mut self) {
+        let chess = &self.engine.chess;
+        let result = match (chess.check_check(chess.player), chess.player) {
+            (Some(()), Team::White) => "0-1 {Black mates}",
+            (Some(()), Team::Black) => "1-0 {White mates}",
+            (None, _) => "1/2-1/2 {Stalemate}",
+        };
+        self.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use crate::search::MATE;
+    use crate::xboard::{format_score, parse_base_time, XBoard};
+
+    fn xboard() -> (XBoard, Arc<Mutex<Vec<String>>>) {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let out = lines.clone();
+        (
+            XBoard::new(move |line| out.lock().unwrap().push(line)),
+            lines,
+        )
+    }
+
+    fn wait_for_move(lines: &Mutex<Vec<String>>) -> String {
+        loop {
+            if let Some(line) = lines
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|line| line.starts_with("move"))
+            {
+                return line.clone();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_features_and_ping() {
+        let (mut xboard, lines) = xboard();
+        xboard.handle("xboard");
+        xboard.handle("protover 2");
+        xboard.handle("ping 7");
+
+        let lines = lines.lock().unwrap();
+        assert!(lines[0].starts_with("feature") && lines[0].contains("setboard=1"));
+        assert!(lines[0].ends_with("done=1"));
+        assert_eq!(lines[1], "pong 7");
+    }
+
+    #[test]
+    fn test_engine_answers_usermove() {
+        let (mut xboard, lines) = xboard();
+        xboard.handle("new");
+        xboard.handle("sd 2");
+        xboard.handle("post");
+        xboard.handle("usermove e2e4");
+
+        let mv = wait_for_move(&lines);
+        assert!(lines.lock().unwrap()[0].starts_with("1 "));
+
+        // the engine move is applied before the next command
+        xboard.handle("force");
+        assert_eq!(xboard.engine.chess.history.len(), 2);
+        assert_eq!(
+            xboard.engine.chess.latest_move.unwrap().to_string(),
+            mv["move ".len()..]
+        );
+
+        xboard.handle("remove");
+        assert!(xboard.engine.chess.history.is_empty());
+    }
+
+    #[test]
+    fn test_setboard_go_and_undo() {
+        let (mut xboard, lines) = xboard();
+        xboard.handle("force");
+        xboard.handle("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        xboard.handle("st 1");
+        xboard.handle("sd 3");
+        xboard.handle("go");
+        assert_eq!(wait_for_move(&lines), "move a1a8");
+
+        xboard.handle("undo");
+        assert!(xboard.engine.chess.history.is_empty());
+        xboard.handle("usermove h2h4");
+        assert!(lines
+            .lock()
+            .unwrap()
+            .contains(&"Illegal move: h2h4".to_string()));
+    }
+
+    #[test]
+    fn test_level_and_scores() {
+        assert_eq!(parse_base_time("5"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_base_time("0:30"), Some(Duration::from_secs(30)));
+        assert_eq!(format_score(MATE - 3), 100_002);
+        assert_eq!(format_score(-MATE + 2), -100_001);
+        assert_eq!(format_score(-40), -40);
+    }
+}
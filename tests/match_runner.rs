@@ -0,0 +1,78 @@
+use chess_rs::{
+    match_runner::{epd_openings, EngineConfig, GameSettings, Match, Termination, TimeControl},
+    pgn::GameResult,
+    tournament::{Format, Tournament},
+    Team,
+};
+
+fn engine(name: &str) -> EngineConfig {
+    EngineConfig {
+        name: name.to_string(),
+        time_control: TimeControl::Depth(2),
+        ..EngineConfig::new(env!("CARGO_BIN_EXE_uci"))
+    }
+}
+
+#[test]
+fn test_match_between_uci_processes() {
+    let mut runner = Match::new(engine("First"), engine("Second"));
+    runner.openings = epd_openings("6k1/5ppp/8/8/8/8/8/R5K1 w - -").unwrap();
+    runner.games = 2;
+
+    let mut games = vec![];
+    let score = runner
+        .run(|played, _| {
+            games.push(played.clone());
+            true
+        })
+        .unwrap();
+
+    // white mates at once in both games, so every engine wins with white
+    assert_eq!((score.wins, score.draws, score.losses), (1, 0, 1));
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[1].first_engine, Team::Black);
+    for played in &games {
+        assert_eq!(played.termination, Termination::Checkmate);
+        assert_eq!(played.game.result, GameResult::WhiteWins);
+        assert_eq!(played.game.moves.len(), 1);
+        assert_eq!(played.game.moves[0].san, "Ra8#");
+    }
+    let pgn = games[1].game.to_pgn();
+    assert!(pgn.contains("[White \"Second\"]"));
+    assert!(pgn.contains("[Round \"2\"]"));
+    assert!(pgn.contains("[Termination \"normal\"]"));
+    assert!(pgn.contains("White mates"));
+}
+
+#[test]
+fn test_engine_round_robin() {
+    let names = ["A", "B", "C"];
+    let engines = names.map(engine);
+    let mut tournament = Tournament::new(
+        "Test",
+        names.map(str::to_string).to_vec(),
+        Format::RoundRobin,
+    );
+    let openings = epd_openings("6k1/5ppp/8/8/8/8/8/R5K1 w - -").unwrap();
+
+    let mut games = vec![];
+    tournament
+        .play_engines(
+            &engines,
+            &openings,
+            &GameSettings::default(),
+            |round, game| games.push((round, game.clone())),
+        )
+        .unwrap();
+
+    assert!(tournament.is_finished());
+    assert_eq!(games.len(), 3);
+    for (round, game) in &games {
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.tag("Round"), Some(format!("{}.1", round + 1).as_str()));
+        assert_eq!(game.tag("Event"), Some("Test"));
+    }
+    let standings = tournament.standings();
+    assert!(standings.iter().all(|standing| standing.points == 1.));
+    assert!(tournament.crosstable().starts_with("Test\n"));
+}